@@ -1,23 +1,84 @@
-
 //+---------------------------------------------------+
-//|  index.js - diese Datei beinhaltet die JavaScript | 
+//|  index.js - diese Datei beinhaltet die JavaScript |
 //|             Schnittstelle für das Rust-Modul      |
 //+---------------------------------------------------+
 
 // laden des Rust-Packetes (../native/index.node)
 const addon = require('../native');
+const EventEmitter = require('events');
+
+// die Klasse Player wird übernommen, mit ihr können mehrere
+// unabhängige Player erzeugt werden (z.B. für eine Vorschau):
+//     new Player({ device, sampleRate, bufferSeconds })
+// alle Angaben sind optional, die Methoden entsprechen
+// den Funktionen in control, info und playlist
+exports.Player = addon.Player;
+
+// devices() - alle Audio-Geräte mit Name und unterstützten Formaten
+exports.devices = addon.list_devices;
+
+// die folgenden Funktionen verwenden einen gemeinsamen
+// Player, der beim ersten Aufruf erzeugt wird
+let player = null;
+
+function get () {
+    if (!player) create();
+    return player;
+}
+
+function create (options) {
+    player = new addon.Player(options);
+    // bereits angemeldete Empfänger erhalten auch
+    // die Ereignisse des neuen Players
+    if (subscribed) forward(player);
+}
+
+// ruft die Methode name des gemeinsamen Players auf
+const call = name => (...args) => get()[name](...args);
 
-// die init() Funktion wird übernommen
-exports.init = addon.init;
+// die init() Funktion erzeugt den gemeinsamen Player
+// (alle Funktionen werfen bei Fehlern eine Exception,
+// z.B. wenn kein Audio-Gerät vorhanden ist)
+exports.init = options => {
+    if (!player) create(options);
+};
+
+// Sitzung des gemeinsamen Players: Playlist, aktueller Titel mit
+// Position, Lautstärke, Wiederholung und zufällige Wiedergabe
+//     save_session(path) - in einer Datei speichern
+//     load_session(path) - laden, die Wiedergabe ist danach pausiert
+//     set_autosave(path) - regelmäßig und beim Beenden speichern
+//                          (mit null nicht mehr)
+exports.save_session = call('save_session');
+exports.load_session = call('load_session');
+exports.set_autosave = call('set_autosave');
 
 // das control Objekt enthält die Funtionen für
-// play(), pause(), skip(), prev() und skip_to()
+// play(), pause(), skip(), prev(), skip_to(), seek()
+// sowie die Einstellungen der Überblendung, Lautstärke
+// und Lautstärkeangleichung
 exports.control = {
-    play: addon.play,
-    pause: addon.pause,
-    skip: addon.skip,
-    prev: addon.prev,
-    skip_to: addon.skip_to
+    play: call('play'),
+    pause: call('pause'),
+    skip: call('skip'),
+    prev: call('prev'),
+    skip_to: call('skip_to'),           // springe zu einem Eintrag (id aus info.playlist())
+    seek: call('seek'),                 // springe zu einer Position (in Sekunden)
+    set_crossfade: call('set_crossfade'), // Länge (in Sekunden) und Verlauf ("linear"/"equal_power")
+    enable_crossfade: call('enable_crossfade'), // Überblendung ein- (true) oder ausschalten (false)
+    set_volume: call('set_volume'),     // Lautstärke setzen (zwischen 0 und 1)
+    get_volume: call('get_volume'),     // momentane Lautstärke
+    mute: call('mute'),                 // stumm (true) oder laut (false) schalten
+    set_replay_gain: call('set_replay_gain'), // Modus ("off"/"track"/"album"), Vorverstärkung (dB), Übersteuerungsschutz
+    set_device: call('set_device'),     // Audio-Gerät wechseln (Name aus info.devices(), ohne Name: Standard-Gerät)
+    set_repeat: call('set_repeat'),     // Wiederholung: "off", "one" (aktueller Titel) oder "all" (Playlist)
+    get_repeat: call('get_repeat'),     // momentane Wiederholung
+    set_shuffle: call('set_shuffle'),   // zufällige Wiedergabe: "off", "track" (einzelne Titel) oder "album" (ganze Alben)
+    get_shuffle: call('get_shuffle'),   // momentane zufällige Wiedergabe
+    close: () => {                      // Player beenden und Audio-Gerät freigeben
+        if (player) player.close();
+        player = null;
+    }
 }
 
 // das info Objekt fasst Funktionen zum aktuellen
@@ -25,21 +86,76 @@ exports.control = {
 exports.info = {
     // curr_info() - informationen über den momentanen Titel
     curr_info: () => ({
-        tag: addon.curr_tag(),          // Infos über Titel, Künstler, Album
-        path: addon.curr_playing(),     // Dateipfad des aktuellen Titels
-        id: addon.curr_id()             // Position in der Playlist
+        tag: get().curr_tag(),          // Infos über Titel, Künstler, Album
+        path: get().curr_playing(),     // Dateipfad des aktuellen Titels
+        id: get().curr_id(),            // id des Eintrags in der Playlist (null ohne Titel)
+        index: get().curr_index()       // Position in der Playlist
     }),
     // changed() - haben sich die Informationen seit dem
     //             letzten Aufruf geändert
-    changed: addon.changed,
+    //             (statt regelmäßig nachzufragen, können
+    //             die Ereignisse in events verwendet werden)
+    changed: call('changed'),
+    // position() und duration() - Position und Länge des
+    //                             momentanen Titels in Sekunden
+    position: call('position'),
+    duration: call('duration'),
+    // devices() - alle Audio-Geräte mit Name und unterstützten Formaten,
+    //             das Gerät des Players ist als aktiv markiert
+    devices: call('list_devices'),
     // playlist() - vollständige Auskunf über alle Titel in der
-    //              Playlist
-    playlist: addon.playlist
+    //              Playlist, jeder Eintrag hat eine eindeutige id
+    //              und den Dateipfad (path). playlist("playback")
+    //              gibt die Titel in der Reihenfolge der Wiedergabe
+    //              an, die bei zufälliger Wiedergabe abweicht
+    playlist: call('playlist')
 }
 
-// Funtionen, die die Playlist verändern
+// Funtionen, die die Playlist verändern, eingefügte
+// Titel erhalten eine id (Rückgabewert)
 exports.playlist = {
-    add_to_queue: addon.add_to_queue,   // Datei hinten an der Playlist anhängen
-    add_next: addon.add_next,           // Datei vorne Anhängen (unterbricht aktuellen Titel)
-    import_m3u: addon.import_m3u        // Playlist importieren
+    add_to_queue: call('add_to_queue'), // Datei hinten an der Playlist anhängen
+    play_next: call('play_next'),       // Datei nach dem aktuellen Titel einfügen
+    play_now: call('play_now'),         // Datei sofort spielen (unterbricht aktuellen Titel)
+    import_playlist: call('import_playlist'), // Playlist (M3U, PLS, XSPF) importieren, gibt alle ids zurück
+    import_m3u: call('import_playlist'),  // wie import_playlist (früherer Name)
+    // export_playlist(path, format, relative) - Playlist in eine Datei schreiben,
+    // format: "m3u", "pls" oder "xspf" (ohne Angabe nach der Endung von path),
+    // relative: Pfade relativ zum Ordner der Playlist (sonst absolut)
+    export_playlist: call('export_playlist'),
+    // Einträge werden über ihre id angesprochen, Positionen zählen alle
+    // Titel der Playlist wie info.curr_info().index. Der aktuelle
+    // Titel wird nur abgebrochen, wenn er entfernt wird
+    remove: call('remove'),             // Eintrag mit einer id entfernen
+    move: call('move_track'),           // Eintrag (id) an eine Position verschieben
+    insert: call('insert'),             // Datei an einer Position einfügen
+    clear: call('clear'),               // Playlist leeren (beendet die Wiedergabe)
+    clear_upcoming: call('clear_upcoming'), // alle folgenden Titel entfernen
+    dedupe: call('dedupe')              // doppelte Einträge entfernen, gibt deren Anzahl zurück
+}
+
+// Ereignisse des gemeinsamen Players, z.B.
+//     events.on('track-started', ({ path, id, tag }) => ...)
+// verfügbar sind: track-started, track-ended ({ path }),
+// queue-changed, playback-state-changed ({ playing }),
+// repeat-changed ({ mode }), shuffle-changed ({ mode }),
+// error ({ message }) und end-of-queue
+exports.events = new EventEmitter();
+
+// das Rust-Modul wird erst benachrichtigt, sobald der
+// erste Empfänger angemeldet wird
+let subscribed = false;
+exports.events.on('newListener', () => {
+    if (subscribed) return;
+    subscribed = true;
+    forward(get());
+});
+
+// leitet die Ereignisse eines Players an events weiter
+function forward (p) {
+    p.subscribe((name, data) => {
+        // ein 'error' ohne Empfänger würde eine Exception auslösen
+        if (name === 'error' && exports.events.listenerCount('error') === 0) return;
+        exports.events.emit(name, data);
+    });
 }
\ No newline at end of file
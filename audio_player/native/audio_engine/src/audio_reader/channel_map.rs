@@ -0,0 +1,149 @@
+//+-------------------------------------------------------------+
+//| channel_map.rs - wandelt Audiodaten mit beliebig vielen     |
+//|          Kanälen in Stereo um. Mono wird auf beide Kanäle   |
+//|          verteilt, Mehrkanalton (z.B. 5.1) wird nach den    |
+//|          üblichen Faktoren (ITU-R BS.775) heruntergemischt. |
+//+-------------------------------------------------------------+
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+//+--------------------------------
+//| enum ChannelOrder
+//|     - Reihenfolge der Kanäle, sie
+//|       unterscheidet sich je nach Format
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelOrder {
+    // Wave und Flac: vorne links, vorne rechts, Mitte, LFE, hinten ...
+    Wave,
+    // Opus (und Vorbis): vorne links, Mitte, vorne rechts, ...
+    Vorbis
+}
+
+// Position eines Lautsprechers
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    BackLeft,
+    BackRight,
+    BackCenter,
+    SideLeft,
+    SideRight
+}
+
+impl Speaker {
+    // Anteil am linken und rechten Kanal beim Heruntermischen,
+    // der Tieftonkanal (LFE) wird weggelassen
+    fn gains(&self) -> (f32, f32) {
+        match self {
+            Speaker::FrontLeft => (1.0, 0.0),
+            Speaker::FrontRight => (0.0, 1.0),
+            Speaker::FrontCenter => (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+            Speaker::Lfe => (0.0, 0.0),
+            Speaker::BackLeft | Speaker::SideLeft => (FRAC_1_SQRT_2, 0.0),
+            Speaker::BackRight | Speaker::SideRight => (0.0, FRAC_1_SQRT_2),
+            Speaker::BackCenter => (0.5, 0.5)
+        }
+    }
+}
+
+// Lautsprecher in der Reihenfolge der Kanäle, None für unbekannte
+// Anordnungen (z.B. mehr als 8 Kanäle)
+fn speakers(channels: usize, order: ChannelOrder) -> Option<Vec<Speaker>> {
+    use Speaker::*;
+
+    let layout = match (order, channels) {
+        (_, 2) => vec![FrontLeft, FrontRight],
+        (ChannelOrder::Wave, 3) => vec![FrontLeft, FrontRight, FrontCenter],
+        (ChannelOrder::Wave, 4) => vec![FrontLeft, FrontRight, BackLeft, BackRight],
+        (ChannelOrder::Wave, 5) => vec![FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight],
+        (ChannelOrder::Wave, 6) => vec![FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight],
+        (ChannelOrder::Wave, 7) => vec![FrontLeft, FrontRight, FrontCenter, Lfe, BackCenter, SideLeft, SideRight],
+        (ChannelOrder::Wave, 8) => vec![FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight, SideLeft, SideRight],
+        (ChannelOrder::Vorbis, 3) => vec![FrontLeft, FrontCenter, FrontRight],
+        (ChannelOrder::Vorbis, 4) => vec![FrontLeft, FrontRight, BackLeft, BackRight],
+        (ChannelOrder::Vorbis, 5) => vec![FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight],
+        (ChannelOrder::Vorbis, 6) => vec![FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight, Lfe],
+        (ChannelOrder::Vorbis, 7) => vec![FrontLeft, FrontCenter, FrontRight, SideLeft, SideRight, BackCenter, Lfe],
+        (ChannelOrder::Vorbis, 8) => vec![FrontLeft, FrontCenter, FrontRight, SideLeft, SideRight, BackLeft, BackRight, Lfe],
+        _ => return None
+    };
+
+    Some(layout)
+}
+
+//+--------------------------------
+//| struct ChannelMap
+//|     - Faktoren, mit denen jeder Kanal
+//|       zum linken und rechten Kanal beiträgt
+
+#[derive(Debug, Clone)]
+pub struct ChannelMap {
+    channels: usize,
+    order: ChannelOrder,
+    gains: Vec<(f32, f32)>
+}
+
+impl ChannelMap {
+    pub fn new(channels: usize, order: ChannelOrder) -> ChannelMap {
+        let channels = channels.max(1);
+
+        let gains = match (channels, speakers(channels, order)) {
+            // Mono wird unverändert auf beide Kanäle verteilt
+            (1, _) => vec![(1.0, 1.0)],
+            (_, Some(layout)) => {
+                let gains : Vec<(f32, f32)> = layout.iter().map(|s| s.gains()).collect();
+
+                // die Summe der Faktoren darf 1 nicht übersteigen,
+                // sonst kann das Ergebnis übersteuern
+                let left : f32 = gains.iter().map(|g| g.0).sum();
+                let right : f32 = gains.iter().map(|g| g.1).sum();
+                let scale = 1.0 / left.max(right).max(1.0);
+
+                gains.iter().map(|(l, r)| (l * scale, r * scale)).collect()
+            },
+            // unbekannte Anordnung: nur die ersten beiden Kanäle verwenden
+            (_, None) => (0 .. channels)
+                .map(|c| match c { 0 => (1.0, 0.0), 1 => (0.0, 1.0), _ => (0.0, 0.0) })
+                .collect()
+        };
+
+        ChannelMap {
+            channels: channels,
+            order: order,
+            gains: gains
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn order(&self) -> ChannelOrder {
+        self.order
+    }
+
+    // wandelt abwechselnd gespeicherte Kanäle (interleaved) in Stereo um
+    pub fn to_stereo(&self, input: &[f32]) -> Vec<f32> {
+        if self.channels == 2 {
+            return Vec::from(input);
+        }
+
+        let mut res = Vec::with_capacity(input.len() / self.channels * 2);
+
+        for frame in input.chunks_exact(self.channels) {
+            let (mut left, mut right) = (0.0, 0.0);
+            for (sample, (l, r)) in frame.iter().zip(self.gains.iter()) {
+                left += sample * l;
+                right += sample * r;
+            }
+            res.push(left);
+            res.push(right);
+        }
+
+        res
+    }
+}
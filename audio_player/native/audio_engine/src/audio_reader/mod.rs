@@ -0,0 +1,1010 @@
+
+//+-------------------------------------------------------------+
+//| mod.rs - enthält die Einbinding der unterschiedlichen       |
+//|          Audiodateiformate. Das Programm unterstützt die    |
+//|          Formate .opus, .mp3, .wav und .flacc.              |
+//|        - organisiert das Resampling. Die Audiodaten können  |
+//|          in unterschiedlichen Sanplingraten vorliegen, die  |
+//|          hier angeglichen werden.                           |
+//|        - wendet die Lautstärkeangleichung (ReplayGain) an.  |
+//|        - wandelt alle Kanäle in Stereo um (s. channel_map). |
+//+-------------------------------------------------------------+
+
+extern crate hound;         // Wave-Dateien (.wav)
+extern crate minimp3;       // Mpeg-Dateien (.mp3)
+extern crate opusfile;      // Opus-Dateien (.opus)
+extern crate claxon;        // Flac-Dateien (.flac)
+extern crate id3;           // Zusatzinformationen für .mp3-Dateien
+
+extern crate mime_detective;    // erkennt Dateitypen
+
+use mime_detective::MimeDetective;
+
+use samplerate::{ConverterType, Samplerate};
+
+use std::{
+    iter::Iterator,
+    fs::File,
+    future::Future,
+    sync::{Arc, atomic::{AtomicU64, Ordering}},
+};
+
+use futures::sink::SinkExt;
+
+use async_trait::async_trait;
+
+use crate::error::{Error, Result};
+
+pub mod buffered_reader;
+pub mod replay_gain;
+pub mod channel_map;
+pub mod prebuffer;
+
+use buffered_reader::{BufferedReader, ReaderTarget};
+use replay_gain::{ReplayGain, GainInfo, R128_OFFSET};
+use channel_map::{ChannelMap, ChannelOrder};
+
+//+------------------------------------------
+//| struct Resampler<T>
+//|     - empfängt samples vom Typ f32, wandelt
+//|       sie in Stereo und so, dass die Sampling-
+//|       rate der des Audio-Geräts entspricht und
+//|       sendet sie an einen BufferedReader
+//|       (s. audio_reader/buffered_reader.rs)
+
+pub struct Resampler<T> {
+    // Samplingrate der erhaltenen samples
+    orig_rate: u32,
+    // Samplingrate des Audio-Geräts
+    dest_rate: u32,
+    // Empfänger der Daten
+    target: BufferedReader<f32, T>,
+    // Converter aus der libsamplerate Library
+    converter: samplerate::Samplerate,
+    // Umwandlung der Kanäle der Datei in Stereo
+    channel_map: ChannelMap,
+    // Fortschritt des Lesevorgangs (s. Progress)
+    progress: Arc<Progress>,
+    // Faktor der Lautstärkeangleichung (s. replay_gain.rs)
+    gain: f32
+}
+
+// markiert den Datentypen als Threadsicher
+unsafe impl<T> std::marker::Send for Resampler <T> {}
+
+impl<T: ReaderTarget<f32>> Resampler<T>
+{
+    // ändert Anzahl und Reihenfolge der Kanäle der folgenden Daten
+    // (bei Dateien, in denen sich diese ändern können)
+    fn set_channels (&mut self, channels: usize, order: ChannelOrder)
+    {
+        if self.channel_map.channels() != channels || self.channel_map.order() != order {
+            self.channel_map = ChannelMap::new(channels, order);
+        }
+    }
+
+    // konvertiert die Daten und schreibt sie in den Buffer, input
+    // enthält die Kanäle der Datei abwechselnd (interleaved)
+    async fn resample (&mut self, input: &[f32]) -> Result<()>
+    {
+        let stereo = self.channel_map.to_stereo(input);
+
+        let mut converted = {
+            if self.dest_rate == self.orig_rate {
+                // keine Konvertierung notwendig
+                stereo
+            } else {
+                // benutze converter für die Konvertierung
+                self.converter.process(&stereo).map_err(Error::decode)?
+            }
+        };
+
+        if self.gain != 1.0 {
+            for sample in converted.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+
+        let _ = self.target.send(converted).await;
+
+        // erst wenn die Daten im Buffer liegen, gelten sie als gelesen
+        self.progress.advance((input.len() / self.channel_map.channels()) as u64);
+        Ok(())
+    }
+
+    // schreibt die restlichen Daten aus dem Converter in den Buffer
+    async fn finish (&mut self) -> Result<()>
+    {
+        if self.dest_rate != self.orig_rate {
+            let mut rest = self.converter.process_last(&[]).map_err(Error::decode)?;
+            for sample in rest.iter_mut() {
+                *sample *= self.gain;
+            }
+            let _ = self.target.send(rest).await;
+        }
+        Ok(())
+    }
+}
+
+// Rust - traits
+// Traits sind die Rust-eigene Methode, um Vererbung
+// zu implementieren. Alle Objekte eines traits enthalten
+// die gleichen Funktionen, auf die in generischer Weise
+// (ohne genaue Angabe des Typs) zugegriffen werden kann.
+
+//+------------------------------------------
+//| struct Progress
+//|     - zählt die bereits gelesenen Samples
+//|       einer Datei. Wird zwischen dem Lese-
+//|       Thread und dem globalen Zustand geteilt,
+//|       um Position und Länge des aktuellen
+//|       Titels abfragen zu können.
+
+pub struct Progress {
+    // Samplingrate der Datei
+    sample_rate: u32,
+    // Anzahl der gelesenen Samples (je Kanal)
+    position: AtomicU64,
+    // Länge der Datei in Samples (je Kanal)
+    length: u64
+}
+
+impl Progress {
+    // beginnt bei Sample start (nach einem Sprung)
+    pub fn new (sample_rate: u32, start: u64, length: u64) -> Progress {
+        Progress {
+            sample_rate: sample_rate,
+            position: AtomicU64::new(start),
+            length: length
+        }
+    }
+
+    fn advance (&self, samples: u64) {
+        self.position.fetch_add(samples, Ordering::Relaxed);
+    }
+
+    // bis wohin die Datei gelesen wurde (in Sekunden)
+    pub fn position (&self) -> f64 {
+        self.position.load(Ordering::Relaxed) as f64 / self.sample_rate as f64
+    }
+
+    // Länge der Datei (in Sekunden)
+    pub fn duration (&self) -> f64 {
+        self.length as f64 / self.sample_rate as f64
+    }
+}
+
+//+-------------------------------------------------
+//| trait AudioProducer
+//|     - vereinigt Funktionen, um mit 
+//|       Audiodateien zu interagieren
+//|     - alle Audiodateien definieren diese
+//|       Funktionen
+
+#[async_trait]
+pub trait AudioProducer : Sized {
+    // Öffnet eine Datei mit dem Dateinamen
+    fn open(file_name: &str) -> Result<Self>;
+    // Gibt die Samplingrate der Datei an
+    fn native_samplerate (&self) -> u32;
+    // Gibt die Anzahl der Kanäle der Datei an
+    fn channels (&self) -> usize;
+    // Gibt die Reihenfolge der Kanäle an (s. channel_map.rs)
+    fn channel_order (&self) -> ChannelOrder;
+    // Liest die Datei vollständig und asynchron in den Resampler ein
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()>;
+    // springt zu einem Sample (je Kanal gezählt) innerhalb der Datei
+    fn seek (&mut self, sample: u64) -> Result<()>;
+    // gibt die Länge der Datei in Samples (je Kanal gezählt) an
+    fn length (&self) -> u64;
+}
+
+// Liest eine Audiodatei von Typ P in einen Buffer von Typ
+// BufferedReader<f32, T>, wobei P den trait AudioProducer
+// und T den Typ ReaderTarget<f32> implementieren muss.
+// Dabei werden die Kanäle in Stereo umgewandelt, die
+// Samplingrate der Datei an die angegebene angepasst
+// und alle Samples werden mit gain multipliziert.
+// Der Fortschritt wird in progress festgehalten.
+pub fn resample_read <'a, T: ReaderTarget<f32> + 'a, P: AudioProducer> (
+    prod: &'a mut P, 
+    target: BufferedReader<f32, T>, 
+    sample_rate: u32,
+    gain: f32,
+    progress: Arc<Progress>) -> impl Future<Output = Result<()>> + 'a
+{
+    // die Daten sind bereits in Stereo umgewandelt (s. channel_map.rs),
+    // ungewöhnliche Samplingraten kann libsamplerate ablehnen
+    let converter = Samplerate::new(
+        ConverterType::SincBestQuality, 
+        prod.native_samplerate(), 
+        sample_rate, 2)
+    .map_err(Error::decode);
+
+    async move {
+        let mut resampler = Resampler {
+            orig_rate: prod.native_samplerate(),
+            dest_rate: sample_rate,
+            target: target,
+            converter: converter?,
+            channel_map: ChannelMap::new(prod.channels(), prod.channel_order()),
+            progress: progress,
+            gain: gain
+        };
+
+        let res = prod.read(&mut resampler).await;
+        // Daten, die noch im Converter liegen, ebenfalls schreiben,
+        // damit das Ende des Titels nicht abgeschnitten wird
+        let rest = resampler.finish().await;
+        res.and(rest)
+    }
+}
+
+//+---------------------------------------------------------------
+//| struct Tags
+//|     - diese Struktur enthält Metadaten über die Datei:
+//|         +- Künstler
+//|         +- Album
+//|         +- Titel
+
+#[derive(Debug, Clone, Default)]
+pub struct Tags {
+    artist: String,
+    album: String,
+    title: String
+}
+
+impl Tags {
+    // leeres Tag, falls keine Informationen angegeben
+    pub fn empty() -> Tags {
+        Tags {
+            artist: String::from(""),
+            album: String::from(""),
+            title: String::from("")
+        }
+    }
+
+    // liest die Tags einer Datei, leer falls sie nicht geöffnet werden kann
+    pub fn read(path: &str) -> Tags {
+        AudioFile::open(path).map(|f| f.tags()).unwrap_or_else(|_| Tags::empty())
+    }
+
+    pub fn artist(&self) -> String { self.artist.clone() }
+    pub fn album(&self) -> String { self.album.clone() }
+    pub fn title(&self) -> String { self.title.clone() }
+}
+
+//+--------------------------------------------------------
+//| trait Tagged
+//|     - kennzeichnet Objekte, die Tags enthalten, wird
+//|       also von allen Audiodateien implementiert
+
+pub trait Tagged {
+    fn tags (&self) -> Tags;
+    // Angaben zur Lautstärkeangleichung (s. replay_gain.rs)
+    fn replay_gain (&self) -> ReplayGain;
+}
+
+//+-------------------------------------------------
+//| enum AudioFile<'a>
+//|     - vereinfacht den Zugriff auf Audiodateien,
+//|       indem automatisch der Dateityp erkannt und
+//|       die richtige Struktur erstellt wird.
+
+pub enum AudioFile<'a> 
+{
+    WavFile(WavReader),
+    Mp3File(Mp3Reader),
+    OpusFile(OpusReader<'a>),
+    FlacFile(FlacReader)
+}
+
+#[async_trait]
+impl AudioProducer for AudioFile<'_> {
+    // open erkennt den Dateityp und erstellt nach Format das richtige Objekt
+    fn open(file_name: &str) -> Result<Self> {
+        // prüfen, ob die Datei existiert und lesbar ist
+        File::open(file_name)?;
+
+        // versuche den Dateityp herauszufinden
+        let unknown = |e| Error::UnsupportedFormat(format!("{} ({:?})", file_name, e));
+        let struppi = MimeDetective::new().map_err(unknown)?;
+        let mime_type = struppi.detect_filepath(file_name).map_err(unknown)?;
+        
+        let mut guessed_type = mime_type.subtype().as_str();
+
+        // falls die Magic Bibiliothek nicht erfolgreich war
+        if mime_type.type_() != mime::AUDIO {
+            // versuche den Dateityp anhand der Dateiendung herauszufinden
+            if file_name.ends_with(".mp3") { guessed_type = "mpeg" }
+            else if file_name.ends_with(".wav") { guessed_type = "wav" }
+            else if file_name.ends_with(".opus") { guessed_type = "ogg" }
+            else if file_name.ends_with(".flac") { guessed_type = "flac" }
+            else {
+                return Err(Error::UnsupportedFormat(
+                    format!("not an audio file: {} ({})", file_name, mime_type)));
+            }
+        }
+
+        match guessed_type {
+            "mpeg" => {
+                let file_reader = Mp3Reader::open(file_name)?;
+                Ok(AudioFile::Mp3File(file_reader))
+            },
+            "wav" | "x-wav" => {
+                let file_reader = <WavReader as AudioProducer>::open(file_name)?;
+                Ok(AudioFile::WavFile(file_reader))
+            },
+            "ogg" => {
+                let file_reader = <OpusReader as AudioProducer>::open(file_name)?;
+                Ok(AudioFile::OpusFile(file_reader))
+            }
+            "flac" | "x-flac" => {
+                let file_reader = FlacReader::open(file_name)?;
+                Ok(AudioFile::FlacFile(file_reader))
+            }
+            other => Err(Error::UnsupportedFormat(format!("{}: audio/{}", file_name, other)))
+        }
+    }
+
+    // die anderen Funktionen werden einfach an das jeweilige Objekt weitergegeben
+    fn native_samplerate (&self) -> u32 {
+        match self {
+            AudioFile::Mp3File(f) => f.native_samplerate(),
+            AudioFile::WavFile(f) => f.native_samplerate(),
+            AudioFile::OpusFile(f) => f.native_samplerate(),
+            AudioFile::FlacFile(f) => f.native_samplerate()
+        }
+    }
+    fn channels (&self) -> usize {
+        match self {
+            AudioFile::Mp3File(f) => f.channels(),
+            AudioFile::WavFile(f) => f.channels(),
+            AudioFile::OpusFile(f) => f.channels(),
+            AudioFile::FlacFile(f) => f.channels()
+        }
+    }
+    fn channel_order (&self) -> ChannelOrder {
+        match self {
+            AudioFile::Mp3File(f) => f.channel_order(),
+            AudioFile::WavFile(f) => f.channel_order(),
+            AudioFile::OpusFile(f) => f.channel_order(),
+            AudioFile::FlacFile(f) => f.channel_order()
+        }
+    }
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()> {
+        match self {
+            AudioFile::Mp3File(f) => f.read(target).await,
+            AudioFile::WavFile(f) => f.read(target).await,
+            AudioFile::OpusFile(f) => f.read(target).await,
+            AudioFile::FlacFile(f) => f.read(target).await
+        }
+    }
+
+    fn seek(&mut self, sample: u64) -> Result<()> {
+        match self {
+            AudioFile::Mp3File(f) =>  f.seek(sample),
+            AudioFile::WavFile(f) =>  AudioProducer::seek(f, sample),
+            AudioFile::OpusFile(f) => f.seek(sample),
+            AudioFile::FlacFile(f) => f.seek(sample)
+        }
+    }
+
+    fn length(&self) -> u64 {
+        match self {
+            AudioFile::Mp3File(f) =>  f.length(),
+            AudioFile::WavFile(f) =>  f.length(),
+            AudioFile::OpusFile(f) => f.length(),
+            AudioFile::FlacFile(f) => f.length()
+        }
+    }
+}
+
+impl Tagged for AudioFile<'_> {
+    fn tags(&self) -> Tags {
+        match self {
+            AudioFile::Mp3File(f) =>  (f as &dyn Tagged).tags(),
+            AudioFile::WavFile(f) =>  (f as &dyn Tagged).tags(),
+            AudioFile::OpusFile(f) => (f as &dyn Tagged).tags(),
+            AudioFile::FlacFile(f) => (f as &dyn Tagged).tags()
+        }
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        match self {
+            AudioFile::Mp3File(f) =>  (f as &dyn Tagged).replay_gain(),
+            AudioFile::WavFile(f) =>  (f as &dyn Tagged).replay_gain(),
+            AudioFile::OpusFile(f) => (f as &dyn Tagged).replay_gain(),
+            AudioFile::FlacFile(f) => (f as &dyn Tagged).replay_gain()
+        }
+    }
+}
+
+// Typ für Wave-Dateien
+pub type WavReader = hound::WavReader<std::io::BufReader<std::fs::File>>;
+
+#[async_trait]
+impl AudioProducer for WavReader {
+    fn open(file_name: &str) -> Result<Self> {
+        hound::WavReader::open(file_name).map_err(Error::decode)
+    }
+
+    fn native_samplerate(&self) -> u32 {
+        self.spec().sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.spec().channels as usize
+    }
+
+    fn channel_order(&self) -> ChannelOrder { ChannelOrder::Wave }
+
+    fn seek(&mut self, sample: u64) -> Result<()> {
+        Ok(hound::WavReader::seek(self, sample as u32)?)
+    }
+
+    fn length(&self) -> u64 {
+        self.duration() as u64
+    }
+
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()> {
+        // eine Sekunde mit allen Kanälen
+        let chunk_len = self.native_samplerate() as usize * self.channels();
+        let spec = self.spec();
+
+        // Samples auf -1 bis 1 normalisieren: Ganzzahlen (8 bis 32 Bit) werden
+        // durch 2^(Bits - 1) geteilt, Fließkommazahlen liegen bereits so vor
+        let mut samples : Box<dyn Iterator<Item = hound::Result<f32>> + Send + '_> = match spec.sample_format {
+            hound::SampleFormat::Float => Box::new(self.samples::<f32>()),
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                Box::new(self.samples::<i32>().map(move |x| x.map(|x| x as f32 * scale)))
+            }
+        };
+
+        loop {
+            let mut chunk = Vec::with_capacity(chunk_len);
+            for sample in samples.by_ref().take(chunk_len) {
+                chunk.push(sample.map_err(Error::decode)?);
+            }
+
+            if chunk.is_empty() { break; }
+            target.resample(&chunk).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tagged for WavReader {
+    fn tags(&self) -> Tags {
+        //TODO: figure out wave
+        Tags::empty()
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        ReplayGain::none()
+    }
+}
+
+// Typ für Mp3-Dateien
+pub struct Mp3Reader {
+    decoder: minimp3::Decoder<std::fs::File>,
+    sample_rate: u32,
+    // Anzahl der Kanäle (1 oder 2)
+    channels: usize,
+    tags: Tags,
+    // Lautstärkeangleichung aus den TXXX-Frames des ID3-Tags
+    replay_gain: ReplayGain,
+    // Dateiname, um die Datei bei Sprüngen erneut zu öffnen
+    path: String,
+    // Informationen aus dem LAME-Header (falls vorhanden)
+    lame: Option<LameInfo>,
+    // Anzahl der bereits dekodierten Samples (je Kanal)
+    position: u64,
+    // Samples vor dieser Position werden verworfen
+    skip_to: u64
+}
+
+//+---------------------------------------------------------------
+//| struct LameInfo
+//|     - Mp3-Encoder fügen am Anfang und Ende jeder Datei Stille
+//|       ein. Damit Titel ohne Lücke ineinander übergehen, speichert
+//|       LAME die Länge dieser Stille im ersten Frame der Datei
+//|       (Xing- bzw. Info-Frame), der selbst keine Audiodaten enthält.
+
+#[derive(Debug, Clone, Copy)]
+struct LameInfo {
+    // Anzahl der Samples (je Kanal) aller Frames,
+    // ohne den Info-Frame und einschließlich der Stille
+    samples: Option<u64>,
+    // Stille am Anfang in Samples (je Kanal)
+    delay: u64,
+    // Stille am Ende in Samples (je Kanal)
+    padding: u64
+}
+
+// Verzögerung des Mp3-Decoders in Samples,
+// die zur Verzögerung des Encoders hinzukommt
+const MP3_DECODER_DELAY : u64 = 528 + 1;
+
+// Größe eines ID3v2-Tags, angegeben in 4 mal 7 Bit
+fn id3v2_size (header: &[u8]) -> usize {
+    if header.len() >= 10 && &header[0..3] == b"ID3" {
+        10 + header[6..10].iter().fold(0, |acc, b| (acc << 7) | (*b & 0x7f) as usize)
+    } else {
+        0
+    }
+}
+
+// liest den Xing/LAME-Header aus dem ersten Frame der Datei
+fn read_lame_info (file_name: &str) -> Option<LameInfo> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut f = File::open(file_name).ok()?;
+    let mut id3_header = [0; 10];
+    f.read_exact(&mut id3_header).ok()?;
+    f.seek(SeekFrom::Start(id3v2_size(&id3_header) as u64)).ok()?;
+
+    let mut frame = [0; 256];
+    f.read_exact(&mut frame).ok()?;
+
+    // der Header muss direkt nach dem ID3-Tag beginnen
+    if frame[0] != 0xff || frame[1] & 0xe0 != 0xe0 { return None; }
+
+    let version = (frame[1] >> 3) & 0x03;
+    let layer = (frame[1] >> 1) & 0x03;
+    let mono = frame[3] >> 6 == 3;
+
+    // der Xing-Header folgt auf die Seiteninformationen des Frames
+    let side_info = match (version == 3, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9
+    };
+    let mut pos = 4 + side_info;
+    let tag = &frame[pos .. pos + 4];
+
+    if layer != 1 || (tag != b"Xing" && tag != b"Info") { return None; }
+
+    let read_u32 = |p: usize| 
+        u32::from_be_bytes([frame[p], frame[p + 1], frame[p + 2], frame[p + 3]]);
+
+    // die Flags geben an, welche Felder vorhanden sind
+    let flags = read_u32(pos + 4);
+    pos += 8;
+
+    let mut samples = None;
+    if flags & 0x1 != 0 {                                             // Anzahl der Frames
+        samples = Some(read_u32(pos) as u64 * mp3_frame_samples(version, layer) as u64);
+        pos += 4;
+    }
+    if flags & 0x2 != 0 { pos += 4; }                                 // Anzahl der Bytes
+    if flags & 0x4 != 0 { pos += 100; }                               // Inhaltsverzeichnis
+    if flags & 0x8 != 0 { pos += 4; }                                 // Qualität
+
+    // die LAME-Erweiterung enthält Anfangs- und Endstille (je 12 Bit)
+    let encoder = &frame[pos .. pos + 4];
+    let (delay, padding) = if encoder == b"LAME" || encoder == b"Lavf" || encoder == b"Lavc" {
+        let d = &frame[pos + 21 .. pos + 24];
+        (
+            ((d[0] as u64) << 4) | (d[1] as u64 >> 4),
+            (((d[1] & 0x0f) as u64) << 8) | d[2] as u64
+        )
+    } else {
+        (0, 0)
+    };
+
+    Some(LameInfo {
+        samples: samples,
+        delay: delay + MP3_DECODER_DELAY,
+        padding: padding.saturating_sub(MP3_DECODER_DELAY)
+    })
+}
+
+impl Mp3Reader {
+    // öffnet den Decoder am Anfang der Datei,
+    // der Info-Frame wird dabei übersprungen
+    fn open_decoder (&mut self) -> Result<()> {
+        let f = File::open(&self.path)?;
+        self.decoder = minimp3::Decoder::new(f);
+        self.position = 0;
+
+        if self.lame.is_some() {
+            self.decoder.next_frame().map_err(Error::decode)?;
+        }
+
+        Ok(())
+    }
+
+    // Position, an der die Audiodaten enden
+    // (die Stille am Ende wird nicht gespielt)
+    fn end (&self) -> Option<u64> {
+        match self.lame {
+            Some(LameInfo { samples: Some(samples), delay, padding }) => 
+                Some(samples.saturating_sub(padding).max(delay)),
+            _ => None
+        }
+    }
+}
+
+// Anzahl der Samples (je Kanal) in einem Frame
+// der angegebenen Mpeg-Version und Layer
+fn mp3_frame_samples (version: u8, layer: u8) -> usize {
+    match (version, layer) {
+        (_, 3) => 384,      // Layer I
+        (3, _) => 1152,     // Mpeg 1, Layer II und III
+        (_, 2) => 1152,     // Mpeg 2 und 2.5, Layer II
+        _ => 576            // Mpeg 2 und 2.5, Layer III
+    }
+}
+
+// Bestimmt die Länge einer Mp3-Datei in Samples (je Kanal).
+// Da Mp3-Dateien keine Längenangabe enthalten müssen, werden
+// die Header aller Frames gelesen (ohne diese zu dekodieren).
+fn mp3_length (file_name: &str) -> Option<u64> {
+    // Bitraten in kbit/s, nach Mpeg-Version und Layer
+    const BITRATES : [[u32; 15]; 5] = [
+        [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448], // Mpeg 1, Layer I
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],    // Mpeg 1, Layer II
+        [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],     // Mpeg 1, Layer III
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],    // Mpeg 2, Layer I
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160]          // Mpeg 2, Layer II und III
+    ];
+    // Samplingraten nach Mpeg-Version (1, 2, 2.5)
+    const SAMPLE_RATES : [[u32; 3]; 3] = [
+        [44100, 48000, 32000],
+        [22050, 24000, 16000],
+        [11025, 12000, 8000]
+    ];
+
+    let data = std::fs::read(file_name).ok()?;
+
+    // ID3v2-Tag am Dateianfang überspringen
+    let mut pos = id3v2_size(&data);
+
+    let mut samples = 0;
+    let mut found = false;
+
+    while pos + 4 <= data.len() {
+        let header = &data[pos .. pos + 4];
+
+        // jeder Frame beginnt mit 11 gesetzten Bits
+        if header[0] != 0xff || header[1] & 0xe0 != 0xe0 { pos += 1; continue; }
+
+        let version = (header[1] >> 3) & 0x03;  // 3: Mpeg 1, 2: Mpeg 2, 0: Mpeg 2.5
+        let layer = (header[1] >> 1) & 0x03;    // 3: Layer I, 2: Layer II, 1: Layer III
+        let bitrate_index = (header[2] >> 4) as usize;
+        let rate_index = ((header[2] >> 2) & 0x03) as usize;
+        let padding = ((header[2] >> 1) & 0x01) as usize;
+
+        // ungültige Header überspringen
+        if version == 1 || layer == 0 || bitrate_index == 0 
+            || bitrate_index == 15 || rate_index == 3 
+        { 
+            pos += 1; 
+            continue; 
+        }
+
+        let bitrate = match (version, layer) {
+            (3, l) => BITRATES[3 - l as usize][bitrate_index],
+            (_, 3) => BITRATES[3][bitrate_index],
+            _ => BITRATES[4][bitrate_index]
+        } as usize * 1000;
+
+        let sample_rate = match version {
+            3 => SAMPLE_RATES[0][rate_index],
+            2 => SAMPLE_RATES[1][rate_index],
+            _ => SAMPLE_RATES[2][rate_index]
+        } as usize;
+
+        let frame_samples = mp3_frame_samples(version, layer);
+
+        // Länge des Frames in Bytes (bei Layer I in Slots zu 4 Bytes)
+        let frame_len = if layer == 3 {
+            (frame_samples / 32 * bitrate / sample_rate + padding) * 4
+        } else {
+            frame_samples / 8 * bitrate / sample_rate + padding
+        };
+
+        samples += frame_samples as u64;
+        found = true;
+        pos += frame_len;
+    }
+
+    if found { Some(samples) } else { None }
+}
+
+#[async_trait]
+impl AudioProducer for Mp3Reader {
+    fn open(file_name: &str) -> Result<Self> {
+        // Dateien ohne ID3-Tag können trotzdem abgespielt werden
+        let (tags, replay_gain) = match id3::Tag::read_from_path(file_name) {
+            Ok(tag) => (
+                Tags {
+                    artist: tag.artist().unwrap_or("").to_owned(),
+                    album: tag.album().unwrap_or("").to_owned(),
+                    title: tag.title().unwrap_or("").to_owned()
+                },
+                ReplayGain::from_fields(|name| {
+                    tag.extended_texts()
+                        .find(|t| t.description.eq_ignore_ascii_case(name))
+                        .map(|t| t.value.clone())
+                })
+            ),
+            Err(_) => (Tags::empty(), ReplayGain::none())
+        };
+
+        let f = File::open(file_name)?;
+        let mut dec = minimp3::Decoder::new(f);
+        let first = dec.next_frame().map_err(Error::decode)?;
+        let (r, channels) = (first.sample_rate, first.channels);
+
+        let lame = read_lame_info(file_name);
+
+        let mut reader = Mp3Reader { 
+            decoder: dec,
+            sample_rate: r as u32,
+            channels: channels,
+            tags: tags,
+            replay_gain: replay_gain,
+            path: file_name.to_owned(),
+            lame: lame,
+            position: 0,
+            skip_to: lame.map(|l| l.delay).unwrap_or(0)
+        };
+
+        // der erste Frame wurde bereits gelesen, er darf
+        // nur übersprungen werden, falls er keine Audiodaten enthält
+        reader.open_decoder()?;
+        Ok(reader)
+    }
+    fn native_samplerate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize { self.channels }
+
+    fn channel_order(&self) -> ChannelOrder { ChannelOrder::Wave }
+
+    fn seek(&mut self, sample: u64) -> Result<()> {
+        let delay = self.lame.map(|l| l.delay).unwrap_or(0);
+        let target = sample + delay;
+
+        // zurückspringen ist nur durch erneutes Öffnen möglich
+        if target < self.position {
+            self.open_decoder()?;
+        }
+
+        // die Frames bis zur Position werden erst beim Lesen dekodiert und
+        // auf das Sample genau verworfen (s. skip_to), da ihre Länge erst
+        // nach dem Dekodieren feststeht
+        self.skip_to = target;
+        Ok(())
+    }
+
+    fn length(&self) -> u64 { 
+        match (self.end(), self.lame) {
+            (Some(end), Some(lame)) => end - lame.delay,
+            _ => mp3_length(&self.path).unwrap_or(0)
+        }
+    }
+
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()> {
+        let end = self.end().unwrap_or(u64::MAX);
+
+        loop {
+            let n = match self.decoder.next_frame() {
+                Ok(n) => n,
+                Err(minimp3::Error::Eof) => break,
+                Err(e) => return Err(Error::decode(e))
+            };
+
+            let begin = self.position;
+            self.position += (n.data.len() / n.channels) as u64;
+
+            // Stille am Anfang und Ende abschneiden
+            let from = (self.skip_to.max(begin) - begin) as usize * n.channels;
+            let to = (end.min(self.position).max(begin) - begin) as usize * n.channels;
+
+            if from >= to { 
+                if self.position >= end { break; }
+                continue; 
+            }
+
+            let curr_samples : Vec<f32> 
+                = n.data[from .. to].iter()
+                    .map(|x| *x as f32 / 32768.0)
+                    .collect();
+            
+            // die Anzahl der Kanäle kann sich von Frame zu Frame ändern
+            target.set_channels(n.channels, ChannelOrder::Wave);
+            target.resample(curr_samples.as_slice()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tagged for Mp3Reader {
+    fn tags(&self) -> Tags {
+        self.tags.clone()
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        self.replay_gain
+    }
+}
+
+// Typ für Opus-Dateien
+pub type OpusReader<'a> = opusfile::Opusfile<'a>;
+
+#[async_trait]
+impl AudioProducer for OpusReader<'_> {
+    fn open(file_name: &str) -> Result<Self> {
+        opusfile::Opusfile::open(file_name).map_err(Error::decode)
+    }
+
+    fn native_samplerate(&self) -> u32 { 48000 }
+
+    fn channels(&self) -> usize { self.channel_count(-1) }
+
+    fn channel_order(&self) -> ChannelOrder { ChannelOrder::Vorbis }
+
+    fn seek(&mut self, sample: u64) -> Result<()> {
+        self.pcm_seek(sample as i64).map_err(Error::decode)
+    }
+
+    fn length(&self) -> u64 { 
+        self.pcm_total().unwrap_or(0) as u64
+    }
+
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()> {
+        // Platz für 120 ms bei bis zu 8 Kanälen
+        let mut buf = vec![0.0; 5760 * 8];
+        loop {
+            let (n, channels) = self.read_float(&mut buf).map_err(Error::decode)?;
+            if n == 0 { break; }
+
+            // verkettete Dateien können die Anzahl der Kanäle ändern
+            target.set_channels(channels, ChannelOrder::Vorbis);
+            let samples = buf.split_at(n * channels).0;
+            target.resample(samples).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tagged for OpusReader<'_> {
+    fn tags(&self) -> Tags {
+        match opusfile::Opusfile::tags(self) {
+            Some(tags) => Tags {
+                artist: tags.get_tag("artist").join(", ").to_owned(),
+                album: tags.get_tag("album").join(" ").to_owned(),
+                title: tags.get_tag("title").join(" ").to_owned()
+            },
+            None => Tags::empty()
+        }
+    }
+
+    // die R128-Angaben sind in 1/256 dB gespeichert und enthalten
+    // keinen höchsten Pegel, die Anpassung aus dem Header wendet
+    // libopusfile bereits selbst an
+    fn replay_gain(&self) -> ReplayGain {
+        let info = |gain: i32| GainInfo {
+            gain: gain as f32 / 256.0 + R128_OFFSET,
+            peak: None
+        };
+
+        ReplayGain {
+            track: self.track_gain().map(info),
+            album: self.album_gain().map(info)
+        }
+    }
+}
+
+// Typ für Flac-Dateien
+pub struct FlacReader {
+    reader: claxon::FlacReader<std::fs::File>,
+    // Dateiname, um die Datei bei Sprüngen erneut zu öffnen
+    path: String,
+    // Anzahl der bereits dekodierten Samples (je Kanal)
+    position: u64,
+    // Samples vor dieser Position werden verworfen (nach einem Sprung)
+    skip_to: u64
+}
+
+#[async_trait]
+impl AudioProducer for FlacReader {
+    fn open(file_name: &str) -> Result<Self> {
+        let reader = claxon::FlacReader::open(file_name).map_err(Error::decode)?;
+
+        Ok(FlacReader {
+            reader: reader,
+            path: file_name.to_owned(),
+            position: 0,
+            skip_to: 0
+        })
+    }
+
+    fn native_samplerate (&self) -> u32 {
+        self.reader.streaminfo().sample_rate
+    }
+
+    fn channels (&self) -> usize {
+        self.reader.streaminfo().channels as usize
+    }
+
+    fn channel_order (&self) -> ChannelOrder { ChannelOrder::Wave }
+
+    fn seek(&mut self, sample: u64) -> Result<()> {
+        // zurückspringen ist nur durch erneutes Öffnen möglich
+        if sample < self.position {
+            self.reader = claxon::FlacReader::open(&self.path).map_err(Error::decode)?;
+            self.position = 0;
+        }
+
+        // die Blöcke bis zur Position werden erst beim Lesen übersprungen,
+        // im Block mit der Position wird auf das Sample genau abgeschnitten
+        self.skip_to = sample;
+        Ok(())
+    }
+
+    fn length(&self) -> u64 { 
+        self.reader.streaminfo().samples.unwrap_or(0)
+    }
+
+    async fn read <T: ReaderTarget<f32>> (&mut self, target: &mut Resampler<T>) -> Result<()> {
+        // Samples auf -1 bis 1 normalisieren
+        let bits = self.reader.streaminfo().bits_per_sample;
+        let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+
+        let mut blocks = self.reader.blocks();
+        let mut buffer = Vec::new();
+
+        while let Some(chunk) = blocks.read_next_or_eof(buffer).map_err(Error::decode)? {
+            let begin = self.position;
+            self.position += chunk.duration() as u64;
+
+            // Blöcke vor der Startposition überspringen (s. seek)
+            if self.position <= self.skip_to {
+                buffer = chunk.into_buffer();
+                continue;
+            }
+            let from = (self.skip_to.max(begin) - begin) as u32;
+
+            // claxon speichert die Kanäle nacheinander,
+            // der Resampler erwartet sie abwechselnd
+            let mut samples = Vec::with_capacity(chunk.len() as usize);
+            for i in from .. chunk.duration() {
+                for ch in 0 .. chunk.channels() {
+                    samples.push(chunk.sample(ch, i) as f32 * scale);
+                }
+            }
+
+            buffer = chunk.into_buffer();
+            target.resample(&samples).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tagged for FlacReader {
+    fn tags(&self) -> Tags {
+        let artists : Vec<&str> = self.reader.get_tag("artist").collect();
+        let album : Vec<&str> = self.reader.get_tag("album").collect();
+        let title : Vec<&str> = self.reader.get_tag("title").collect();
+
+        Tags {
+            artist: artists.join(", ").to_owned(),
+            album: album.join(" ").to_owned(),
+            title: title.join(" ").to_owned()
+        }
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        ReplayGain::from_fields(|name| self.reader.get_tag(name).next().map(|v| v.to_owned()))
+    }
+}
@@ -0,0 +1,115 @@
+//+-------------------------------------------------------------+
+//| prebuffer.rs - Vorauslesen des folgenden Titels. Während    |
+//|          der vorherige Titel noch spielt, wird der Anfang   |
+//|          bereits dekodiert und zwischengespeichert. Beginnt |
+//|          der Titel, gehen die Daten an den eigentlichen     |
+//|          Empfänger (s. player::Prepared).                   |
+//+-------------------------------------------------------------+
+
+use std::{
+    pin::Pin,
+    future::Future,
+    task::{Context, Poll},
+    collections::VecDeque,
+    sync::{Arc, Mutex}
+};
+
+use futures::task::AtomicWaker;
+
+use super::buffered_reader::ReaderTarget;
+
+//+--------------------------------
+//| struct Prebuffer<T>
+//|     - nimmt bis zu limit Samples auf, bis
+//|       der Empfänger vom Typ T feststeht,
+//|       danach werden alle Daten an ihn
+//|       weitergegeben
+
+pub struct Prebuffer<T> {
+    // vorab dekodierte Samples (Stereo, Samplingrate des Audio-Geräts)
+    samples: VecDeque<f32>,
+    // so viele Samples werden höchstens vorab aufgenommen
+    limit: usize,
+    // Empfänger der Daten, sobald der Titel beginnt
+    target: Option<Arc<Mutex<T>>>
+}
+
+impl<T: ReaderTarget<f32>> Prebuffer<T> {
+    pub fn new(limit: usize) -> Prebuffer<T> {
+        Prebuffer {
+            samples: VecDeque::with_capacity(limit),
+            limit: limit,
+            target: None
+        }
+    }
+
+    // ab jetzt gehen alle Daten an target,
+    // zuerst die bereits aufgenommenen
+    pub fn connect(&mut self, target: Arc<Mutex<T>>) {
+        self.target = Some(target);
+    }
+}
+
+impl<T: ReaderTarget<f32>> ReaderTarget<f32> for Prebuffer<T> {
+    fn read_iter <I: Iterator<Item = f32>> (&mut self, iter: &mut I) -> usize {
+        let target = match &self.target {
+            Some(target) => target,
+            None => {
+                let free = self.limit.saturating_sub(self.samples.len());
+                let before = self.samples.len();
+                self.samples.extend(iter.take(free));
+                return self.samples.len() - before;
+            }
+        };
+
+        let mut target = target.lock().unwrap();
+
+        // neue Daten erst, wenn die vorab aufgenommenen weitergegeben sind
+        if !self.samples.is_empty() {
+            let sent = target.read_iter(&mut self.samples.iter().copied());
+            self.samples.drain(.. sent);
+            if !self.samples.is_empty() { return 0; }
+        }
+
+        target.read_iter(iter)
+    }
+
+    fn is_full(&self) -> bool {
+        match &self.target {
+            Some(target) => target.lock().unwrap().is_full(),
+            None => self.samples.len() >= self.limit
+        }
+    }
+}
+
+//+--------------------------------
+//| struct WakeOn<F>
+//|     - ein vorab begonnener Lesevorgang hat
+//|       seinen eigenen Waker (s. BufferedReader).
+//|       Spielt der Titel, wird er wie jeder
+//|       andere über den Waker geweckt, den der
+//|       Audio-Thread benachrichtigt.
+
+pub struct WakeOn<F> {
+    future: F,
+    waker: Arc<AtomicWaker>
+}
+
+impl<F> WakeOn<F> {
+    pub fn new(future: F, waker: Arc<AtomicWaker>) -> WakeOn<F> {
+        WakeOn {
+            future: future,
+            waker: waker
+        }
+    }
+}
+
+impl<F: Future + Unpin> Future for WakeOn<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // vor dem Lesen anmelden, damit keine Benachrichtigung verloren geht
+        self.waker.register(cx.waker());
+        Pin::new(&mut self.future).poll(cx)
+    }
+}
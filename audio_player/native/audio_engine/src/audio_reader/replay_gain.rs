@@ -0,0 +1,147 @@
+//+-------------------------------------------------------------+
+//| replay_gain.rs - Lautstärkeangleichung zwischen Titeln.     |
+//|          Die Dateien speichern, um wie viel dB sie leiser   |
+//|          oder lauter gespielt werden müssen (ReplayGain     |
+//|          bzw. R128 bei Opus), sowie den höchsten Pegel,     |
+//|          um Übersteuerung zu vermeiden.                     |
+//+-------------------------------------------------------------+
+
+// R128-Werte beziehen sich auf -23 LUFS, ReplayGain auf etwa -18 LUFS,
+// damit beide gleich laut klingen, wird diese Differenz addiert
+pub const R128_OFFSET : f32 = 5.0;
+
+//+--------------------------------
+//| struct GainInfo
+//|     - Lautstärkeanpassung eines Titels
+//|       oder Albums
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainInfo {
+    // Anpassung in dB
+    pub gain: f32,
+    // höchster Pegel (1.0 entspricht Vollaussteuerung)
+    pub peak: Option<f32>
+}
+
+//+--------------------------------
+//| struct ReplayGain
+//|     - Lautstärkeanpassungen aus den
+//|       Metadaten einer Datei
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplayGain {
+    pub track: Option<GainInfo>,
+    pub album: Option<GainInfo>
+}
+
+impl ReplayGain {
+    // keine Angaben vorhanden
+    pub fn none() -> ReplayGain {
+        ReplayGain { track: None, album: None }
+    }
+
+    // liest die Felder REPLAYGAIN_TRACK_GAIN, REPLAYGAIN_ALBUM_PEAK, ...
+    // get gibt den Wert eines Feldes zurück (Groß-/Kleinschreibung egal)
+    pub fn from_fields<F: Fn(&str) -> Option<String>> (get: F) -> ReplayGain {
+        let read = |kind: &str| {
+            let gain = get(&format!("REPLAYGAIN_{}_GAIN", kind)).and_then(|g| parse_number(&g))?;
+            let peak = get(&format!("REPLAYGAIN_{}_PEAK", kind)).and_then(|p| parse_number(&p));
+
+            Some(GainInfo { gain: gain, peak: peak })
+        };
+
+        ReplayGain {
+            track: read("TRACK"),
+            album: read("ALBUM")
+        }
+    }
+}
+
+// liest Angaben wie "-6.48 dB" oder "0.988553"
+fn parse_number (value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = if value.len() >= 2 && value[value.len() - 2 ..].eq_ignore_ascii_case("db") {
+        &value[.. value.len() - 2]
+    } else {
+        value
+    };
+
+    value.trim().parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+//+--------------------------------
+//| enum GainMode
+//|     - welche Anpassung verwendet wird
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GainMode {
+    // keine Anpassung
+    Off,
+    // jeder Titel gleich laut
+    Track,
+    // Lautstärkeunterschiede innerhalb eines Albums bleiben erhalten
+    Album
+}
+
+impl GainMode {
+    // Name, wie er aus JavaScript übergeben wird
+    pub fn from_name(name: &str) -> Option<GainMode> {
+        match name {
+            "off" => Some(GainMode::Off),
+            "track" => Some(GainMode::Track),
+            "album" => Some(GainMode::Album),
+            _ => None
+        }
+    }
+}
+
+//+--------------------------------
+//| struct GainSettings
+//|     - Einstellungen der Lautstärkeangleichung
+
+#[derive(Debug, Clone, Copy)]
+pub struct GainSettings {
+    pub mode: GainMode,
+    // zusätzliche Anpassung in dB für Titel mit Angaben
+    pub preamp: f32,
+    // Verstärkung begrenzen, sodass der höchste Pegel nicht übersteuert
+    pub prevent_clipping: bool
+}
+
+impl Default for GainSettings {
+    fn default() -> GainSettings {
+        GainSettings::new()
+    }
+}
+
+impl GainSettings {
+    pub fn new() -> GainSettings {
+        GainSettings {
+            mode: GainMode::Off,
+            preamp: 0.0,
+            prevent_clipping: true
+        }
+    }
+
+    // Faktor, mit dem die Samples eines Titels multipliziert werden
+    pub fn factor(&self, rg: &ReplayGain) -> f32 {
+        // fehlt die gewünschte Angabe, wird die andere verwendet
+        let info = match self.mode {
+            GainMode::Off => None,
+            GainMode::Track => rg.track.or(rg.album),
+            GainMode::Album => rg.album.or(rg.track)
+        };
+
+        match info {
+            None => 1.0,
+            Some(GainInfo { gain, peak }) => {
+                let factor = 10f32.powf((gain + self.preamp) / 20.0);
+
+                match peak {
+                    Some(peak) if self.prevent_clipping && peak > 0.0 => factor.min(1.0 / peak),
+                    _ => factor
+                }
+            }
+        }
+    }
+}
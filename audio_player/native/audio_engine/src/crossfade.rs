@@ -0,0 +1,314 @@
+//+-------------------------------------------------------------+
+//| crossfade.rs - Überblendung zwischen zwei Titeln. Das Ende  |
+//|          des ausgehenden Titels wird zurückgehalten und mit |
+//|          dem Anfang des folgenden Titels gemischt.          |
+//|        - kurzes Aus- und Einblenden beim Überspringen von   |
+//|          Titeln, damit kein Knacken zu hören ist.           |
+//+-------------------------------------------------------------+
+
+use std::collections::VecDeque;
+
+use ringbuf::{Producer, Consumer};
+
+use crate::audio_reader::buffered_reader::ReaderTarget;
+
+//+--------------------------------
+//| enum Curve
+//|     - Verlauf der Lautstärke
+//|       während der Überblendung
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    // Lautstärken ändern sich gleichmäßig
+    Linear,
+    // die Summe der Leistung bleibt gleich, dadurch
+    // entsteht in der Mitte kein Lautstärkeeinbruch
+    EqualPower
+}
+
+impl Curve {
+    // Name, wie er aus JavaScript übergeben wird
+    pub fn from_name(name: &str) -> Option<Curve> {
+        match name {
+            "linear" => Some(Curve::Linear),
+            "equal_power" => Some(Curve::EqualPower),
+            _ => None
+        }
+    }
+
+    // Lautstärke von ausgehendem und eingehendem Titel,
+    // progress läuft dabei von 0 bis 1
+    fn gains(&self, progress: f32) -> (f32, f32) {
+        match self {
+            Curve::Linear => (1.0 - progress, progress),
+            Curve::EqualPower => {
+                let angle = progress * std::f32::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+        }
+    }
+}
+
+//+--------------------------------
+//| struct Crossfader
+//|     - schreibt die Daten des Lese-Threads in
+//|       den Ringbuffer. Ist die Überblendung
+//|       aktiviert, werden die letzten Sekunden
+//|       jedes Titels zurückgehalten, bis klar ist,
+//|       ob ein weiterer Titel folgt.
+
+pub struct Crossfader {
+    // Ringbuffer zum Audio-Thread
+    prod: Producer<f32>,
+    // Anzahl aller jemals in den Ringbuffer geschriebenen Samples
+    written: u64,
+    // Samplingrate des Audio-Geräts
+    sample_rate: u32,
+    // Überblendung aktiviert
+    enabled: bool,
+    // Länge der Überblendung in Sekunden
+    seconds: f32,
+    // Verlauf der Überblendung
+    curve: Curve,
+    // zurückgehaltene Samples des aktuellen Titels
+    delay: VecDeque<f32>,
+    // Ende des vorherigen Titels, wird mit dem Anfang gemischt
+    tail: VecDeque<f32>,
+    // ursprüngliche Länge von tail
+    tail_len: usize
+}
+
+impl Crossfader {
+    pub fn new(prod: Producer<f32>, sample_rate: u32) -> Crossfader {
+        Crossfader {
+            prod: prod,
+            written: 0,
+            sample_rate: sample_rate,
+            enabled: false,
+            seconds: 5.0,
+            curve: Curve::EqualPower,
+            delay: VecDeque::new(),
+            tail: VecDeque::new(),
+            tail_len: 0
+        }
+    }
+
+    // Samplingrate des Audio-Geräts
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // ändert die Samplingrate (beim Wechsel des Audio-Geräts),
+    // zurückgehaltene Daten haben die alte Rate und werden verworfen
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.delay.clear();
+        self.tail.clear();
+    }
+
+    // schaltet die Überblendung ein oder aus
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // setzt Länge (in Sekunden) und Verlauf der Überblendung
+    pub fn configure(&mut self, seconds: f32, curve: Curve) {
+        self.seconds = seconds.max(0.0);
+        self.curve = curve;
+    }
+
+    // Anzahl der zurückzuhaltenden Samples (beide Kanäle)
+    fn hold(&self) -> usize {
+        if self.enabled {
+            (self.seconds * self.sample_rate as f32) as usize * 2
+        } else {
+            0
+        }
+    }
+
+    // Dauer (in Sekunden) der zurückgehaltenen Daten
+    pub fn latency(&self) -> f64 {
+        self.delay.len() as f64 / (2 * self.sample_rate) as f64
+    }
+
+    // Position (Anzahl Samples seit Beginn) hinter den bisher
+    // gelesenen Daten, zurückgehaltene Samples zählen mit. Direkt
+    // nach begin_track ist das der Anfang des neuen Titels.
+    pub fn end(&self) -> u64 {
+        self.written + self.delay.len() as u64
+    }
+
+    // wird aufgerufen, bevor ein neuer Titel gelesen wird:
+    // die zurückgehaltenen Samples werden zum Ende, das
+    // mit dem Anfang des neuen Titels gemischt wird
+    pub fn begin_track(&mut self) {
+        if self.hold() == 0 { return; }
+
+        self.tail = std::mem::take(&mut self.delay);
+        self.tail_len = self.tail.len();
+    }
+
+    // verwirft alle zurückgehaltenen Daten (beim Überspringen)
+    // und gibt an, wie viele Samples bisher geschrieben wurden,
+    // diese gehören alle zum abgebrochenen Titel (s. SkipFader)
+    pub fn reset(&mut self) -> u64 {
+        self.delay.clear();
+        self.tail.clear();
+        self.written
+    }
+
+    // schreibt die zurückgehaltenen Daten in den Ringbuffer,
+    // falls kein weiterer Titel folgt
+    pub fn flush(&mut self) {
+        while !self.prod.is_full() {
+            match self.delay.pop_front() {
+                Some(s) => self.push(s),
+                None => break
+            }
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        if self.prod.push(sample).is_ok() {
+            self.written += 1;
+        }
+    }
+
+    // mischt ein Sample des neuen Titels mit dem Ende des vorherigen
+    fn mix(&mut self, sample: f32) -> f32 {
+        match self.tail.pop_front() {
+            None => sample,
+            Some(old) => {
+                let progress = 1.0 - self.tail.len() as f32 / self.tail_len as f32;
+                let (gain_out, gain_in) = self.curve.gains(progress);
+                old * gain_out + sample * gain_in
+            }
+        }
+    }
+}
+
+impl ReaderTarget<f32> for Crossfader {
+    fn read_iter <I: Iterator<Item=f32>> (&mut self, iter: &mut I) -> usize {
+        let hold = self.hold();
+        let mut count = 0;
+
+        loop {
+            // älteste zurückgehaltene Samples in den Ringbuffer
+            // schreiben, bis wieder Platz für neue Samples ist
+            while !self.delay.is_empty() && self.delay.len() >= hold && !self.prod.is_full() {
+                let s = self.delay.pop_front().unwrap();
+                self.push(s);
+            }
+
+            if self.is_full() { break; }
+
+            let sample = match iter.next() {
+                Some(s) => self.mix(s),
+                None => break
+            };
+            count += 1;
+
+            if hold == 0 {
+                self.push(sample);
+            } else {
+                self.delay.push_back(sample);
+            }
+        }
+
+        count
+    }
+
+    fn is_full (&self) -> bool {
+        let hold = self.hold();
+
+        if self.delay.is_empty() && hold == 0 {
+            self.prod.is_full()
+        } else {
+            self.delay.len() >= hold && self.prod.is_full()
+        }
+    }
+}
+
+//+--------------------------------
+//| struct SkipFader
+//|     - liest im Audio-Thread die Samples aus dem
+//|       Ringbuffer. Wird ein Titel abgebrochen, werden
+//|       seine restlichen Samples kurz ausgeblendet und
+//|       verworfen, der neue Titel wird eingeblendet.
+
+pub struct SkipFader {
+    // Länge des Aus- und Einblendens in Samples
+    length: usize,
+    // Anzahl aller jemals gelesenen Samples
+    consumed: u64,
+    // Samples vor dieser Position gehören zum abgebrochenen Titel
+    skip_until: u64,
+    // Position innerhalb des Ausblendens
+    fade_out: usize,
+    // Position innerhalb des Einblendens
+    fade_in: usize
+}
+
+impl SkipFader {
+    pub fn new(length: usize) -> SkipFader {
+        SkipFader {
+            length: length,
+            consumed: 0,
+            skip_until: 0,
+            fade_out: 0,
+            fade_in: length
+        }
+    }
+
+    // alle Samples vor until werden ausgeblendet und verworfen
+    // (s. Crossfader::reset)
+    pub fn skip(&mut self, until: u64) {
+        if until > self.skip_until {
+            self.skip_until = until;
+            self.fade_out = 0;
+            self.fade_in = 0;
+        }
+    }
+
+    // Anzahl aller bisher gelesenen Samples
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    // liest das nächste Sample aus dem Ringbuffer
+    pub fn next(&mut self, cons: &mut Consumer<f32>) -> f32 {
+        if self.consumed < self.skip_until {
+            if self.fade_out < self.length {
+                // abgebrochenen Titel kurz ausblenden
+                let gain = 1.0 - self.fade_out as f32 / self.length as f32;
+                self.fade_out += 1;
+                return self.pop(cons).unwrap_or(0.0) * gain;
+            }
+
+            // den Rest des abgebrochenen Titels verwerfen
+            let rest = (self.skip_until - self.consumed) as usize;
+            self.consumed += cons.pop_each(|_| true, Some(rest)) as u64;
+
+            if self.consumed < self.skip_until { return 0.0; }
+        }
+
+        match self.pop(cons) {
+            // neuen Titel einblenden
+            Some(sample) if self.fade_in < self.length => {
+                let gain = self.fade_in as f32 / self.length as f32;
+                self.fade_in += 1;
+                sample * gain
+            },
+            Some(sample) => sample,
+            None => 0.0
+        }
+    }
+
+    fn pop(&mut self, cons: &mut Consumer<f32>) -> Option<f32> {
+        let sample = cons.pop();
+        if sample.is_some() {
+            self.consumed += 1;
+        }
+        sample
+    }
+}
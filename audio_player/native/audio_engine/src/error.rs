@@ -0,0 +1,73 @@
+//+-------------------------------------------------------------+
+//| error.rs - Fehlertypen der Bibiliothek. Alle Fehler werden  |
+//|          in einem gemeinsamen Typ zusammengefasst, das      |
+//|          Node-Modul gibt sie als Exception weiter.          |
+//+-------------------------------------------------------------+
+
+use std::fmt;
+
+//+--------------------------------
+//| enum Error
+//|     - alle Fehler, die beim Abspielen
+//|       auftreten können
+
+#[derive(Debug)]
+pub enum Error {
+    // Lesen oder Schreiben einer Datei fehlgeschlagen
+    Io(std::io::Error),
+    // Dateiformat wird nicht unterstützt
+    UnsupportedFormat(String),
+    // Audiodaten konnten nicht dekodiert werden
+    Decode(String),
+    // Fehler des Audio-Geräts
+    Device(String),
+    // Playlist konnte nicht gelesen werden
+    Playlist(String),
+    // gespeicherte Sitzung konnte nicht gelesen werden (s. session.rs)
+    Session(String),
+    // Fehler bei der Verbindung zum D-Bus (s. mpris.rs)
+    Bus(String)
+}
+
+// Kurzform für Ergebnisse mit diesem Fehlertyp
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // Fehler aus Bibiliotheken, die nur über Debug ausgegeben werden können
+    pub fn decode<E: fmt::Debug>(e: E) -> Error {
+        Error::Decode(format!("{:?}", e))
+    }
+
+    pub fn device<E: fmt::Debug>(e: E) -> Error {
+        Error::Device(format!("{:?}", e))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::UnsupportedFormat(s) => write!(f, "unsupported format: {}", s),
+            Error::Decode(s) => write!(f, "decoding error: {}", s),
+            Error::Device(s) => write!(f, "audio device error: {}", s),
+            Error::Playlist(s) => write!(f, "playlist error: {}", s),
+            Error::Session(s) => write!(f, "session error: {}", s),
+            Error::Bus(s) => write!(f, "D-Bus error: {}", s)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(feature = "mpris")]
+impl From<dbus::Error> for Error {
+    fn from(e: dbus::Error) -> Error {
+        Error::Bus(e.message().unwrap_or("unknown error").to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,94 @@
+//+-------------------------------------------------------------+
+//| events.rs - Benachrichtigungen über Veränderungen (neuer    |
+//|          Titel, Playlist, Wiedergabe, Fehler). Sie werden   |
+//|          vom Lese- und Audio-Thread ausgelöst und an alle   |
+//|          angemeldeten Empfänger weitergegeben.              |
+//+-------------------------------------------------------------+
+
+use std::sync::{Arc, Mutex};
+
+use crate::audio_reader::Tags;
+use crate::player::{RepeatMode, ShuffleMode};
+
+//+--------------------------------
+//| enum Event
+//|     - alle Ereignisse, über die
+//|       benachrichtigt wird
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    // ein Titel ist zu hören (id: Eintrag der Playlist, s. player::Track)
+    TrackStarted { path: String, id: u32, tags: Tags },
+    // ein Titel wurde bis zum Ende gespielt
+    TrackEnded { path: String },
+    // die Playlist oder der aktuelle Titel hat sich verändert
+    QueueChanged,
+    // die Wiedergabe wurde fortgesetzt oder pausiert
+    PlaybackState { playing: bool },
+    // die Wiederholung wurde geändert
+    RepeatChanged { mode: RepeatMode },
+    // die zufällige Wiedergabe wurde ein- oder ausgeschaltet
+    ShuffleChanged { mode: ShuffleMode },
+    // ein Titel konnte nicht geöffnet oder gelesen werden
+    Error { message: String },
+    // der letzte Titel der Playlist ist zu Ende
+    EndOfQueue
+}
+
+impl Event {
+    // Name, unter dem das Ereignis an JavaScript übergeben wird
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::TrackStarted { .. } => "track-started",
+            Event::TrackEnded { .. } => "track-ended",
+            Event::QueueChanged => "queue-changed",
+            Event::PlaybackState { .. } => "playback-state-changed",
+            Event::RepeatChanged { .. } => "repeat-changed",
+            Event::ShuffleChanged { .. } => "shuffle-changed",
+            Event::Error { .. } => "error",
+            Event::EndOfQueue => "end-of-queue"
+        }
+    }
+}
+
+// Empfänger eines Ereignisses, wird im auslösenden Thread aufgerufen,
+// oft während der Zustand des Players gesperrt ist. Er darf daher nicht
+// selbst auf den Zustand zugreifen, sondern nur weiterleiten.
+pub type Listener = Box<dyn Fn(&Event) + Send>;
+
+//+--------------------------------
+//| struct Events
+//|     - Liste der angemeldeten Empfänger,
+//|       Kopien teilen sich dieselbe Liste
+
+#[derive(Clone)]
+pub struct Events(Arc<Mutex<Vec<Listener>>>);
+
+impl Default for Events {
+    fn default() -> Events {
+        Events::new()
+    }
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    // meldet einen Empfänger an
+    pub fn subscribe(&self, listener: Listener) {
+        self.0.lock().unwrap().push(listener);
+    }
+
+    // meldet alle Empfänger ab
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    // benachrichtigt alle Empfänger
+    pub fn emit(&self, event: Event) {
+        for listener in self.0.lock().unwrap().iter() {
+            listener(&event);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+
+//+-------------------------------------------------------------+
+//| lib.rs - die Wiedergabe als eigenständige Rust-Bibliothek:  |
+//|          Dekodieren der Audiodateien, Verwaltung der        |
+//|          Playlist und Ausgabe auf dem Audio-Gerät. Die      |
+//|          Steuerung erfolgt über Player (s. player.rs).      |
+//+-------------------------------------------------------------+
+
+// Felder werden im ganzen Projekt ausgeschrieben (feld: feld)
+#![allow(clippy::redundant_field_names)]
+
+// importiere Bibiliotheken (crates)
+
+extern crate cpal;              // Zugriff auf Audiogeräte
+extern crate samplerate;        // Resampling der Audiodateien
+extern crate ringbuf;           // Ringbuffer um Audiodaten zu übertragen
+#[cfg(feature = "mpris")]
+extern crate dbus;              // Steuerung über den Session-Bus (MPRIS)
+#[cfg(feature = "remote")]
+extern crate tiny_http;         // HTTP-Server für die Fernsteuerung
+#[cfg(feature = "remote")]
+extern crate tungstenite;       // WebSockets für die Fernsteuerung
+
+// Modul für das Lesen der Audiodateien (siehe dort)
+pub mod audio_reader;
+// Modul mit den Fehlertypen (siehe dort)
+pub mod error;
+// Modul für die Benachrichtigung über Ereignisse (siehe dort)
+pub mod events;
+// Modul für die Überblendung zwischen Titeln (siehe dort)
+mod crossfade;
+// Modul für die Lautstärkeregelung (siehe dort)
+mod volume;
+// Modul für die Ausgabe auf dem Audio-Gerät (siehe dort)
+mod output;
+// Modul für Playlist und Steuerung (siehe dort)
+mod player;
+// Modul für die zufällige Wiedergabe (siehe dort)
+mod shuffle;
+// Modul für das Speichern der Sitzung (siehe dort)
+mod session;
+// Modul für Playlist-Dateien: M3U, PLS und XSPF (siehe dort)
+mod playlist;
+// Modul für den Zwischenspeicher der Tags (siehe dort)
+mod tag_cache;
+// Modul für die Steuerung über D-Bus (siehe dort), nur mit Feature "mpris"
+#[cfg(feature = "mpris")]
+pub mod mpris;
+// Modul für den Server nach dem MPD-Protokoll (siehe dort)
+pub mod mpd;
+// Modul für die Fernsteuerung über HTTP (siehe dort), nur mit Feature "remote"
+#[cfg(feature = "remote")]
+pub mod remote;
+
+// die wichtigsten Typen sind direkt zugreifbar
+
+pub use player::{Player, Options, Track, RepeatMode, ShuffleMode, PlaylistOrder};
+pub use playlist::{PlaylistFormat, PlaylistEntry, read_playlist, write_playlist, is_playlist};
+pub use output::{DeviceInfo, list_output_devices};
+pub use error::{Error, Result};
+pub use events::{Event, Listener};
+pub use audio_reader::Tags;
+pub use audio_reader::replay_gain::GainMode;
+pub use crossfade::Curve;
+pub use cpal::{SupportedFormat, SampleFormat};
@@ -0,0 +1,670 @@
+//+-------------------------------------------------------------+
+//| mpd.rs - Server für das Protokoll des Music Player Daemon,  |
+//|          damit vorhandene MPD-Clients (z.B. auf dem Handy)  |
+//|          den Player steuern können. Unterstützt wird ein    |
+//|          Teil der Befehle: Wiedergabe, Playlist und idle.   |
+//|          Als Id dient die id der Einträge (s. Track).       |
+//+-------------------------------------------------------------+
+
+use std::{
+    thread, thread::{sleep, JoinHandle}, time::Duration,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    fmt::Write as FmtWrite,
+    path::Path,
+    sync::{ mpsc::{channel, Receiver, Sender}, Arc, Mutex, atomic::{AtomicBool, Ordering} }
+};
+
+use crate::audio_reader::Tags;
+use crate::events::Event;
+use crate::player::{Player, Track, RepeatMode, ShuffleMode};
+use crate::error::Result;
+
+// Begrüßung beim Verbindungsaufbau, gibt die Version des Protokolls an
+const GREETING : &str = "OK MPD 0.21.0\n";
+
+// Adresse, falls keine angegeben wird (nur lokal erreichbar)
+pub const DEFAULT_ADDRESS : &str = "127.0.0.1:6600";
+
+// Intervall, in dem Verbindungen und Änderungen geprüft werden
+const POLL : Duration = Duration::from_millis(100);
+
+// Fehlercodes (s. Protokoll, ACK_ERROR_*)
+const ACK_ARG : u32 = 2;
+const ACK_UNKNOWN : u32 = 5;
+const ACK_NO_EXIST : u32 = 50;
+const ACK_SYSTEM : u32 = 52;
+
+const COMMANDS : [&str; 37] = [
+    "add", "addid", "clear", "close", "command_list_begin", "command_list_end",
+    "command_list_ok_begin", "commands", "currentsong", "delete", "deleteid", "getvol",
+    "idle", "move", "moveid", "next", "noidle", "notcommands", "outputs", "pause", "ping",
+    "play", "playid", "playlistinfo", "plchanges", "plchangesposid", "previous", "random",
+    "repeat", "seek", "seekcur", "seekid", "setvol", "single", "status", "stop", "tagtypes"
+];
+
+//+--------------------------------
+//| struct Ack
+//|     - Fehler bei der Ausführung
+//|       eines Befehls
+
+struct Ack {
+    code: u32,
+    message: String
+}
+
+impl Ack {
+    fn new(code: u32, message: &str) -> Ack {
+        Ack { code: code, message: message.to_string() }
+    }
+}
+
+type Response = std::result::Result<String, Ack>;
+
+//+--------------------------------
+//| struct Hub
+//|     - verteilt Änderungen (Subsysteme
+//|       "player", "playlist", "mixer") an
+//|       alle Verbindungen
+
+struct Hub {
+    // Version der Playlist, steigt bei jeder Änderung
+    version: u32,
+    clients: Vec<Sender<&'static str>>
+}
+
+impl Hub {
+    fn notify(&mut self, subsystem: &'static str) {
+        if subsystem == "playlist" {
+            self.version = self.version.wrapping_add(1);
+        }
+        self.clients.retain(|c| c.send(subsystem).is_ok());
+    }
+}
+
+//+--------------------------------
+//| struct MpdServer
+//|     - nimmt Verbindungen an, jede
+//|       Verbindung läuft in einem eigenen
+//|       Thread. Wird der Server frei-
+//|       gegeben, enden alle Threads
+
+pub struct MpdServer {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl MpdServer {
+    // öffnet den Port (z.B. DEFAULT_ADDRESS), die Threads halten eine
+    // Referenz auf den Player, solange der Server existiert
+    pub fn start(player: Arc<Player>, address: &str) -> Result<MpdServer> {
+        let listener = TcpListener::bind(address)?;
+        // nicht blockierend, damit der Thread beendet werden kann
+        listener.set_nonblocking(true)?;
+
+        let hub = Arc::new(Mutex::new(Hub { version: 0, clients: Vec::new() }));
+
+        // der Empfänger sperrt nur den Hub, nie den Player (s. events.rs)
+        let events = hub.clone();
+        player.subscribe(Box::new(move |event: &Event| {
+            let subsystem = match event {
+                Event::QueueChanged => "playlist",
+                Event::TrackStarted { .. } | Event::PlaybackState { .. } | Event::EndOfQueue => "player",
+                Event::RepeatChanged { .. } | Event::ShuffleChanged { .. } => "options",
+                _ => return
+            };
+            events.lock().unwrap().notify(subsystem);
+        }));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+
+        let thread = thread::spawn(move || {
+            let mut clients = Vec::new();
+
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let (send, changes) = channel();
+                        hub.lock().unwrap().clients.push(send);
+
+                        let client = Client {
+                            player: player.clone(),
+                            hub: hub.clone(),
+                            stop: stopped.clone(),
+                            changes: changes,
+                            pending: Vec::new()
+                        };
+                        clients.push(thread::spawn(move || { let _ = client.serve(stream); }));
+                    },
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => sleep(POLL),
+                    Err(_) => break
+                }
+            }
+
+            for client in clients {
+                let _ = client.join();
+            }
+        });
+
+        Ok(MpdServer {
+            stop: stop,
+            thread: Some(thread)
+        })
+    }
+}
+
+impl Drop for MpdServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+//+--------------------------------
+//| struct Client
+//|     - eine Verbindung zu einem Client
+
+struct Client {
+    player: Arc<Player>,
+    hub: Arc<Mutex<Hub>>,
+    stop: Arc<AtomicBool>,
+    // Änderungen seit Verbindungsaufbau (s. Hub)
+    changes: Receiver<&'static str>,
+    // Änderungen, die noch nicht über idle gemeldet wurden
+    pending: Vec<&'static str>
+}
+
+impl Client {
+    // beantwortet Befehle, bis die Verbindung oder der Server endet
+    fn serve(mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(POLL))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        writer.write_all(GREETING.as_bytes())?;
+
+        let mut line = Vec::new();
+        // Befehlsliste (command_list_begin) und ob nach
+        // jedem Befehl list_OK gesendet wird
+        let mut list : Option<(bool, Vec<Vec<String>>)> = None;
+        // Subsysteme, auf die ein idle wartet
+        let mut idle : Option<Vec<String>> = None;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            for change in self.changes.try_iter() {
+                if !self.pending.contains(&change) { self.pending.push(change); }
+            }
+
+            if let Some(subsystems) = &idle {
+                if let Some(res) = self.changed(subsystems) {
+                    writer.write_all(res.as_bytes())?;
+                    idle = None;
+                }
+            }
+
+            // eine Zeile kann über mehrere Timeouts hinweg ankommen
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) if line.ends_with(b"\n") => {},
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e)
+            }
+
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            line.clear();
+
+            let args = match parse_args(&text) {
+                Ok(args) => args,
+                Err(ack) => { writer.write_all(ack_line(&ack, 0, "").as_bytes())?; continue; }
+            };
+            let command = match args.first() {
+                Some(c) => c.as_str(),
+                None => continue
+            };
+
+            // während idle ist nur noidle erlaubt, alles andere beendet die Verbindung
+            if idle.is_some() {
+                if command != "noidle" { break; }
+                let all = Vec::new();
+                let res = self.changed(&all).unwrap_or_else(|| String::from("OK\n"));
+                writer.write_all(res.as_bytes())?;
+                idle = None;
+                continue;
+            }
+
+            match command {
+                "command_list_begin" => list = Some((false, Vec::new())),
+                "command_list_ok_begin" => list = Some((true, Vec::new())),
+                "command_list_end" => {
+                    if let Some((list_ok, commands)) = list.take() {
+                        let res = self.execute_list(&commands, list_ok);
+                        writer.write_all(res.as_bytes())?;
+                    }
+                },
+                _ if list.is_some() => {
+                    if let Some((_, commands)) = &mut list { commands.push(args.clone()); }
+                },
+                "idle" => idle = Some(args[1 ..].to_vec()),
+                "noidle" => writer.write_all(b"OK\n")?,
+                "close" => break,
+                _ => {
+                    let res = match self.execute(&args) {
+                        Ok(out) => out + "OK\n",
+                        Err(ack) => ack_line(&ack, 0, command)
+                    };
+                    writer.write_all(res.as_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // meldet Änderungen in den angegebenen (ohne Angabe: allen) Subsystemen
+    fn changed(&mut self, subsystems: &[String]) -> Option<String> {
+        let (matching, rest) : (Vec<&'static str>, Vec<&'static str>) = self.pending.drain(..)
+            .partition(|s| subsystems.is_empty() || subsystems.iter().any(|x| x == s));
+        self.pending = rest;
+        if matching.is_empty() { return None; }
+
+        let mut res = String::new();
+        for s in matching {
+            let _ = writeln!(res, "changed: {}", s);
+        }
+        Some(res + "OK\n")
+    }
+
+    // führt eine Befehlsliste aus, beim ersten Fehler wird abgebrochen
+    fn execute_list(&mut self, commands: &[Vec<String>], list_ok: bool) -> String {
+        let mut res = String::new();
+
+        for (i, args) in commands.iter().enumerate() {
+            match self.execute(args) {
+                Ok(out) => {
+                    res += &out;
+                    if list_ok { res += "list_OK\n"; }
+                },
+                Err(ack) => return res + &ack_line(&ack, i, &args[0])
+            }
+        }
+
+        res + "OK\n"
+    }
+
+    // führt einen Befehl aus, die Antwort enthält noch nicht das abschließende OK
+    fn execute(&mut self, args: &[String]) -> Response {
+        let player = &self.player;
+        let mut out = String::new();
+
+        match args[0].as_str() {
+            "ping" | "clearerror" => {},
+            "status" => self.status(&mut out),
+            "currentsong" => {
+                if let (Some(path), Some(tags)) = (player.curr_playing(), player.curr_tags()) {
+                    let (pos, id) = (player.curr_index() as usize, player.curr_id().unwrap_or(0));
+                    song(&mut out, pos, id, &path, &tags, Some(player.duration()));
+                }
+            },
+            "playlistinfo" => {
+                let range = match args.get(1) {
+                    Some(arg) => Some(parse_range(arg)?),
+                    None => None
+                };
+                self.playlist(&mut out, range);
+            },
+            // die Änderungen werden nicht einzeln verfolgt, es wird alles gesendet
+            "plchanges" => self.playlist(&mut out, None),
+            "plchangesposid" => {
+                for (i, track) in player.tracks().iter().enumerate() {
+                    let _ = write!(out, "cpos: {}\nId: {}\n", i, track.id);
+                }
+            },
+            "add" => {
+                let path = arg(args, 1)?;
+                if !Path::new(path).is_file() {
+                    return Err(Ack::new(ACK_NO_EXIST, "No such file"));
+                }
+                player.add_to_queue(path.to_string());
+            },
+            "addid" => {
+                let path = arg(args, 1)?;
+                if !Path::new(path).is_file() {
+                    return Err(Ack::new(ACK_NO_EXIST, "No such file"));
+                }
+                let pos = match args.get(2) {
+                    Some(pos) => pos.parse::<usize>().map_err(|_| Ack::new(ACK_ARG, "Integer expected"))?,
+                    None => player.files().len()
+                };
+                match player.insert(pos, path.to_string()) {
+                    Some(id) => { let _ = writeln!(out, "Id: {}", id); },
+                    None => return Err(Ack::new(ACK_ARG, "Bad song index"))
+                }
+            },
+            "move" | "moveid" => {
+                let from = self.track(&args[0] == "moveid", arg(args, 1)?)?;
+                let to = self.position(arg(args, 2)?)?;
+                player.move_track(from.id, to);
+            },
+            "clear" => player.clear(),
+            "delete" => {
+                let (start, end) = parse_range(arg(args, 1)?)?;
+                let tracks = player.tracks();
+                if start >= tracks.len() {
+                    return Err(Ack::new(ACK_ARG, "Bad song index"));
+                }
+                let end = end.unwrap_or(start.saturating_add(1)).min(tracks.len());
+                for track in &tracks[start .. end] {
+                    player.remove(track.id);
+                }
+            },
+            "deleteid" => {
+                let track = self.track(true, arg(args, 1)?)?;
+                player.remove(track.id);
+            },
+            "play" | "playid" => {
+                if let Some(arg) = args.get(1) {
+                    let track = self.track(&args[0] == "playid", arg)?;
+                    if player.curr_id() != Some(track.id) {
+                        player.skip_to(track.id);
+                    }
+                }
+                player.play().map_err(system)?;
+            },
+            "pause" => {
+                let pause = match args.get(1) {
+                    Some(arg) => parse_bool(arg)?,
+                    None => player.playing()
+                };
+                let res = if pause { player.pause() } else { player.play() };
+                res.map_err(system)?;
+            },
+            // repeat und single entsprechen zusammen RepeatMode,
+            // single ohne repeat (nach dem Titel anhalten) gibt es nicht
+            "repeat" => {
+                let mode = match (parse_bool(arg(args, 1)?)?, player.repeat()) {
+                    (false, _) => RepeatMode::Off,
+                    (true, RepeatMode::One) => RepeatMode::One,
+                    (true, _) => RepeatMode::All
+                };
+                player.set_repeat(mode);
+            },
+            "single" => {
+                let mode = match (parse_bool(arg(args, 1)?)?, player.repeat()) {
+                    (true, _) => RepeatMode::One,
+                    (false, RepeatMode::One) => RepeatMode::All,
+                    (false, mode) => mode
+                };
+                player.set_repeat(mode);
+            },
+            // MPD kennt nur einzelne Titel, ganze Alben gelten ebenfalls als random
+            "random" => {
+                let mode = match (parse_bool(arg(args, 1)?)?, player.shuffle()) {
+                    (false, _) => ShuffleMode::Off,
+                    (true, ShuffleMode::Off) => ShuffleMode::Track,
+                    (true, mode) => mode
+                };
+                player.set_shuffle(mode);
+            },
+            // es gibt keinen eigenen Zustand "gestoppt"
+            "stop" => player.pause().map_err(system)?,
+            "next" => player.skip(),
+            "previous" => player.prev(),
+            "seek" | "seekid" => {
+                let track = self.track(&args[0] == "seekid", arg(args, 1)?)?;
+                let time = parse_number(arg(args, 2)?)?;
+                if player.curr_id() == Some(track.id) {
+                    player.seek(time);
+                } else {
+                    player.set_start(time);
+                    player.skip_to(track.id);
+                }
+            },
+            // +/- springt relativ zur aktuellen Position
+            "seekcur" => {
+                let time = arg(args, 1)?;
+                let value = parse_number(time.trim_start_matches(&['+', '-'][..]))?;
+                let target = match time.chars().next() {
+                    Some('+') => player.position() + value,
+                    Some('-') => player.position() - value,
+                    _ => value
+                };
+                player.seek(target);
+            },
+            "setvol" => {
+                let volume = parse_number(arg(args, 1)?)?;
+                if !(0.0 ..= 100.0).contains(&volume) {
+                    return Err(Ack::new(ACK_ARG, "Invalid volume value"));
+                }
+                player.set_volume(volume as f32 / 100.0);
+                self.hub.lock().unwrap().notify("mixer");
+            },
+            "getvol" => { let _ = writeln!(out, "volume: {}", volume(player)); },
+            "outputs" => {
+                let _ = write!(out, "outputid: 0\noutputname: {}\nplugin: cpal\noutputenabled: 1\n",
+                    player.device().unwrap_or_default());
+            },
+            "tagtypes" => out += "tagtype: Artist\ntagtype: Album\ntagtype: Title\n",
+            "commands" => {
+                for c in COMMANDS.iter() {
+                    let _ = writeln!(out, "command: {}", c);
+                }
+            },
+            "notcommands" => {},
+            command => return Err(Ack::new(ACK_UNKNOWN, &format!("unknown command \"{}\"", command)))
+        }
+
+        Ok(out)
+    }
+
+    // Antwort auf status
+    fn status(&self, out: &mut String) {
+        let player = &self.player;
+        let tracks = player.tracks();
+        let curr = player.curr_index() as usize;
+        let version = self.hub.lock().unwrap().version;
+
+        let state = match (player.curr_playing().is_some(), player.playing()) {
+            (false, _) => "stop",
+            (true, true) => "play",
+            (true, false) => "pause"
+        };
+
+        let (repeat, single) = match player.repeat() {
+            RepeatMode::Off => (0, 0),
+            RepeatMode::One => (1, 1),
+            RepeatMode::All => (1, 0)
+        };
+
+        let random = if player.shuffle() == ShuffleMode::Off { 0 } else { 1 };
+
+        let _ = write!(out, "volume: {}\nrepeat: {}\nrandom: {}\nsingle: {}\nconsume: 0\n\
+            playlist: {}\nplaylistlength: {}\nstate: {}\n",
+            volume(player), repeat, random, single, version, tracks.len(), state);
+
+        if state != "stop" {
+            let (position, duration) = (player.position(), player.duration());
+            let _ = write!(out, "song: {}\nsongid: {}\ntime: {}:{}\nelapsed: {:.3}\nduration: {:.3}\n",
+                curr, tracks.get(curr).map(|t| t.id).unwrap_or(0), position as u64, duration.round() as u64, position, duration);
+        }
+
+        // bei zufälliger Wiedergabe steht der nächste Titel nicht unbedingt dahinter
+        let next = player.upcoming().first()
+            .and_then(|next| tracks.iter().position(|t| t.id == next.id));
+        if let Some(next) = next {
+            let _ = write!(out, "nextsong: {}\nnextsongid: {}\n", next, tracks[next].id);
+        }
+    }
+
+    // Antwort auf playlistinfo, optional nur ein Ausschnitt
+    fn playlist(&self, out: &mut String, range: Option<(usize, Option<usize>)>) {
+        let player = &self.player;
+        let tracks = player.tracks();
+        let curr = player.curr_id();
+
+        let (start, end) = match range {
+            Some((start, end)) => (start, end.unwrap_or(start.saturating_add(1))),
+            None => (0, tracks.len())
+        };
+
+        for (i, track) in tracks.iter().enumerate().take(end).skip(start) {
+            match player.curr_tags() {
+                Some(tags) if curr == Some(track.id) => song(out, i, track.id, &track.path, &tags, Some(player.duration())),
+                _ => song(out, i, track.id, &track.path, &player.tags(&track.path), None)
+            }
+        }
+    }
+
+    // prüft eine Position in der Playlist
+    fn position(&self, arg: &str) -> std::result::Result<usize, Ack> {
+        let pos = arg.parse::<usize>().map_err(|_| Ack::new(ACK_ARG, "Integer expected"))?;
+        if pos >= self.player.tracks().len() {
+            return Err(Ack::new(ACK_ARG, "Bad song index"));
+        }
+        Ok(pos)
+    }
+
+    // sucht einen Eintrag der Playlist über seine Id oder Position
+    fn track(&self, by_id: bool, arg: &str) -> std::result::Result<Track, Ack> {
+        let tracks = self.player.tracks();
+
+        if by_id {
+            let id = arg.parse::<u32>().map_err(|_| Ack::new(ACK_ARG, "Integer expected"))?;
+            tracks.into_iter().find(|t| t.id == id).ok_or_else(|| Ack::new(ACK_NO_EXIST, "No such song"))
+        } else {
+            let pos = arg.parse::<usize>().map_err(|_| Ack::new(ACK_ARG, "Integer expected"))?;
+            tracks.get(pos).cloned().ok_or_else(|| Ack::new(ACK_ARG, "Bad song index"))
+        }
+    }
+}
+
+// Angaben zu einem Titel (Länge in Sekunden, falls bekannt)
+fn song(out: &mut String, pos: usize, id: u32, path: &str, tags: &Tags, duration: Option<f64>) {
+    let _ = writeln!(out, "file: {}", path);
+    if !tags.artist().is_empty() { let _ = writeln!(out, "Artist: {}", tags.artist()); }
+    if !tags.album().is_empty() { let _ = writeln!(out, "Album: {}", tags.album()); }
+    if !tags.title().is_empty() { let _ = writeln!(out, "Title: {}", tags.title()); }
+    if let Some(duration) = duration {
+        let _ = write!(out, "Time: {}\nduration: {:.3}\n", duration.round() as u64, duration);
+    }
+    let _ = write!(out, "Pos: {}\nId: {}\n", pos, id);
+}
+
+// Lautstärke in Prozent
+fn volume(player: &Player) -> u32 {
+    (player.volume() * 100.0).round() as u32
+}
+
+fn system(e: crate::error::Error) -> Ack {
+    Ack::new(ACK_SYSTEM, &e.to_string())
+}
+
+// Fehlerzeile: ACK [code@index] {befehl} nachricht
+fn ack_line(ack: &Ack, index: usize, command: &str) -> String {
+    format!("ACK [{}@{}] {{{}}} {}\n", ack.code, index, command, ack.message)
+}
+
+fn arg(args: &[String], i: usize) -> std::result::Result<&str, Ack> {
+    args.get(i).map(|s| s.as_str()).ok_or_else(|| Ack::new(ACK_ARG, "too few arguments"))
+}
+
+fn parse_bool(arg: &str) -> std::result::Result<bool, Ack> {
+    match arg {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(Ack::new(ACK_ARG, "Boolean (0/1) expected"))
+    }
+}
+
+fn parse_number(arg: &str) -> std::result::Result<f64, Ack> {
+    arg.parse::<f64>().ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| Ack::new(ACK_ARG, "Number expected"))
+}
+
+// Position (N) oder Bereich (START:END, END kann fehlen),
+// ein umgekehrter Bereich ist ungültig
+fn parse_range(arg: &str) -> std::result::Result<(usize, Option<usize>), Ack> {
+    let number = |s: &str| s.parse::<usize>().map_err(|_| Ack::new(ACK_ARG, "Integer or range expected"));
+
+    let (start, end) = match arg.find(':') {
+        Some(i) if i + 1 == arg.len() => (number(&arg[.. i])?, Some(usize::MAX)),
+        Some(i) => (number(&arg[.. i])?, Some(number(&arg[i + 1 ..])?)),
+        None => (number(arg)?, None)
+    };
+
+    match end {
+        Some(end) if end < start => Err(Ack::new(ACK_ARG, "Bad song index")),
+        _ => Ok((start, end))
+    }
+}
+
+// zerlegt eine Zeile in Befehl und Argumente, Argumente können in
+// Anführungszeichen stehen und enthalten dann \" bzw. \\
+fn parse_args(line: &str) -> std::result::Result<Vec<String>, Ack> {
+    let mut res = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            chars.next();
+        }
+
+        let mut arg = String::new();
+        match chars.next() {
+            None => break,
+            Some('"') => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => arg.extend(chars.next()),
+                    Some(c) => arg.push(c),
+                    None => return Err(Ack::new(ACK_ARG, "Missing closing '\"'"))
+                }
+            },
+            Some(c) => {
+                arg.push(c);
+                while let Some(c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                    arg.push(*c);
+                    chars.next();
+                }
+            }
+        }
+        res.push(arg);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bereich oder Fehlermeldung
+    fn range (arg: &str) -> std::result::Result<(usize, Option<usize>), String> {
+        parse_range(arg).map_err(|ack| ack.message)
+    }
+
+    #[test]
+    fn ranges () {
+        assert_eq!(range("3"), Ok((3, None)));
+        assert_eq!(range("2:5"), Ok((2, Some(5))));
+        assert_eq!(range("2:2"), Ok((2, Some(2))));
+        assert_eq!(range("4:"), Ok((4, Some(usize::MAX))));
+
+        assert_eq!(range("5:2"), Err("Bad song index".to_string()));
+        assert_eq!(range(":2"), Err("Integer or range expected".to_string()));
+        assert_eq!(range("-1"), Err("Integer or range expected".to_string()));
+        assert_eq!(range("a:b"), Err("Integer or range expected".to_string()));
+    }
+
+    #[test]
+    fn args () {
+        let args = parse_args(r#"add "a \"b\" \\c.mp3"  x"#).ok().unwrap();
+        assert_eq!(args, vec!["add", r#"a "b" \c.mp3"#, "x"]);
+        assert!(parse_args(r#"add "offen"#).is_err());
+    }
+}
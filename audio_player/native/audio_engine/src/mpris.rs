@@ -0,0 +1,477 @@
+//+-------------------------------------------------------------+
+//| mpris.rs - Steuerung über den Session-Bus nach der MPRIS-   |
+//|          Spezifikation (org.mpris.MediaPlayer2.croissant).  |
+//|          Damit funktionieren Medientasten, die Player-      |
+//|          Widgets von GNOME/KDE und playerctl.               |
+//|        - nur mit dem Feature "mpris" verfügbar              |
+//+-------------------------------------------------------------+
+
+use std::{
+    thread, thread::JoinHandle, time::Duration,
+    sync::{ mpsc::channel, Arc, atomic::{AtomicBool, Ordering} }
+};
+
+use dbus::{
+    Message, Path,
+    arg::{PropMap, RefArg, Variant},
+    blocking::Connection,
+    blocking::stdintf::org_freedesktop_dbus::{PropertiesPropertiesChanged, RequestNameReply},
+    channel::{MatchingReceiver, Sender},
+    message::{MatchRule, SignalArgs}
+};
+
+use dbus_crossroads::{Crossroads, IfaceToken, MethodErr};
+
+use crate::audio_reader::Tags;
+use crate::events::Event;
+use crate::player::{Player, RepeatMode, ShuffleMode};
+use crate::error::Result;
+
+// Name auf dem Session-Bus und Pfad des Objekts (s. MPRIS-Spezifikation)
+const BUS_NAME : &str = "org.mpris.MediaPlayer2.croissant";
+const OBJECT_PATH : &str = "/org/mpris/MediaPlayer2";
+
+const ROOT_IFACE : &str = "org.mpris.MediaPlayer2";
+const PLAYER_IFACE : &str = "org.mpris.MediaPlayer2.Player";
+const TRACKLIST_IFACE : &str = "org.mpris.MediaPlayer2.TrackList";
+
+// Titel werden über die id ihres Eintrags angesprochen (s. Track). Der
+// Namensraum /org/mpris ist der Spezifikation vorbehalten (bis auf NoTrack)
+const TRACK_PREFIX : &str = "/org/croissant/track/";
+const NO_TRACK : &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+const MIME_TYPES : [&str; 6] = ["audio/mpeg", "audio/wav", "audio/x-wav", "audio/flac", "audio/ogg", "audio/opus"];
+
+//+--------------------------------
+//| struct Mpris
+//|     - meldet einen Player am Session-
+//|       Bus an, Anfragen werden in einem
+//|       eigenen Thread beantwortet. Wird
+//|       die Struktur freigegeben, endet
+//|       der Thread und der Name wird frei
+
+pub struct Mpris {
+    // beendet den Thread
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Mpris {
+    // der Thread hält eine Referenz auf den Player, solange Mpris existiert
+    pub fn start(player: Arc<Player>) -> Result<Mpris> {
+        let conn = Connection::new_session()?;
+
+        // läuft bereits ein Player unter diesem Namen, wird die Prozess-ID angehängt
+        if conn.request_name(BUS_NAME, false, true, true)? != RequestNameReply::PrimaryOwner {
+            conn.request_name(format!("{}.instance{}", BUS_NAME, std::process::id()), false, true, true)?;
+        }
+
+        let mut cr = Crossroads::new();
+        let ifaces = [register_root(&mut cr), register_player(&mut cr), register_tracklist(&mut cr)];
+        cr.insert(OBJECT_PATH, &ifaces, player.clone());
+
+        conn.start_receive(MatchRule::new_method_call(), Box::new(move |msg, conn| {
+            let _ = cr.handle_message(msg, conn);
+            true
+        }));
+
+        // Ereignisse werden im Thread in Signale übersetzt, der Empfänger
+        // selbst darf nicht auf den Player zugreifen (s. events.rs)
+        let (send, events) = channel();
+        player.subscribe(Box::new(move |event: &Event| {
+            let _ = send.send(event.clone());
+        }));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                if conn.process(Duration::from_millis(100)).is_err() { break; }
+
+                for event in events.try_iter() {
+                    for msg in signals(&player, &event) {
+                        let _ = conn.send(msg);
+                    }
+                }
+            }
+        });
+
+        Ok(Mpris {
+            stop: stop,
+            thread: Some(thread)
+        })
+    }
+}
+
+impl Drop for Mpris {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// org.mpris.MediaPlayer2: allgemeine Angaben über das Programm
+fn register_root(cr: &mut Crossroads) -> IfaceToken<Arc<Player>> {
+    cr.register(ROOT_IFACE, |b| {
+        // es gibt kein Fenster, das angezeigt oder geschlossen werden könnte
+        b.method("Raise", (), (), |_, _: &mut Arc<Player>, _: ()| Ok(()));
+        b.method("Quit", (), (), |_, _: &mut Arc<Player>, _: ()| Ok(()));
+
+        b.property("CanQuit").emits_changed_const().get(|_, _| Ok(false));
+        b.property("CanRaise").emits_changed_const().get(|_, _| Ok(false));
+        b.property("HasTrackList").emits_changed_const().get(|_, _| Ok(true));
+        b.property("Identity").emits_changed_const().get(|_, _| Ok(String::from("croissant")));
+        b.property("SupportedUriSchemes").emits_changed_const()
+            .get(|_, _| Ok(vec![String::from("file")]));
+        b.property("SupportedMimeTypes").emits_changed_const()
+            .get(|_, _| Ok(MIME_TYPES.iter().map(|m| m.to_string()).collect::<Vec<String>>()));
+    })
+}
+
+// org.mpris.MediaPlayer2.Player: Steuerung der Wiedergabe
+fn register_player(cr: &mut Crossroads) -> IfaceToken<Arc<Player>> {
+    cr.register(PLAYER_IFACE, |b| {
+        b.signal::<(i64,), _>("Seeked", ("Position",));
+
+        b.method("Next", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            player.skip();
+            Ok(())
+        });
+        b.method("Previous", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            player.prev();
+            Ok(())
+        });
+        b.method("Pause", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            player.pause().map_err(|e| MethodErr::failed(&e))
+        });
+        b.method("Play", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            player.play().map_err(|e| MethodErr::failed(&e))
+        });
+        b.method("PlayPause", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            let res = if player.playing() { player.pause() } else { player.play() };
+            res.map_err(|e| MethodErr::failed(&e))
+        });
+        // es gibt keinen eigenen Zustand "gestoppt": pausieren und an den Anfang
+        b.method("Stop", (), (), |_, player: &mut Arc<Player>, _: ()| {
+            player.pause().map_err(|e| MethodErr::failed(&e))?;
+            player.seek(0.0);
+            Ok(())
+        });
+        // relativer Sprung, über das Ende hinaus geht es zum nächsten Titel
+        b.method("Seek", ("Offset",), (), |ctx, player: &mut Arc<Player>, (offset,): (i64,)| {
+            let target = (player.position() + seconds(offset)).max(0.0);
+            if target >= player.duration() {
+                player.skip();
+            } else {
+                player.seek(target);
+                ctx.push_msg(seeked(target));
+            }
+            Ok(())
+        });
+        // absoluter Sprung, wird ignoriert, falls der Titel nicht mehr läuft
+        b.method("SetPosition", ("TrackId", "Position"), (),
+            |ctx, player: &mut Arc<Player>, (track, position): (Path<'static>, i64)| {
+                let target = seconds(position);
+                let current = player.curr_id().is_some() && parse_track_id(&track) == player.curr_id();
+
+                if current && target >= 0.0 && target <= player.duration() {
+                    player.seek(target);
+                    ctx.push_msg(seeked(target));
+                }
+                Ok(())
+            });
+        b.method("OpenUri", ("Uri",), (), |_, player: &mut Arc<Player>, (uri,): (String,)| {
+            let path = file_path(&uri).ok_or_else(|| MethodErr::invalid_arg(&uri))?;
+            player.play_now(path);
+            Ok(())
+        });
+
+        b.property("PlaybackStatus").get(|_, player| Ok(playback_status(player)));
+        b.property("LoopStatus")
+            .get(|_, player| Ok(loop_status(player.repeat()).to_string()))
+            .set(|_, player, status: String| {
+                let mode = repeat_mode(&status).ok_or_else(|| MethodErr::invalid_arg(&status))?;
+                player.set_repeat(mode);
+                Ok(Some(status))
+            });
+        // MPRIS kennt nur an und aus, ganze Alben gelten als an
+        b.property("Shuffle")
+            .get(|_, player| Ok(player.shuffle() != ShuffleMode::Off))
+            .set(|_, player, shuffle: bool| {
+                player.set_shuffle(if shuffle { ShuffleMode::Track } else { ShuffleMode::Off });
+                Ok(Some(shuffle))
+            });
+        b.property("Rate").emits_changed_const().get(|_, _| Ok(1.0));
+        b.property("MinimumRate").emits_changed_const().get(|_, _| Ok(1.0));
+        b.property("MaximumRate").emits_changed_const().get(|_, _| Ok(1.0));
+        b.property("Metadata").get(|_, player| Ok(current_metadata(player)));
+        b.property("Volume")
+            .get(|_, player| Ok(player.volume() as f64))
+            .set(|_, player, level: f64| {
+                let level = level.clamp(0.0, 1.0);
+                player.set_volume(level as f32);
+                Ok(Some(level))
+            });
+        // die Position ändert sich laufend und wird daher nicht gemeldet
+        b.property("Position").emits_changed_false().get(|_, player| Ok(micros(player.position())));
+        b.property("CanGoNext").get(|_, player| Ok(can_go_next(player)));
+        b.property("CanGoPrevious").get(|_, player| Ok(can_go_previous(player)));
+        b.property("CanPlay").emits_changed_const().get(|_, _| Ok(true));
+        b.property("CanPause").emits_changed_const().get(|_, _| Ok(true));
+        b.property("CanSeek").get(|_, player| Ok(player.curr_playing().is_some()));
+        b.property("CanControl").emits_changed_const().get(|_, _| Ok(true));
+    })
+}
+
+// org.mpris.MediaPlayer2.TrackList: Zugriff auf die Playlist. Die Playlist
+// kann über AddTrack und RemoveTrack bearbeitet werden, jede Änderung wird
+// als TrackListReplaced gemeldet (s. signals)
+fn register_tracklist(cr: &mut Crossroads) -> IfaceToken<Arc<Player>> {
+    cr.register(TRACKLIST_IFACE, |b| {
+        b.signal::<(Vec<Path<'static>>, Path<'static>), _>("TrackListReplaced", ("Tracks", "CurrentTrack"));
+
+        b.method("GetTracksMetadata", ("TrackIds",), ("Metadata",),
+            |_, player: &mut Arc<Player>, (ids,): (Vec<Path<'static>>,)| {
+                let tracks = player.tracks();
+                let curr = player.curr_id();
+
+                let res = ids.iter()
+                    .filter_map(|id| parse_track_id(id))
+                    .filter_map(|id| tracks.iter().find(|t| t.id == id))
+                    .map(|t| match player.curr_tags() {
+                        Some(tags) if Some(t.id) == curr => metadata(t.id, &t.path, &tags, Some(player.duration())),
+                        _ => metadata(t.id, &t.path, &player.tags(&t.path), None)
+                    })
+                    .collect::<Vec<PropMap>>();
+
+                Ok((res,))
+            });
+        // fügt eine Datei nach AfterTrack ein, mit NoTrack am Anfang
+        b.method("AddTrack", ("Uri", "AfterTrack", "SetAsCurrent"), (),
+            |_, player: &mut Arc<Player>, (uri, after, current): (String, Path<'static>, bool)| {
+                let path = file_path(&uri).ok_or_else(|| MethodErr::invalid_arg(&uri))?;
+                let index = if &*after == NO_TRACK {
+                    0
+                } else {
+                    let after_id = parse_track_id(&after);
+                    player.tracks().iter().position(|t| Some(t.id) == after_id)
+                        .map(|i| i + 1)
+                        .ok_or_else(|| MethodErr::invalid_arg(&after))?
+                };
+
+                let id = player.insert(index, path).ok_or_else(|| MethodErr::invalid_arg(&after))?;
+                if current {
+                    player.skip_to(id);
+                }
+                Ok(())
+            });
+        // nur das Entfernen des aktuellen Titels bricht die Wiedergabe ab
+        b.method("RemoveTrack", ("TrackId",), (),
+            |_, player: &mut Arc<Player>, (track,): (Path<'static>,)| {
+                match parse_track_id(&track) {
+                    Some(id) if player.remove(id) => Ok(()),
+                    _ => Err(MethodErr::invalid_arg(&track))
+                }
+            });
+        b.method("GoTo", ("TrackId",), (), |_, player: &mut Arc<Player>, (track,): (Path<'static>,)| {
+            if let Some(id) = parse_track_id(&track) {
+                player.skip_to(id);
+            }
+            Ok(())
+        });
+
+        b.property("Tracks").emits_changed_invalidates().get(|_, player| Ok(track_ids(player)));
+        b.property("CanEditTracks").emits_changed_const().get(|_, _| Ok(true));
+    })
+}
+
+// Signale, die nach einem Ereignis des Players gesendet werden
+fn signals(player: &Player, event: &Event) -> Vec<Message> {
+    let path = Path::from(OBJECT_PATH);
+    let mut res = Vec::new();
+
+    match event {
+        Event::QueueChanged => {
+            res.push(Message::signal(&path, &TRACKLIST_IFACE.into(), &"TrackListReplaced".into())
+                .append2(track_ids(player), current_track(player)));
+            res.push(player_changed(player, &path));
+        },
+        Event::TrackStarted { .. } | Event::PlaybackState { .. } | Event::EndOfQueue
+            | Event::RepeatChanged { .. } | Event::ShuffleChanged { .. } => {
+            res.push(player_changed(player, &path));
+        },
+        _ => {}
+    }
+
+    res
+}
+
+// PropertiesChanged für alle veränderlichen Eigenschaften von org.mpris.MediaPlayer2.Player
+fn player_changed(player: &Player, path: &Path) -> Message {
+    let mut changed = PropMap::new();
+    insert(&mut changed, "PlaybackStatus", playback_status(player));
+    insert(&mut changed, "LoopStatus", loop_status(player.repeat()).to_string());
+    insert(&mut changed, "Shuffle", player.shuffle() != ShuffleMode::Off);
+    insert(&mut changed, "Metadata", current_metadata(player));
+    insert(&mut changed, "CanGoNext", can_go_next(player));
+    insert(&mut changed, "CanGoPrevious", can_go_previous(player));
+    insert(&mut changed, "CanSeek", player.curr_playing().is_some());
+
+    PropertiesPropertiesChanged {
+        interface_name: PLAYER_IFACE.to_string(),
+        changed_properties: changed,
+        invalidated_properties: Vec::new()
+    }.to_emit_message(path)
+}
+
+// Signal nach einem Sprung innerhalb des Titels
+fn seeked(position: f64) -> Message {
+    Message::signal(&Path::from(OBJECT_PATH), &PLAYER_IFACE.into(), &"Seeked".into())
+        .append1(micros(position))
+}
+
+fn playback_status(player: &Player) -> String {
+    let status = match (player.curr_playing().is_some(), player.playing()) {
+        (false, _) => "Stopped",
+        (true, true) => "Playing",
+        (true, false) => "Paused"
+    };
+    String::from(status)
+}
+
+// LoopStatus entspricht der Wiederholung (s. RepeatMode)
+fn loop_status(mode: RepeatMode) -> &'static str {
+    match mode {
+        RepeatMode::Off => "None",
+        RepeatMode::One => "Track",
+        RepeatMode::All => "Playlist"
+    }
+}
+
+fn repeat_mode(status: &str) -> Option<RepeatMode> {
+    match status {
+        "None" => Some(RepeatMode::Off),
+        "Track" => Some(RepeatMode::One),
+        "Playlist" => Some(RepeatMode::All),
+        _ => None
+    }
+}
+
+// mit repeat-all geht es am Ende der Playlist am Anfang weiter und umgekehrt,
+// sonst nach der Reihenfolge der Wiedergabe (s. Player::upcoming und history)
+fn can_go_next(player: &Player) -> bool {
+    !player.upcoming().is_empty() || (player.repeat() == RepeatMode::All && !player.tracks().is_empty())
+}
+
+fn can_go_previous(player: &Player) -> bool {
+    !player.history().is_empty() || (player.repeat() == RepeatMode::All && !player.tracks().is_empty())
+}
+
+// Metadaten des aktuellen Titels, ohne Titel nur die Kennung "NoTrack"
+fn current_metadata(player: &Player) -> PropMap {
+    match (player.curr_id(), player.curr_playing(), player.curr_tags()) {
+        (Some(id), Some(path), Some(tags)) => metadata(id, &path, &tags, Some(player.duration())),
+        _ => {
+            let mut res = PropMap::new();
+            insert(&mut res, "mpris:trackid", Path::from(NO_TRACK));
+            res
+        }
+    }
+}
+
+// Metadaten eines Titels (Länge in Sekunden, falls bekannt)
+fn metadata(id: u32, path: &str, tags: &Tags, length: Option<f64>) -> PropMap {
+    let mut res = PropMap::new();
+    insert(&mut res, "mpris:trackid", track_id(id));
+    insert(&mut res, "xesam:url", file_url(path));
+
+    if let Some(length) = length {
+        insert(&mut res, "mpris:length", micros(length));
+    }
+    if !tags.title().is_empty() {
+        insert(&mut res, "xesam:title", tags.title());
+    }
+    if !tags.artist().is_empty() {
+        insert(&mut res, "xesam:artist", vec![tags.artist()]);
+    }
+    if !tags.album().is_empty() {
+        insert(&mut res, "xesam:album", tags.album());
+    }
+
+    res
+}
+
+fn insert<A: RefArg + 'static>(map: &mut PropMap, key: &str, value: A) {
+    map.insert(key.to_string(), Variant(Box::new(value)));
+}
+
+// Kennungen aller Titel der Playlist
+fn track_ids(player: &Player) -> Vec<Path<'static>> {
+    player.tracks().iter().map(|t| track_id(t.id)).collect()
+}
+
+// Kennung des aktuellen Titels, ohne Titel "NoTrack"
+fn current_track(player: &Player) -> Path<'static> {
+    player.curr_id().map(track_id).unwrap_or_else(|| Path::from(NO_TRACK))
+}
+
+// Kennung eines Eintrags der Playlist (s. Track)
+fn track_id(id: u32) -> Path<'static> {
+    Path::from(format!("{}{}", TRACK_PREFIX, id))
+}
+
+// id des Eintrags zu einer Kennung
+fn parse_track_id(id: &Path) -> Option<u32> {
+    id.strip_prefix(TRACK_PREFIX)?.parse().ok()
+}
+
+// MPRIS gibt Zeiten in Mikrosekunden an
+fn micros(seconds: f64) -> i64 {
+    (seconds * 1_000_000.0) as i64
+}
+
+fn seconds(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+// wandelt einen Dateinamen in eine file://-URL um
+fn file_url(path: &str) -> String {
+    let path = std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+
+    let mut url = String::from("file://");
+    for b in path.bytes() {
+        match b {
+            b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => url.push(b as char),
+            _ => url.push_str(&format!("%{:02X}", b))
+        }
+    }
+    url
+}
+
+// Dateiname zu einer file://-URL, absolute Pfade werden direkt übernommen
+fn file_path(uri: &str) -> Option<String> {
+    if uri.starts_with('/') {
+        return Some(uri.to_string());
+    }
+
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut res = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1 ..= i + 2]).ok()?;
+            res.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            res.push(encoded[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(res).ok()
+}
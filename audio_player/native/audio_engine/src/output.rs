@@ -0,0 +1,413 @@
+//+-------------------------------------------------------------+
+//| output.rs - handhabt die Audio-Hardware: Auswahl von Gerät  |
+//|          und Sampleformat sowie der Audio-Thread, der die   |
+//|          Daten aus dem Ringbuffer an das Gerät übergibt.    |
+//+-------------------------------------------------------------+
+
+use cpal::traits::{HostTrait, DeviceTrait, EventLoopTrait};
+use cpal::{StreamData, UnknownTypeOutputBuffer, Format};
+
+use std::{
+    thread, thread::sleep, time::Duration, marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{ mpsc::{channel, Sender, Receiver}, Arc, atomic::{AtomicUsize, AtomicBool, Ordering} }
+};
+
+use futures::task::AtomicWaker;
+
+use crate::crossfade::SkipFader;
+use crate::volume::{Volume, GainRamp};
+use crate::error::{Error, Result};
+use crate::events::{Event, Events};
+
+//+--------------------------------
+//| enum Message
+//|     - Nachrichten an den Audio-Thread
+
+pub enum Message {
+    // alle Samples vor dieser Position gehören
+    // zu einem abgebrochenen Titel (s. SkipFader)
+    Skip(u64),
+    // Ereignis, das ausgelöst wird, sobald die Samples
+    // bis zu dieser Position abgespielt wurden
+    Mark(u64, Event),
+    // neue Samplingrate nach dem Wechsel des Geräts
+    SampleRate(u32)
+}
+
+//+--------------------------------
+//| struct CpalPlayer<'a>
+//|     - diese Struktur handhabt
+//|       die Audio Hardware
+
+pub struct CpalPlayer<'a> {
+    // handle für die Kontrolle des Audio-Threads
+    event_loop: Arc<cpal::EventLoop>,
+    // id des Audio-Streaams
+    stream_id: cpal::StreamId,
+    // Name des Audio-Geräts
+    pub device: String,
+    // Anzahl der Kanäle des Audio-Geräts, wird mit dem Audio-Thread geteilt
+    channels: Arc<AtomicUsize>,
+    // gibt an ob gerade Musik abgespielt wird
+    playing: bool,
+    // Kommunikation mit dem Audio-Thread (bei Abbruch und Ereignissen)
+    pub channel: Sender<Message>,
+    // Samplingrate des Audio-Geräts
+    pub sample_rate: u32,
+    // Anzahl der Samples, die noch im Ringbuffer liegen
+    buffered: Arc<AtomicUsize>,
+    // fordert den Audio-Thread zum Beenden auf (s. Drop)
+    stop: Arc<AtomicBool>,
+    // Audio-Thread, true über stopped, sobald er beendet ist
+    thread: Option<thread::JoinHandle<()>>,
+    stopped: Receiver<bool>,
+    // enthält keine Daten, nur für das Rust-Typensystem vorhanden
+    phantom: PhantomData<&'a ()>
+}
+
+// sucht ein Audio-Gerät anhand seines Namens, ohne Namen wird
+// das Standard-Gerät verwendet. Gibt es kein Standard-Gerät,
+// wird das erste verfügbare Gerät genommen.
+fn find_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device> {
+    match name {
+        Some(name) => host.output_devices().map_err(Error::device)?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or(Error::Device(format!("no output device named {}", name))),
+        None => host.default_output_device()
+            .or_else(|| host.output_devices().ok()?.next())
+            .ok_or(Error::Device(String::from("no output device available")))
+    }
+}
+
+// wählt das Sampleformat für ein Audio-Gerät. Bevorzugt werden Stereo,
+// f32 und die gewünschte Samplingrate (in dieser Reihenfolge)
+fn choose_format(device: &cpal::Device, preferred_rate: Option<u32>) -> Result<Format> {
+    let formats : Vec<cpal::SupportedFormat> = device.supported_output_formats()
+        .map_err(Error::device)?
+        .filter(|f| f.channels > 0)
+        .collect();
+
+    // gewünschte Rate, dann die des Standardformats, dann 48 kHz und 44,1 kHz
+    let default_rate = device.default_output_format().ok().map(|f| f.sample_rate.0);
+    let rates : Vec<u32> = preferred_rate.into_iter()
+        .chain(default_rate)
+        .chain(vec![48000, 44100])
+        .collect();
+
+    // erste Samplingrate aus rates, die das Format unterstützt
+    let rate_for = |f: &cpal::SupportedFormat| rates.iter().cloned()
+        .find(|r| f.min_sample_rate.0 <= *r && *r <= f.max_sample_rate.0);
+
+    let rank = |f: &cpal::SupportedFormat| (
+        f.channels != 2,
+        match f.data_type {
+            cpal::SampleFormat::F32 => 0,
+            cpal::SampleFormat::I16 => 1,
+            cpal::SampleFormat::U16 => 2
+        },
+        rate_for(f).is_none()
+    );
+
+    let best = formats.iter()
+        .min_by_key(|f| rank(f))
+        .ok_or(Error::Device(String::from("device supports no output format")))?;
+
+    Ok(Format {
+        channels: best.channels,
+        sample_rate: cpal::SampleRate(rate_for(best).unwrap_or(best.max_sample_rate.0)),
+        data_type: best.data_type
+    })
+}
+
+// öffnet ein Audio-Gerät (s. find_device) und wählt das Format
+// (s. choose_format), preferred_rate ist die gewünschte Samplingrate
+pub fn open_device(name: Option<&str>, preferred_rate: Option<u32>) -> Result<(cpal::Device, Format)> {
+    let host = cpal::default_host();
+    let device = find_device(&host, name)?;
+    let format = choose_format(&device, preferred_rate)?;
+    Ok((device, format))
+}
+
+// schreibt Stereo-Samples in den Buffer des Audio-Geräts und wandelt sie
+// dabei in dessen Sampleformat und Kanalanzahl um. Mono-Geräte erhalten
+// den Mittelwert beider Kanäle, weitere Kanäle bleiben still.
+fn write_output<T: cpal::Sample>(buffer: &mut [T], stereo: &[f32], channels: usize) {
+    for (frame, s) in buffer.chunks_mut(channels).zip(stereo.chunks(2)) {
+        let left = s[0].clamp(-1.0, 1.0);
+        let right = s[1].clamp(-1.0, 1.0);
+
+        if channels == 1 {
+            frame[0] = T::from(&((left + right) / 2.0));
+            continue;
+        }
+
+        for (i, out) in frame.iter_mut().enumerate() {
+            *out = T::from(&match i { 0 => left, 1 => right, _ => 0.0 });
+        }
+    }
+}
+
+//+--------------------------------
+//| struct DeviceInfo
+//|     - Name und unterstützte Formate
+//|       eines Audio-Geräts
+
+pub struct DeviceInfo {
+    pub name: String,
+    // Standard-Gerät des Systems
+    pub default: bool,
+    pub formats: Vec<cpal::SupportedFormat>
+}
+
+// listet alle Audio-Geräte auf, die Daten ausgeben können
+pub fn list_output_devices() -> Result<Vec<DeviceInfo>> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+
+    let mut res = Vec::new();
+    for device in host.output_devices().map_err(Error::device)? {
+        let name = match device.name() {
+            Ok(name) => name,
+            Err(_) => continue
+        };
+        let formats = device.supported_output_formats()
+            .map(|f| f.collect())
+            .unwrap_or_default();
+
+        res.push(DeviceInfo {
+            default: Some(&name) == default.as_ref(),
+            name: name,
+            formats: formats
+        });
+    }
+
+    Ok(res)
+}
+
+impl CpalPlayer<'_> {
+    // erzeugt einen neuen Audio-Player auf einem
+    // Gerät mit dem angegebenen Format (s. open_device)
+    pub fn new<'a>(
+        device: cpal::Device,
+        format: Format,
+        mut cons: ringbuf::Consumer<f32>,
+        shared_waker: Arc<AtomicWaker>,
+        volume: Arc<Volume>,
+        events: Events
+    ) -> Result<CpalPlayer<'a>>
+    {
+        let host = cpal::default_host();
+        let event_loop = Arc::new(host.event_loop());
+        let sample_rate = format.sample_rate.0;
+
+        // Kommunikations-Kanal zum Abbruch von Dateien
+        // und für Ereignisse (s. Message)
+        let (send, recv) = channel();
+
+        // angekündigte Ereignisse, die noch nicht zu hören sind
+        let mut pending : Vec<(u64, Event)> = Vec::new();
+
+        // beim Abbruch wird 20 ms lang aus- bzw. eingeblendet
+        let mut fader = SkipFader::new(sample_rate as usize * 2 / 50);
+
+        // wendet die Lautstärke an (s. volume.rs)
+        let mut ramp = GainRamp::new(volume, sample_rate);
+
+        // Füllstand des Ringbuffers, wird vom Audio-Thread aktualisiert
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let buffered_copy = buffered.clone();
+
+        // Kanalanzahl, kann sich beim Wechsel des Geräts ändern
+        let channels = Arc::new(AtomicUsize::new(format.channels as usize));
+        let channels_copy = channels.clone();
+
+        // Zwischenspeicher für die Stereo-Samples aus dem Ringbuffer
+        let mut stereo = Vec::new();
+
+        // Beenden des Audio-Threads (s. Drop)
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_copy = stop.clone();
+        let (stopped_send, stopped) = channel();
+
+        // Audio-Stream erstellen
+        let stream_id = event_loop.build_output_stream(&device, &format).map_err(Error::device)?;
+        event_loop.play_stream(stream_id.clone()).map_err(Error::device)?;
+
+        // Kopie des event_loop für Verschiebung in den Audio-Thread
+        let event_loop_copy = event_loop.clone();
+        
+        // erstellen des Audio-Threads
+        // alle Variablen, die innerhalb des Threads
+        // verwendet werden, werden von Rust in den Thread
+        // verschoben und sind von außen nicht mehr zugreifbar.
+        // Beim Wechsel des Geräts (s. set_device) wird nur der
+        // Stream ersetzt, der Thread und der Ringbuffer bleiben.
+        let thread = thread::spawn(move || {
+            let audio_thread = thread::current().id();
+            let stop_signal = stopped_send.clone();
+
+            // run() kehrt nie zurück, zum Beenden wird der
+            // Callback daher per resume_unwind verlassen
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| event_loop_copy.run(move |stream_id, stream_result| {
+                if stop_copy.load(Ordering::Relaxed) {
+                    if thread::current().id() == audio_thread {
+                        panic::resume_unwind(Box::new(()));
+                    }
+                    // manche Backends (CoreAudio) rufen den Callback aus
+                    // einem eigenen Thread auf, dort darf kein unwind
+                    // stattfinden, der Audio-Thread bleibt bestehen
+                    let _ = stop_signal.send(false);
+                }
+
+                if cons.is_empty() {
+                    // zur entlastung des Prozessors 200 ms warten
+                    sleep(Duration::from_millis(200));
+                }
+
+                // audio buffer aus dem Argument erhalten
+                let stream_data = match stream_result {
+                    Ok(data) => data,
+                    Err(err) => {
+                        events.emit(Event::Error { message: format!("an error occurred on stream {:?}: {}", stream_id, err) });
+                        return;
+                    }
+                };
+
+                while let Ok(message) = recv.try_recv() {
+                    match message {
+                        Message::Skip(written) => {
+                            // abgebrochene Daten ausblenden und verwerfen,
+                            // ihre Ereignisse werden nicht mehr ausgelöst
+                            fader.skip(written);
+                            pending.clear();
+                        },
+                        Message::Mark(position, event) => pending.push((position, event)),
+                        Message::SampleRate(rate) => ramp.set_sample_rate(rate)
+                    }
+                }
+
+                let buffer = match stream_data {
+                    StreamData::Output { buffer } => buffer,
+                    _ => return
+                };
+
+                // liest so viele Stereo-Samples aus dem Ringbuffer (cons),
+                // wie das Audio-Gerät Frames erwartet
+                let channels = channels_copy.load(Ordering::Relaxed);
+                stereo.clear();
+                for _ in 0 .. buffer.len() / channels * 2 {
+                    stereo.push(fader.next(&mut cons));
+                }
+                ramp.apply(&mut stereo);
+
+                buffered_copy.store(cons.len(), Ordering::Relaxed);
+
+                // Ereignisse auslösen, deren Position erreicht wurde
+                let consumed = fader.consumed();
+                pending.retain(|(position, event)| {
+                    if *position > consumed { return true; }
+                    events.emit(event.clone());
+                    false
+                });
+
+                // in das Sampleformat des Audio-Geräts umwandeln
+                match buffer {
+                    UnknownTypeOutputBuffer::F32(mut buffer) => write_output(&mut buffer, &stereo, channels),
+                    UnknownTypeOutputBuffer::I16(mut buffer) => write_output(&mut buffer, &stereo, channels),
+                    UnknownTypeOutputBuffer::U16(mut buffer) => write_output(&mut buffer, &stereo, channels)
+                }
+
+                // dem Erzeuger der Daten signalisieren, dass
+                // weitere Daten in den Buffer geschrieben werden können
+                shared_waker.wake();
+            })));
+
+            let _ = stopped_send.send(true);
+        });
+    
+        Ok(CpalPlayer {
+            event_loop: event_loop,
+            stream_id: stream_id,
+            device: device.name().unwrap_or_default(),
+            channels: channels,
+            playing: true,
+            channel: send,
+            sample_rate: sample_rate,
+            buffered: buffered,
+            stop: stop,
+            thread: Some(thread),
+            stopped: stopped,
+            phantom: PhantomData
+        })
+    }
+
+    // Audio-Thread über Abbruch benachrichtigen, alle
+    // Samples vor written gehören zum abgebrochenen Titel
+    pub fn clear_buffer(&self, written: u64) {
+        // schlägt nur fehl, falls der Audio-Thread beendet wurde
+        let _ = self.channel.send(Message::Skip(written));
+    }
+
+    // Dauer (in Sekunden) der Daten, die noch im Ringbuffer liegen
+    pub fn latency(&self) -> f64 {
+        self.buffered.load(Ordering::Relaxed) as f64 / (2 * self.sample_rate) as f64
+    }
+
+    // wechselt das Audio-Gerät, die Daten im Ringbuffer (und
+    // damit die Position im aktuellen Titel) bleiben erhalten.
+    // Die bisherige Samplingrate wird nach Möglichkeit beibehalten,
+    // sonst muss der aktuelle Titel neu gelesen werden (s. PlayerState)
+    pub fn set_device(&mut self, name: Option<&str>) -> Result<()> {
+        let (device, format) = open_device(name, Some(self.sample_rate))?;
+
+        // der neue Stream wird erstellt, bevor der alte entfernt wird,
+        // bei einem Fehler bleibt so das bisherige Gerät erhalten
+        let stream_id = self.event_loop.build_output_stream(&device, &format).map_err(Error::device)?;
+        self.event_loop.destroy_stream(self.stream_id.clone());
+
+        self.stream_id = stream_id;
+        self.device = device.name().unwrap_or_default();
+        self.channels.store(format.channels as usize, Ordering::Relaxed);
+        self.sample_rate = format.sample_rate.0;
+        let _ = self.channel.send(Message::SampleRate(self.sample_rate));
+
+        if self.playing { self.play() } else { Ok(()) }
+    }
+
+    // gibt an, ob der Audio-Thread läuft (nicht pausiert ist)
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    // Audio-Thread aufwecken
+    pub fn play(&mut self) -> Result<()> {
+        self.playing = true;
+        self.event_loop.play_stream(self.stream_id.clone()).map_err(Error::device)
+    }
+
+    // Audio-Thread pausieren
+    pub fn pause(&mut self) -> Result<()> {
+        self.playing = false;
+        self.event_loop.pause_stream(self.stream_id.clone()).map_err(Error::device)
+    }
+}
+
+// beim Freigeben wird der Audio-Thread beendet und der Audio-Stream
+// entfernt. Der Thread prüft stop nur, solange der Stream läuft.
+impl Drop for CpalPlayer<'_> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.play();
+
+        // ohne Antwort (z.B. nach einem Fehler des Streams) wird
+        // nicht gewartet, der Thread bleibt dann bestehen
+        if let Ok(true) = self.stopped.recv_timeout(Duration::from_secs(1)) {
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+
+        self.event_loop.destroy_stream(self.stream_id.clone());
+    }
+}
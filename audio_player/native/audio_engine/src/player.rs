@@ -0,0 +1,2014 @@
+//+-------------------------------------------------------------+
+//| player.rs - verwaltet die Playlist und den Zustand eines    |
+//|          Players sowie den Thread, der die Dateien liest.   |
+//|        - Player ist die öffentliche Schnittstelle, über     |
+//|          die die Wiedergabe gesteuert wird.                 |
+//+-------------------------------------------------------------+
+
+use std::{
+    thread, thread::{sleep, JoinHandle}, time::Duration,
+    sync::{ mpsc::Sender, Arc, Mutex, MutexGuard },
+    collections::{VecDeque, HashSet, HashMap},
+    pin::Pin, future::Future, task::{Context, Poll}
+};
+
+use futures::{
+    executor::block_on,
+    task::{AtomicWaker, noop_waker_ref},
+    future::{Abortable, AbortHandle, ready}
+};
+
+use ringbuf::RingBuffer;
+
+use crate::audio_reader::buffered_reader::BufferedReader;
+use crate::audio_reader::prebuffer::{Prebuffer, WakeOn};
+use crate::audio_reader::{AudioFile, AudioProducer, resample_read, Tags, Tagged, Progress};
+use crate::audio_reader::replay_gain::{ReplayGain, GainSettings, GainMode};
+use crate::crossfade::{Crossfader, Curve};
+use crate::volume::Volume;
+use crate::output::{CpalPlayer, Message, open_device};
+use crate::error::{Error, Result};
+use crate::events::{Event, Events, Listener};
+use crate::shuffle::Shuffle;
+use crate::session::Session;
+use crate::playlist::{PlaylistFormat, PlaylistEntry, read_playlist, write_playlist};
+use crate::tag_cache::TagCache;
+
+// Abstand, in dem die Sitzung automatisch gespeichert wird (s. Player::set_autosave)
+const AUTOSAVE_INTERVAL : Duration = Duration::from_secs(5);
+
+// Dauer (in Sekunden), die vom folgenden Titel im Voraus dekodiert wird
+const PREBUFFER_SECONDS : f32 = 1.0;
+
+// Lesevorgang eines Titels (s. audio_reader::resample_read)
+type ReadFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+//+--------------------------------
+//| struct Track
+//|     - ein Eintrag der Playlist. Die id wird
+//|       beim Einfügen vergeben und bleibt gleich,
+//|       auch wenn die Playlist verändert wird.
+//|       Dieselbe Datei kann mehrmals vorkommen.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    // eindeutige Nummer des Eintrags
+    pub id: u32,
+    // Dateiname
+    pub path: String
+}
+
+//+--------------------------------
+//| enum RepeatMode
+//|     - Verhalten am Ende eines
+//|       Titels bzw. der Playlist
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepeatMode {
+    // am Ende der Playlist endet die Wiedergabe
+    Off,
+    // der aktuelle Titel wird wiederholt
+    One,
+    // am Ende der Playlist beginnt sie von vorne
+    All
+}
+
+impl RepeatMode {
+    // Name, wie er aus JavaScript übergeben wird
+    pub fn from_name(name: &str) -> Option<RepeatMode> {
+        match name {
+            "off" => Some(RepeatMode::Off),
+            "one" => Some(RepeatMode::One),
+            "all" => Some(RepeatMode::All),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::One => "one",
+            RepeatMode::All => "all"
+        }
+    }
+}
+
+//+--------------------------------
+//| enum ShuffleMode
+//|     - zufällige Reihenfolge der
+//|       Wiedergabe (s. shuffle.rs)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShuffleMode {
+    // in der Reihenfolge der Playlist
+    Off,
+    // einzelne Titel gemischt
+    Track,
+    // ganze Alben gemischt, ihre Titel in der ursprünglichen Reihenfolge
+    Album
+}
+
+impl ShuffleMode {
+    // Name, wie er aus JavaScript übergeben wird
+    pub fn from_name(name: &str) -> Option<ShuffleMode> {
+        match name {
+            "off" => Some(ShuffleMode::Off),
+            "track" => Some(ShuffleMode::Track),
+            "album" => Some(ShuffleMode::Album),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShuffleMode::Off => "off",
+            ShuffleMode::Track => "track",
+            ShuffleMode::Album => "album"
+        }
+    }
+}
+
+//+--------------------------------
+//| enum PlaylistOrder
+//|     - Reihenfolge, in der die Playlist
+//|       angegeben wird (s. Player::playlist)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistOrder {
+    // Reihenfolge der Playlist, in der die Titel eingefügt wurden
+    Display,
+    // bereits gespielte Titel in der gespielten Reihenfolge,
+    // dann der aktuelle und die folgenden Titel
+    Playback
+}
+
+//+--------------------------------
+//| struct Current
+//|     - Informationen über den
+//|       momentan gespielten Titel
+
+struct Current {
+    // id des Eintrags (s. Track)
+    id: u32,
+    // Dateiname
+    path: String,
+    // Künstler, Album, Titel
+    tags: Tags,
+    // zum Abbrechen des Lesevorgangs
+    handle: AbortHandle,
+    // Fortschritt des Lesevorgangs (s. audio_reader/mod.rs)
+    progress: Arc<Progress>
+}
+
+impl Current {
+    // der Eintrag der Playlist zum aktuellen Titel
+    fn track(&self) -> Track {
+        Track { id: self.id, path: self.path.clone() }
+    }
+}
+
+//+--------------------------------
+//| struct Playerstate<'a>
+//|     - diese Struktur speichert
+//|       den momentanen Zustand
+//|       des Programms
+
+struct PlayerState<'a> {
+    // player: handhabt die Audiogeräte
+    player: Option<CpalPlayer<'a>>,
+
+    // crossfader: schreibt die Daten in den Ringbuffer (s. crossfade.rs)
+    crossfader: Option<Arc<Mutex<Crossfader>>>,
+
+    // volume: Lautstärke, wird mit dem Audio-Thread geteilt (s. volume.rs)
+    volume: Arc<Volume>,
+
+    // replay_gain: Einstellungen der Lautstärkeangleichung
+    replay_gain: GainSettings,
+
+    // play_queue: Liste der noch zu spielenden Titel
+    play_queue: VecDeque<Track>,
+    
+    // played_list: Liste der bereits gespielten Titel (in dieser Reihenfolge)
+    played_list: Vec<Track>,
+    
+    // curr: momentan gespielter Titel
+    curr: Option<Current>,
+
+    // next_id: id des nächsten eingefügten Eintrags (s. Track)
+    next_id: u32,
+
+    // repeat: Wiederholung eines Titels oder der Playlist
+    repeat: RepeatMode,
+
+    // shuffle: zufällige Wiedergabe, die Reihenfolge der Playlist
+    // steht dann dort und nicht in played_list und play_queue
+    shuffle: Option<Shuffle>,
+
+    // start: Position (in Sekunden), an der der nächste Titel beginnt
+    start: Option<f64>,
+
+    // restart: id des Titels, der nach einem Sprung (s. seek) ab der
+    // Startposition neu gelesen wird, er wird nicht erneut gemeldet
+    restart: Option<u32>,
+
+    // session: Datei, in der die Sitzung automatisch gespeichert wird
+    session: Option<String>,
+
+    // autosaving: der Thread, der die Sitzung speichert, läuft
+    autosaving: bool,
+
+    // changed: Veränderung seit der letzten Kontrolle
+    changed: bool,
+
+    // events: angemeldete Empfänger von Ereignissen (s. events.rs)
+    events: Events,
+
+    // closed: der Player wurde beendet, der Lese-Thread hält an
+    closed: bool
+}
+
+// Zustand eines Players, wird mit dessen Lese-Thread geteilt
+type SharedState = Arc<Mutex<PlayerState<'static>>>;
+
+impl<'a> PlayerState<'a> {
+    // erzeugt einen neuen, leeren Zustand
+    fn new() -> PlayerState<'a> {
+        PlayerState { 
+            player: None, 
+            crossfader: None,
+            volume: Arc::new(Volume::new()),
+            replay_gain: GainSettings::new(),
+            played_list: Vec::new(), 
+            play_queue: VecDeque::new(), 
+            curr: None,
+            next_id: 1,
+            repeat: RepeatMode::Off,
+            shuffle: None,
+            start: None,
+            restart: None,
+            session: None,
+            autosaving: false,
+            changed: false,
+            events: Events::new(),
+            closed: false
+        }
+    }
+
+    // initialisiert den Zustand mit einem CpalPlayer und
+    // dem zugehörigen Crossfader (siehe dort)
+    fn init(&mut self, player: CpalPlayer<'a>, crossfader: Arc<Mutex<Crossfader>>) {
+        self.player = Some(player);
+        self.crossfader = Some(crossfader);
+    }
+
+    // bricht den momentanen Titel ab und gibt das Audio-Gerät zurück, das
+    // außerhalb der Sperre freigegeben wird (s. Player::close). Der
+    // Lese-Thread beendet sich daraufhin (s. spawn_file_reader)
+    fn close(&mut self) -> Option<CpalPlayer<'a>> {
+        // die Sitzung wird vor dem Beenden ein letztes Mal gespeichert
+        if let (Some(path), false) = (&self.session, self.closed) {
+            if let Err(e) = self.session().write(path) {
+                self.events.emit(Event::Error { message: format!("cannot save session {}: {}", path, e) });
+            }
+        }
+
+        self.abort_curr();
+        self.crossfader = None;
+        self.events.clear();
+        self.closed = true;
+        self.player.take()
+    }
+
+    // bricht momentanen Titel ab
+    fn abort_curr(&mut self) {
+        if let Some(curr) = &self.curr {
+            curr.handle.abort();
+        }
+
+        if let (Some(player), Some(crossfader)) = (&self.player, &self.crossfader) {
+            // alle bisher geschriebenen Daten gehören zum abgebrochenen Titel
+            let written = crossfader.lock().unwrap().reset();
+            player.clear_buffer(written);
+        }
+    }
+
+    // setzt die Wiedergabe fort
+    fn play(&mut self) -> Result<()> {
+        if let Some(player) = &mut self.player {
+            player.play()?;
+            self.events.emit(Event::PlaybackState { playing: true });
+        }
+        Ok(())
+    }
+
+    // pausiert die Wiedergabe
+    fn pause(&mut self) -> Result<()> {
+        if let Some(player) = &mut self.player {
+            player.pause()?;
+            self.events.emit(Event::PlaybackState { playing: false });
+        }
+        Ok(())
+    }
+
+    // gibt an, ob die Wiedergabe läuft (nicht pausiert ist)
+    fn playing(&self) -> bool {
+        self.player.as_ref().map(|p| p.playing()).unwrap_or(false)
+    }
+
+    // legt Länge und Verlauf der Überblendung fest
+    fn set_crossfade(&mut self, seconds: f32, curve: Curve) {
+        if let Some(crossfader) = &self.crossfader {
+            crossfader.lock().unwrap().configure(seconds, curve);
+        }
+    }
+
+    // schaltet die Überblendung ein oder aus
+    fn enable_crossfade(&mut self, enabled: bool) {
+        if let Some(crossfader) = &self.crossfader {
+            crossfader.lock().unwrap().set_enabled(enabled);
+        }
+    }
+
+    // legt die Lautstärkeangleichung fest, sie gilt ab dem nächsten Titel
+    fn set_replay_gain(&mut self, mode: GainMode, preamp: f32, prevent_clipping: bool) {
+        self.replay_gain = GainSettings {
+            mode: mode,
+            preamp: preamp,
+            prevent_clipping: prevent_clipping
+        };
+    }
+
+    // wechselt das Audio-Gerät (s. CpalPlayer::set_device). Hat das
+    // neue Gerät eine andere Samplingrate, wird der aktuelle Titel ab
+    // der momentanen Position mit der neuen Rate erneut gelesen
+    fn set_device(&mut self, name: Option<&str>) -> Result<()> {
+        let position = self.position();
+
+        let player = self.player.as_mut()
+            .ok_or(Error::Device(String::from("player not initialized")))?;
+        let old_rate = player.sample_rate;
+        player.set_device(name)?;
+        let new_rate = player.sample_rate;
+
+        if new_rate != old_rate {
+            if let Some(crossfader) = &self.crossfader {
+                crossfader.lock().unwrap().set_sample_rate(new_rate);
+            }
+            self.seek(position);
+        }
+
+        Ok(())
+    }
+
+    // springt an eine Position (in Sekunden) im aktuellen Titel,
+    // indem dieser abgebrochen und ab der Position neu gelesen wird
+    fn seek(&mut self, seconds: f64) {
+        if let Some(curr) = &self.curr {
+            let seconds = seconds.max(0.0).min(curr.progress.duration());
+
+            self.play_queue.push_front(curr.track());
+            self.start = Some(seconds);
+            self.restart = Some(curr.id);
+            self.abort_curr();
+            self.curr = None;
+        }
+    }
+
+    // legt die Startposition (in Sekunden) des nächsten Titels fest
+    fn set_start(&mut self, seconds: f64) {
+        self.start = Some(seconds.max(0.0));
+    }
+
+    // gibt die Startposition des nächsten Titels an und setzt sie zurück
+    fn take_start(&mut self) -> Option<f64> {
+        self.start.take()
+    }
+
+    // gibt an, ob der Titel id nach einem Sprung fortgesetzt wird (s. seek)
+    fn take_restart(&mut self, id: u32) -> bool {
+        self.restart.take() == Some(id)
+    }
+
+    // Position im aktuellen Titel (in Sekunden),
+    // die noch nicht abgespielten Daten im Buffer werden abgezogen
+    fn position(&self) -> f64 {
+        match (&self.curr, &self.player, &self.crossfader) {
+            (Some(curr), Some(player), Some(crossfader)) => {
+                let latency = player.latency() + crossfader.lock().unwrap().latency();
+                (curr.progress.position() - latency).max(0.0)
+            },
+            (Some(curr), _, _) => curr.progress.position(),
+            _ => 0.0
+        }
+    }
+
+    // Länge des aktuellen Titels (in Sekunden)
+    fn duration(&self) -> f64 {
+        self.curr.as_ref().map(|c| c.progress.duration()).unwrap_or(0.0)
+    }
+
+    // nächster Eintrag in der Playlist
+    fn next(&self) -> Option<Track> { 
+        self.play_queue.front().cloned() 
+    }
+
+    // Dateiname des darauf folgenden Songs in der Playlist,
+    // mit Wiederholung ggf. derselbe bzw. der erste Titel
+    fn following(&self) -> Option<String> {
+        match self.repeat {
+            RepeatMode::One => self.play_queue.front(),
+            RepeatMode::All if self.play_queue.len() <= 1 => {
+                return self.tracks().first().map(|t| t.path.clone());
+            },
+            _ => self.play_queue.get(1)
+        }.map(|t| t.path.clone())
+    }
+
+    // legt die Wiederholung fest
+    fn set_repeat(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+        self.events.emit(Event::RepeatChanged { mode: mode });
+    }
+
+    // der aktuelle Titel wurde vollständig gelesen, bei
+    // repeat-one wird er als nächstes noch einmal gespielt
+    fn finished(&mut self) {
+        if self.repeat == RepeatMode::One {
+            if let Some(curr) = self.curr.take() {
+                self.play_queue.push_front(curr.track());
+            }
+        }
+    }
+
+    // am Ende der Playlist wird sie bei repeat-all wieder von vorne
+    // gespielt, gibt an, ob die Playlist von vorne beginnt
+    fn wrap(&mut self) -> bool {
+        if self.repeat != RepeatMode::All || !self.play_queue.is_empty() { return false; }
+
+        let tracks = self.tracks();
+        if tracks.is_empty() { return false; }
+
+        // bei zufälliger Wiedergabe wird für jeden Durchgang neu gemischt
+        let tracks = match &mut self.shuffle {
+            Some(shuffle) => shuffle.mix(tracks),
+            None => tracks
+        };
+
+        self.played_list.clear();
+        self.curr = None;
+        self.play_queue.extend(tracks);
+        self.events.emit(Event::QueueChanged);
+        true
+    }
+
+    // schaltet die zufällige Wiedergabe ein oder aus, die folgenden Titel
+    // werden neu gemischt. albums: Album je id (s. Shuffle::mix)
+    fn set_shuffle(&mut self, mode: ShuffleMode, albums: HashMap<u32, String>) {
+        match (mode, self.shuffle.take()) {
+            (ShuffleMode::Off, Some(shuffle)) => self.unshuffle(shuffle.order),
+            (ShuffleMode::Off, None) => {},
+            (mode, old) => {
+                let order = match old {
+                    Some(old) => old.order,
+                    None => self.tracks().iter().map(|t| t.id).collect()
+                };
+                let mut shuffle = Shuffle::new(mode, order, albums);
+
+                let upcoming = self.play_queue.drain(..).collect();
+                self.play_queue.extend(shuffle.mix(upcoming));
+                self.shuffle = Some(shuffle);
+            }
+        }
+
+        self.events.emit(Event::ShuffleChanged { mode: mode });
+        self.events.emit(Event::QueueChanged);
+    }
+
+    // stellt die Reihenfolge der Playlist wieder her, es geht nach
+    // dem aktuellen (bzw. nächsten) Titel in dieser Reihenfolge weiter
+    fn unshuffle(&mut self, order: Vec<u32>) {
+        let curr = self.curr_id();
+        let anchor = curr.or_else(|| self.play_queue.front().map(|t| t.id));
+
+        let mut tracks : HashMap<u32, Track> = self.played_list.drain(..)
+            .chain(self.play_queue.drain(..))
+            .map(|t| (t.id, t))
+            .collect();
+
+        let mut played = true;
+        for id in order {
+            if Some(id) == anchor { played = false; }
+            match tracks.remove(&id) {
+                Some(track) if played => self.played_list.push(track),
+                Some(track) => self.play_queue.push_back(track),
+                None => {}
+            }
+        }
+    }
+
+    // entfernt Einträge, die es nicht mehr gibt,
+    // aus der Reihenfolge der Playlist (s. shuffle)
+    fn sync_order(&mut self) {
+        if self.shuffle.is_none() { return; }
+
+        let ids : HashSet<u32> = self.playback().iter().map(|t| t.id).collect();
+        if let Some(shuffle) = &mut self.shuffle {
+            shuffle.order.retain(|id| ids.contains(id));
+        }
+    }
+
+    // fügt bei zufälliger Wiedergabe eine id an einer
+    // Position der Reihenfolge der Playlist ein
+    fn order_insert(&mut self, index: usize, id: u32) {
+        if let Some(shuffle) = &mut self.shuffle {
+            shuffle.order.insert(index.min(shuffle.order.len()), id);
+        }
+    }
+
+    // reiht einen neuen Eintrag in die folgenden Titel ein, bei zufälliger
+    // Wiedergabe an zufälliger Stelle (s. Shuffle::insert_position).
+    // index: Position in der Playlist (s. files)
+    fn enqueue(&mut self, track: Track, index: usize) {
+        match &mut self.shuffle {
+            Some(shuffle) => {
+                shuffle.order.insert(index.min(shuffle.order.len()), track.id);
+                let i = shuffle.insert_position(self.play_queue.len());
+                self.play_queue.insert(i, track);
+            },
+            None => self.play_queue.push_back(track)
+        }
+    }
+
+    // entfernt das erste Element der Playlist und setzt den aktuellen Titel.
+    // restart: derselbe Titel wird nach einem Sprung fortgesetzt (s. seek),
+    // die Playlist hat sich dann nicht verändert
+    fn advance(&mut self, info: Option<Current>, restart: bool) {
+        let _ = self.play_queue.pop_front();
+
+        if let Some(l) = &self.curr {
+            self.played_list.push(l.track()) 
+        }
+        self.curr = info;
+
+        if !restart {
+            self.changed = true;
+            self.events.emit(Event::QueueChanged);
+        }
+    }
+
+    // gibt an, ob sich seit dem letzten Aufruf informationen verändert haben
+    fn changed (&mut self) -> bool {
+        let c = self.changed;
+        self.changed = false;
+        c
+    }
+
+    // hängt den momentanen und zuletzt gespielten Titel an die playlist an
+    // bricht momentane Wiedergabe ab
+    fn go_back(&mut self) {
+        if let Some(curr) = self.curr.as_ref().map(|c| c.track()) {
+            self.play_queue.push_front(curr);
+
+            // bei repeat-all geht es vom Anfang zum letzten Titel der
+            // Playlist, alle anderen gelten dann als bereits gespielt
+            let last = match self.played_list.pop() {
+                None if self.repeat == RepeatMode::All => {
+                    let last = self.play_queue.pop_back();
+                    self.played_list.extend(self.play_queue.drain(..));
+                    last
+                },
+                last => last
+            };
+            if let Some(last) = last {
+                self.play_queue.push_front(last);
+            }
+
+            self.abort_curr();
+
+            self.curr = None;
+            self.events.emit(Event::QueueChanged);
+        }
+    }
+
+    // gibt den Dateinamen des aktuellen Titels an
+    fn curr_playing(&self) -> Option<String> {
+        self.curr.as_ref().map(|c| c.path.clone())
+    }
+
+    // gibt die Informationen (Künstler, Album, Titel) des aktuellen Titels an
+    fn curr_tags(&self) -> Option<Tags> {
+        self.curr.as_ref().map(|c| c.tags.clone())
+    }
+
+    // gibt die id des aktuellen Titels an (s. Track)
+    fn curr_id(&self) -> Option<u32> {
+        self.curr.as_ref().map(|c| c.id)
+    }
+
+    // gibt die Position des aktuellen (bzw. nächsten) Titels in der Playlist an
+    fn curr_index(&self) -> u32 {
+        let index = match &self.shuffle {
+            Some(shuffle) => {
+                let anchor = self.curr_id().or_else(|| self.play_queue.front().map(|t| t.id));
+                anchor.and_then(|id| shuffle.order.iter().position(|&o| o == id))
+                    .unwrap_or(shuffle.order.len())
+            },
+            None => self.played_list.len()
+        };
+        index as u32
+    }
+
+    // gibt alle Einträge der Playlist an (s. PlaylistOrder::Display)
+    fn tracks(&self) -> Vec<Track> {
+        match &self.shuffle {
+            Some(shuffle) => {
+                let mut tracks : HashMap<u32, Track> = self.playback().into_iter().map(|t| (t.id, t)).collect();
+                shuffle.order.iter().filter_map(|id| tracks.remove(id)).collect()
+            },
+            None => self.playback()
+        }
+    }
+
+    // gibt alle Einträge in der Reihenfolge der Wiedergabe an
+    // (s. PlaylistOrder::Playback)
+    fn playback(&self) -> Vec<Track> {
+        self.played_list.iter().cloned()
+            .chain(self.curr.as_ref().map(|c| c.track()))
+            .chain(self.play_queue.iter().cloned())
+            .collect()
+    }
+
+    // gibt die Dateinamen aller Titel in der Playlist an
+    fn files(&self) -> Vec<String> {
+        self.tracks().into_iter().map(|t| t.path).collect()
+    }
+
+    // erzeugt einen Eintrag mit einer neuen id
+    fn track(&mut self, path: String) -> Track {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        Track { id: id, path: path }
+    }
+
+    // hängt eine Datei an die Playlist an
+    fn add_to_queue(&mut self, title: String) -> u32 {
+        let track = self.track(title);
+        let id = track.id;
+        self.enqueue(track, usize::MAX);
+        self.events.emit(Event::QueueChanged);
+        id
+    }
+
+    // hängt mehrere Dateien an die Playlist an (z.B. beim Import)
+    fn extend_queue(&mut self, titles: Vec<String>) -> Vec<u32> {
+        let mut ids = Vec::with_capacity(titles.len());
+        for title in titles {
+            let track = self.track(title);
+            ids.push(track.id);
+            self.enqueue(track, usize::MAX);
+        }
+        self.events.emit(Event::QueueChanged);
+        ids
+    }
+
+    // fügt eine Datei direkt nach dem aktuellen Titel ein,
+    // die Wiedergabe wird nicht unterbrochen
+    fn play_next(&mut self, title: String) -> u32 {
+        self.play_next_all(vec![title])[0]
+    }
+
+    // fügt mehrere Dateien in dieser Reihenfolge direkt
+    // nach dem aktuellen Titel ein (z.B. einen Ordner)
+    fn play_next_all(&mut self, titles: Vec<String>) -> Vec<u32> {
+        let tracks : Vec<Track> = titles.into_iter().map(|t| self.track(t)).collect();
+        let ids : Vec<u32> = tracks.iter().map(|t| t.id).collect();
+        let index = self.curr_index() as usize + if self.curr.is_some() { 1 } else { 0 };
+
+        if let Some(shuffle) = &mut self.shuffle {
+            let index = index.min(shuffle.order.len());
+            shuffle.order.splice(index .. index, ids.iter().cloned());
+        }
+        for track in tracks.into_iter().rev() {
+            self.play_queue.push_front(track);
+        }
+
+        self.events.emit(Event::QueueChanged);
+        ids
+    }
+
+    // spielt eine Datei sofort ab, der abgebrochene
+    // Titel folgt danach wieder von vorne
+    fn play_now(&mut self, title: String) -> u32 {
+        let track = self.track(title);
+        let id = track.id;
+        let index = self.curr_index() as usize;
+        self.order_insert(index, id);
+
+        if let Some(p) = &self.curr {
+            self.play_queue.push_front(p.track());
+        }
+        self.play_queue.push_front(track);
+        self.abort_curr();
+        self.curr = None;
+        self.events.emit(Event::QueueChanged);
+        id
+    }
+
+    // entfernt einen Eintrag der Playlist, die Wiedergabe
+    // wird nur abgebrochen, wenn der aktuelle Titel entfernt wird
+    fn remove(&mut self, id: u32) -> bool {
+        if self.curr_id() == Some(id) {
+            self.abort_curr();
+            self.curr = None;
+        } else if let Some(i) = self.played_list.iter().position(|t| t.id == id) {
+            self.played_list.remove(i);
+        } else if let Some(i) = self.play_queue.iter().position(|t| t.id == id) {
+            self.play_queue.remove(i);
+        } else {
+            return false;
+        }
+
+        self.sync_order();
+        self.events.emit(Event::QueueChanged);
+        true
+    }
+
+    // verschiebt einen Eintrag an eine andere Position der Playlist (s. files),
+    // auch der aktuelle Titel kann verschoben werden, ohne abzubrechen.
+    // Bei zufälliger Wiedergabe ändert sich die Reihenfolge der Wiedergabe nicht
+    fn move_track(&mut self, id: u32, to: usize) -> bool {
+        if let Some(shuffle) = &mut self.shuffle {
+            let from = match shuffle.order.iter().position(|&o| o == id) {
+                Some(from) if to < shuffle.order.len() => from,
+                _ => return false
+            };
+            shuffle.order.remove(from);
+            shuffle.order.insert(to, id);
+            self.events.emit(Event::QueueChanged);
+            return true;
+        }
+
+        let len = self.played_list.len() + self.play_queue.len() + if self.curr.is_some() { 1 } else { 0 };
+        if to >= len { return false; }
+
+        let mut entries = self.take_entries();
+        let from = if self.curr_id() == Some(id) {
+            entries.iter().position(|e| e.is_none())
+        } else {
+            entries.iter().position(|e| e.as_ref().map(|t| t.id) == Some(id))
+        };
+        let from = match from {
+            Some(from) => from,
+            None => {
+                self.set_entries(entries);
+                return false;
+            }
+        };
+        let played = entries[.. from].iter().all(|e| e.is_some());
+
+        let entry = entries.remove(from);
+        let to = match entries.iter().position(|e| e.is_none()) {
+            _ if self.curr.is_some() => to,
+            Some(marker) if to < marker => to,
+            Some(marker) if to > marker => to + 1,
+            // direkt an der Stelle des nächsten Titels bleibt ein bereits
+            // gespielter Titel gespielt und ein folgender Titel folgend
+            _ => if played { to } else { to + 1 }
+        };
+        entries.insert(to, entry);
+
+        self.set_entries(entries);
+        self.events.emit(Event::QueueChanged);
+        true
+    }
+
+    // fügt eine Datei an einer Position der Playlist (s. files) ein,
+    // an der Position des nächsten Titels wird sie als nächstes gespielt.
+    // Gibt die id des neuen Eintrags an
+    fn insert(&mut self, index: usize, title: String) -> Option<u32> {
+        if index > self.files().len() { return None; }
+
+        let track = self.track(title);
+        let id = track.id;
+
+        // bei zufälliger Wiedergabe irgendwann unter den folgenden Titeln
+        if self.shuffle.is_some() {
+            self.enqueue(track, index);
+            self.events.emit(Event::QueueChanged);
+            return Some(id);
+        }
+
+        let index = self.entry_index(index);
+        let mut entries = self.take_entries();
+        entries.insert(index, Some(track));
+
+        self.set_entries(entries);
+        self.events.emit(Event::QueueChanged);
+        Some(id)
+    }
+
+    // leert die Playlist und bricht den aktuellen Titel ab
+    fn clear(&mut self) {
+        self.abort_curr();
+        self.curr = None;
+        self.start = None;
+        self.restart = None;
+        self.played_list.clear();
+        self.play_queue.clear();
+        self.sync_order();
+        self.events.emit(Event::QueueChanged);
+    }
+
+    // entfernt alle noch nicht gespielten Titel,
+    // der aktuelle Titel wird zu Ende gespielt
+    fn clear_upcoming(&mut self) {
+        self.play_queue.clear();
+        self.sync_order();
+        self.events.emit(Event::QueueChanged);
+    }
+
+    // entfernt doppelte Einträge der Playlist, behalten wird der aktuelle
+    // Titel und sonst das erste Vorkommen. Gibt die Anzahl entfernter Titel an
+    fn dedupe(&mut self) -> usize {
+        let mut seen : HashSet<String> = self.curr_playing().into_iter().collect();
+        let before = self.played_list.len() + self.play_queue.len();
+
+        let mut entries = self.take_entries();
+        entries.retain(|e| match e {
+            Some(track) => seen.insert(track.path.clone()),
+            None => true
+        });
+        self.set_entries(entries);
+
+        let removed = before - self.played_list.len() - self.play_queue.len();
+        if removed > 0 {
+            self.sync_order();
+            self.events.emit(Event::QueueChanged);
+        }
+        removed
+    }
+
+    // Index in der Liste von take_entries zu einer Position der Playlist.
+    // Ohne aktuellen Titel hat die Markierung keine eigene Position
+    fn entry_index(&self, index: usize) -> usize {
+        if self.curr.is_none() && index >= self.played_list.len() { index + 1 } else { index }
+    }
+
+    // entnimmt die Playlist als eine Liste, None markiert den aktuellen
+    // Titel bzw. die Stelle, an der der nächste Titel beginnt
+    fn take_entries(&mut self) -> Vec<Option<Track>> {
+        let mut res : Vec<Option<Track>> = self.played_list.drain(..).map(Some).collect();
+        res.push(None);
+        res.extend(self.play_queue.drain(..).map(Some));
+        res
+    }
+
+    // teilt eine Liste von take_entries an der Markierung wieder
+    // in gespielte und folgende Titel auf
+    fn set_entries(&mut self, entries: Vec<Option<Track>>) {
+        let mut played = true;
+
+        for entry in entries {
+            match entry {
+                None => played = false,
+                Some(track) if played => self.played_list.push(track),
+                Some(track) => self.play_queue.push_back(track)
+            }
+        }
+    }
+
+    // entfernt den nächsten Titel aus der Playlist
+    // (falls er nicht geöffnet werden kann)
+    fn rm_next (&mut self) {
+        self.play_queue.pop_front();
+        self.sync_order();
+        self.changed = true;
+        self.events.emit(Event::QueueChanged);
+    }
+
+    // springt zu einem beliebigen Eintrag der Playlist,
+    // gibt false zurück, falls es die id nicht gibt
+    fn skip_to(&mut self, id: u32) -> bool {
+        if self.curr_id() == Some(id) { return true; }
+        if !self.tracks().iter().any(|t| t.id == id) { return false; }
+
+        // momentanen Song abbrechen
+        self.abort_curr();
+
+        // bei zufälliger Wiedergabe wird der gewünschte Titel als nächstes
+        // gespielt, der aktuelle gilt als gespielt (s. go_back)
+        if self.shuffle.is_some() {
+            if let Some(curr) = self.curr.take() {
+                self.played_list.push(curr.track());
+            }
+            let target = match self.played_list.iter().position(|t| t.id == id) {
+                Some(i) => self.played_list.remove(i),
+                None => {
+                    let i = self.play_queue.iter().position(|t| t.id == id).unwrap();
+                    self.play_queue.remove(i).unwrap()
+                }
+            };
+            self.play_queue.push_front(target);
+            self.events.emit(Event::QueueChanged);
+            return true;
+        }
+
+        // der aktuelle Titel wird zu einem gewöhnlichen Eintrag
+        let mut entries = self.take_entries();
+        let marker = entries.iter().position(|e| e.is_none()).unwrap();
+        entries[marker] = self.curr.take().map(|c| c.track());
+        entries.retain(|e| e.is_some());
+
+        // der gewünschte Titel wird der nächste
+        let target = entries.iter().position(|e| e.as_ref().map(|t| t.id) == Some(id)).unwrap();
+        entries.insert(target, None);
+
+        self.set_entries(entries);
+        self.events.emit(Event::QueueChanged);
+        true
+    }
+
+    // der momentane Zustand zum Speichern (s. session.rs)
+    fn session(&self) -> Session {
+        let (current, position, queued) = match (&self.curr, self.start) {
+            (Some(curr), _) => (Some(curr.track()), self.position(), self.play_queue.iter().cloned().collect()),
+            // nach seek oder go_back beginnt der nächste Titel an der Startposition
+            (None, Some(start)) if !self.play_queue.is_empty() => {
+                (self.play_queue.front().cloned(), start, self.play_queue.iter().skip(1).cloned().collect())
+            },
+            (None, _) => (None, 0.0, self.play_queue.iter().cloned().collect::<Vec<Track>>())
+        };
+
+        // Reihenfolge der Playlist als Positionen in der Reihenfolge der Wiedergabe
+        let playback : Vec<u32> = self.played_list.iter().chain(current.iter()).chain(queued.iter())
+            .map(|t| t.id)
+            .collect();
+        let order = match &self.shuffle {
+            Some(shuffle) => shuffle.order.iter()
+                .filter_map(|id| playback.iter().position(|p| p == id))
+                .collect(),
+            None => Vec::new()
+        };
+
+        Session {
+            volume: self.volume.get(),
+            repeat: self.repeat,
+            shuffle: self.shuffle.as_ref().map(|s| s.mode).unwrap_or(ShuffleMode::Off),
+            position: position,
+            played: self.played_list.iter().map(|t| t.path.clone()).collect(),
+            current: current.map(|t| t.path),
+            queued: queued.into_iter().map(|t| t.path).collect(),
+            order: order
+        }
+    }
+
+    // ersetzt den Zustand durch eine gespeicherte Sitzung, der aktuelle
+    // Titel folgt an der gespeicherten Position. albums: Album je Datei
+    fn restore(&mut self, session: Session, albums: HashMap<String, String>) {
+        self.abort_curr();
+        self.curr = None;
+
+        let played : Vec<Track> = session.played.into_iter().map(|p| self.track(p)).collect();
+        let current = session.current.map(|p| self.track(p));
+        let queued : Vec<Track> = session.queued.into_iter().map(|p| self.track(p)).collect();
+        let position = session.position;
+        self.start = current.as_ref().map(|_| position);
+
+        let tracks : Vec<&Track> = played.iter().chain(current.iter()).chain(queued.iter()).collect();
+
+        self.shuffle = match session.shuffle {
+            ShuffleMode::Off => None,
+            mode => {
+                // eine unvollständige Reihenfolge wird durch die der Wiedergabe ersetzt
+                let mut sorted = session.order.clone();
+                sorted.sort_unstable();
+                let order = if sorted.iter().copied().eq(0 .. tracks.len()) {
+                    session.order.iter().map(|&i| tracks[i].id).collect()
+                } else {
+                    tracks.iter().map(|t| t.id).collect()
+                };
+                let albums = tracks.iter()
+                    .filter_map(|t| albums.get(&t.path).map(|a| (t.id, a.clone())))
+                    .collect();
+                Some(Shuffle::new(mode, order, albums))
+            }
+        };
+
+        self.played_list = played;
+        self.play_queue = current.into_iter().chain(queued).collect();
+        self.repeat = session.repeat;
+        self.volume.set(session.volume);
+        self.changed = true;
+
+        let shuffle = self.shuffle.as_ref().map(|s| s.mode).unwrap_or(ShuffleMode::Off);
+        self.events.emit(Event::RepeatChanged { mode: self.repeat });
+        self.events.emit(Event::ShuffleChanged { mode: shuffle });
+        self.events.emit(Event::QueueChanged);
+    }
+}
+
+//+--------------------------------
+//| struct Options
+//|     - Einstellungen beim Erzeugen
+//|       eines Players
+
+pub struct Options {
+    // Name des Audio-Geräts, ohne Namen das Standard-Gerät
+    pub device: Option<String>,
+    // gewünschte Samplingrate, wird verwendet, falls das Gerät sie unterstützt
+    pub sample_rate: Option<u32>,
+    // Größe des Ringbuffers in Sekunden
+    pub buffer_seconds: f32
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            device: None,
+            sample_rate: None,
+            buffer_seconds: 2.0
+        }
+    }
+}
+
+// öffnet das Audio-Gerät und startet Audio- und Lese-Thread
+// für einen neuen Player (s. Player::new)
+fn start(options: &Options) -> Result<SharedState> {
+    let mut state = PlayerState::new();
+
+    // Audio-Gerät öffnen und ein passendes Format wählen
+    let (device, format) = open_device(options.device.as_deref(), options.sample_rate)?;
+
+    // Erstelle den Ringbuffer
+    let sample_rate = format.sample_rate.0;
+    let buffer_size = ((sample_rate * 2) as f32 * options.buffer_seconds.max(0.1)) as usize;
+    let auddiobuf = RingBuffer::<f32>::new(buffer_size);
+    let (prod, cons) = auddiobuf.split();
+    let crossfader = Arc::new(Mutex::new(Crossfader::new(prod, sample_rate)));
+
+    // Erstelle Waker zur benachrichtung des BufferedReader,
+    // wenn wieder Daten geschrieben werden können
+    let shared_waker = Arc::new(AtomicWaker::new());
+
+    // Initialisiere den Player, zu Beginn wird nichts abgespielt
+    let volume = state.volume.clone();
+    let events = state.events.clone();
+    let mut player = CpalPlayer::new(device, format, cons, shared_waker.clone(), volume, events.clone())?;
+    player.pause()?;
+    let messages = player.channel.clone();
+
+    state.init(player, crossfader.clone());
+    let state = Arc::new(Mutex::new(state));
+
+    // Starte den Datei-Thread
+    spawn_file_reader(state.clone(), crossfader, shared_waker, messages, events);
+
+    Ok(state)
+}
+
+//+--------------------------------
+//| struct Prepared
+//|     - ein bereits geöffneter Titel. Der
+//|       folgende Titel wird im Voraus geöffnet
+//|       und sein Anfang dekodiert, damit beim
+//|       Übergang keine Lücke entsteht.
+
+struct Prepared {
+    // Dateiname
+    path: String,
+    // Künstler, Album, Titel
+    tags: Tags,
+    // Angaben zur Lautstärkeangleichung
+    replay_gain: ReplayGain,
+    // Faktor der Lautstärkeangleichung, mit dem gelesen wird
+    gain: f32,
+    // Samplingrate, in die gelesen wird
+    sample_rate: u32,
+    // Fortschritt des Lesevorgangs (s. audio_reader/mod.rs)
+    progress: Arc<Progress>,
+    // nimmt die Daten auf, bis der Titel beginnt (s. audio_reader/prebuffer.rs)
+    prebuffer: Arc<Mutex<Prebuffer<Crossfader>>>,
+    // der Lesevorgang, er besitzt die geöffnete Datei
+    future: ReadFuture
+}
+
+impl Prepared {
+    // öffnet die Datei, springt ggf. an die Startposition (in Sekunden)
+    // und bereitet den Lesevorgang mit den angegebenen Einstellungen vor
+    fn open(path: String, start: Option<f64>, sample_rate: u32, settings: GainSettings, events: &Events) -> Result<Prepared> {
+        let mut file = AudioFile::open(path.as_str())?;
+        let tags = file.tags();
+        let replay_gain = file.replay_gain();
+        let gain = settings.factor(&replay_gain);
+        let length = file.length();
+        let native_rate = file.native_samplerate();
+
+        let start = match start {
+            Some(seconds) => {
+                let sample = (seconds * native_rate as f64) as u64;
+                match file.seek(sample) {
+                    Ok(_) => sample,
+                    // nach einem fehlgeschlagenen Sprung ist unklar, wie weit der
+                    // Decoder gelesen hat, der Titel beginnt daher neu geöffnet
+                    Err(e) => {
+                        events.emit(Event::Error { message: format!("cannot seek in {}: {}", path, e) });
+                        file = AudioFile::open(path.as_str())?;
+                        0
+                    }
+                }
+            },
+            None => 0
+        };
+        let progress = Arc::new(Progress::new(native_rate, start, length));
+
+        // bis der Titel beginnt, hat der Lesevorgang einen eigenen Waker (s. WakeOn)
+        let limit = (sample_rate as f32 * PREBUFFER_SECONDS) as usize * 2;
+        let prebuffer = Arc::new(Mutex::new(Prebuffer::new(limit)));
+        let reader = BufferedReader::new(prebuffer.clone(), Arc::new(AtomicWaker::new()));
+        let read_progress = progress.clone();
+        let future = Box::pin(async move {
+            resample_read(&mut file, reader, sample_rate, gain, read_progress).await
+        });
+
+        Ok(Prepared {
+            path: path,
+            tags: tags,
+            replay_gain: replay_gain,
+            gain: gain,
+            sample_rate: sample_rate,
+            progress: progress,
+            prebuffer: prebuffer,
+            future: future
+        })
+    }
+
+    // dekodiert den Anfang des Titels, bis der Prebuffer voll ist
+    fn prebuffer(&mut self) {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        if let Poll::Ready(res) = self.future.as_mut().poll(&mut cx) {
+            // ein kurzer Titel ist bereits vollständig gelesen
+            self.future = Box::pin(ready(res));
+        }
+    }
+}
+
+// diese Funktion erzeugt einen Thread, der
+// mit state interagiert um nach und nach die
+// Dateien in der PlayList in den Ringbuffer
+// zu schreiben (über den Crossfader, s. crossfade.rs).
+// Ereignisse, die erst beim Abspielen ausgelöst werden
+// sollen, werden über messages an den Audio-Thread gesendet
+fn spawn_file_reader (
+    state: SharedState,
+    prod: Arc<Mutex<Crossfader>>,
+    shared_waker: Arc<AtomicWaker>,
+    messages: Sender<Message>,
+    events: Events
+) {
+    thread::spawn(move || {
+        // Thread, der den folgenden Titel im Voraus öffnet
+        let mut preparing: Option<JoinHandle<Result<Prepared>>> = None;
+        // gibt an, ob das Ende der Playlist bereits gemeldet wurde
+        let mut idle = true;
+        loop {
+            // bereits geöffneten Titel abholen
+            let prepared = preparing.take()
+                .and_then(|handle| handle.join().ok())
+                .and_then(|p| p.ok());
+
+            // zugriff auf den Zustand des Players erhalten
+            let mut guard = state.lock().unwrap();
+
+            // der Player wurde beendet (s. PlayerState::close)
+            if guard.closed { break; }
+
+            // nächste Date erfragen, mit repeat-all beginnt die Playlist
+            // nach dem letzten Titel von vorne (s. PlayerState::wrap)
+            let mut next = guard.next();
+            if next.is_none() && !idle && guard.wrap() {
+                next = guard.next();
+            }
+
+            // Falls keine Datei in der Playlist
+            if next.is_none() {
+                std::mem::drop(guard);
+                // zurückgehaltene Daten abspielen,
+                // da kein Titel zum Überblenden folgt
+                let mut crossfader = prod.lock().unwrap();
+                crossfader.flush();
+
+                // Ende der Playlist melden, sobald es zu hören ist
+                if !idle {
+                    let _ = messages.send(Message::Mark(crossfader.end(), Event::EndOfQueue));
+                    idle = true;
+                }
+                drop(crossfader);
+
+                // auf anhängen neuer Datei warten
+                sleep(Duration::from_millis(100));
+                continue;
+            }
+
+            let Track { id, path: file_name } = next.unwrap();
+            let start = guard.take_start();
+            let restart = guard.take_restart(id);
+            let settings = guard.replay_gain;
+            drop(guard);
+
+            // die Samplingrate des Audio-Geräts kann sich seit dem
+            // letzten Titel geändert haben (s. PlayerState::set_device)
+            let sample_rate = prod.lock().unwrap().sample_rate();
+
+            // Datei öffnen (s. audio_reader/mod.rs), ohne dabei den Zustand
+            // zu sperren. Der bereits geöffnete Titel wird nur verwendet, wenn
+            // er noch der nächste ist, von vorne gespielt werden soll und
+            // mit den momentanen Einstellungen gelesen wurde
+            let opened = match prepared {
+                Some(p) if p.path == file_name && start.is_none() && p.sample_rate == sample_rate
+                    && settings.factor(&p.replay_gain) == p.gain => Ok(p),
+                _ => Prepared::open(file_name.clone(), start, sample_rate, settings, &events)
+            };
+
+            let mut guard = state.lock().unwrap();
+            if guard.closed { break; }
+
+            // die Playlist oder das Audio-Gerät kann sich währenddessen
+            // verändert haben, dann wird der nächste Titel neu bestimmt.
+            // Position und Fortsetzung nach einem Sprung bleiben erhalten,
+            // sofern nicht inzwischen erneut gesprungen wurde
+            if guard.next().map(|t| t.id) != Some(id) || prod.lock().unwrap().sample_rate() != sample_rate {
+                if guard.start.is_none() {
+                    guard.start = start;
+                    if restart {
+                        guard.restart = Some(id);
+                    }
+                }
+                continue;
+            }
+
+            let Prepared { tags, progress, prebuffer, future, .. } = match opened {
+                Ok(p) => p,
+                // Falls Datei nicht geöffnet werden kann
+                Err(e) => {
+                    events.emit(Event::Error { message: format!("cannot open {}: {}", file_name, e) });
+                    // Datei aus Playlist entfernen
+                    guard.rm_next();
+                    continue;
+                }
+            };
+
+            // das Ende des vorherigen Titels wird mit diesem überblendet
+            let begin = {
+                let mut crossfader = prod.lock().unwrap();
+                crossfader.begin_track();
+                crossfader.end()
+            };
+
+            // ab jetzt gehen die Daten in den Crossfader (s. audio_reader/prebuffer.rs),
+            // geweckt wird der Lesevorgang vom Audio-Thread, sobald wieder Platz ist
+            prebuffer.lock().unwrap().connect(prod.clone());
+            let future = WakeOn::new(future, shared_waker.clone());
+
+            // einbetten des Lesevorgangs in einen abbrechbaren Vorgang
+            let (abort_handle, abort_reg) = AbortHandle::new_pair();
+            let future = Abortable::new(future, abort_reg);
+            
+            // folgenden Titel öffnen und seinen Anfang dekodieren, während dieser gelesen wird
+            if let Some(following) = guard.following() {
+                let settings = guard.replay_gain;
+                let events = events.clone();
+                preparing = Some(thread::spawn(move || {
+                    let mut prepared = Prepared::open(following, None, sample_rate, settings, &events)?;
+                    prepared.prebuffer();
+                    Ok(prepared)
+                }));
+            }
+
+            // aktualisieren des Zustands
+            guard.advance(Some(Current {
+                id: id,
+                path: file_name.clone(),
+                tags: tags.clone(),
+                handle: abort_handle,
+                progress: progress
+            }), restart);
+
+            // Beginn des Titels melden, sobald er zu hören ist. Wird er
+            // vorher abgebrochen, verwirft der Audio-Thread die Meldung.
+            // Nach einem Sprung läuft derselbe Titel weiter
+            if !restart {
+                let started = Event::TrackStarted { path: file_name.clone(), id: id, tags: tags };
+                let _ = messages.send(Message::Mark(begin, started));
+            }
+            idle = false;
+            drop(guard);
+
+            // Fehler werden nicht auf der Konsole ausgegeben, sondern
+            // als Ereignis gemeldet (die Konsole gehört z.B. der CLI)
+            match block_on(future) {
+                // Titel wurde abgebrochen
+                Err(_) => {},
+                Ok(res) => {
+                    match res {
+                        // bei repeat-one folgt derselbe Titel noch einmal
+                        Ok(_) => state.lock().unwrap().finished(),
+                        Err(e) => events.emit(Event::Error { message: format!("error while playing {}: {}", file_name, e) })
+                    }
+
+                    let end = prod.lock().unwrap().end();
+                    let _ = messages.send(Message::Mark(end, Event::TrackEnded { path: file_name.clone() }));
+                }
+            }
+        }
+    });
+}
+
+// speichert die Sitzung regelmäßig, solange eine Datei angegeben ist
+// (s. Player::set_autosave), geschrieben wird nur nach Änderungen
+fn spawn_autosave(state: SharedState) {
+    thread::spawn(move || {
+        let mut last : Option<Session> = None;
+
+        loop {
+            sleep(AUTOSAVE_INTERVAL);
+
+            let (path, session, events) = {
+                let mut guard = state.lock().unwrap();
+                match guard.session.clone() {
+                    Some(path) if !guard.closed => (path, guard.session(), guard.events.clone()),
+                    _ => {
+                        guard.autosaving = false;
+                        return;
+                    }
+                }
+            };
+
+            if last.as_ref() == Some(&session) { continue; }
+
+            // ein Fehler wird erst nach der nächsten Änderung erneut gemeldet
+            if let Err(e) = session.write(&path) {
+                events.emit(Event::Error { message: format!("cannot save session {}: {}", path, e) });
+            }
+            last = Some(session);
+        }
+    });
+}
+
+//+--------------------------------
+//| struct Player
+//|     - Schnittstelle zur Wiedergabe. Jeder Player
+//|       hat seinen eigenen Zustand, Audio-Stream
+//|       und Lese-Thread, mehrere Player können
+//|       gleichzeitig spielen. Wird der Player
+//|       freigegeben, endet die Wiedergabe.
+
+pub struct Player {
+    state: SharedState,
+    // Tags der Dateien in der Playlist (s. tag_cache.rs)
+    tags: TagCache
+}
+
+impl Player {
+    // öffnet das Audio-Gerät, zu Beginn ist die Wiedergabe pausiert
+    pub fn new(options: Options) -> Result<Player> {
+        Ok(Player { state: start(&options)?, tags: TagCache::default() })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PlayerState<'static>> {
+        self.state.lock().unwrap()
+    }
+
+    // sperrt den Zustand für eine Änderung, nach close None. Die Änderung
+    // hat dann keine Wirkung, neue Einträge erhalten die id 0 (s. Track)
+    fn open(&self) -> Option<MutexGuard<'_, PlayerState<'static>>> {
+        Some(self.lock()).filter(|state| !state.closed)
+    }
+
+    // setzt die Wiedergabe fort
+    pub fn play(&self) -> Result<()> {
+        self.open().map_or(Ok(()), |mut state| state.play())
+    }
+
+    // pausiert die Wiedergabe
+    pub fn pause(&self) -> Result<()> {
+        self.open().map_or(Ok(()), |mut state| state.pause())
+    }
+
+    // gibt an, ob die Wiedergabe läuft (nicht pausiert ist)
+    pub fn playing(&self) -> bool {
+        self.lock().playing()
+    }
+
+    // bricht den aktuellen Titel ab
+    pub fn skip(&self) {
+        if let Some(mut state) = self.open() {
+            state.abort_curr();
+        }
+    }
+
+    // spielt den vorherigen Titel
+    pub fn prev(&self) {
+        if let Some(mut state) = self.open() {
+            state.go_back();
+        }
+    }
+
+    // springt zu einem Eintrag der Playlist (s. Track),
+    // gibt false zurück, falls es die id nicht gibt
+    pub fn skip_to(&self, id: u32) -> bool {
+        self.open().is_some_and(|mut state| state.skip_to(id))
+    }
+
+    // springt an eine Position (in Sekunden) im aktuellen Titel
+    pub fn seek(&self, seconds: f64) {
+        if let Some(mut state) = self.open() {
+            state.seek(seconds);
+        }
+    }
+
+    // der nächste Titel beginnt an dieser Position (in Sekunden)
+    pub fn set_start(&self, seconds: f64) {
+        if let Some(mut state) = self.open() {
+            state.set_start(seconds);
+        }
+    }
+
+    // Position (in Sekunden) im aktuellen Titel
+    pub fn position(&self) -> f64 {
+        self.lock().position()
+    }
+
+    // Länge (in Sekunden) des aktuellen Titels
+    pub fn duration(&self) -> f64 {
+        self.lock().duration()
+    }
+
+    // hängt eine Datei an die Playlist an, gibt die id des Eintrags an
+    pub fn add_to_queue(&self, path: String) -> u32 {
+        self.open().map_or(0, |mut state| state.add_to_queue(path))
+    }
+
+    // hängt mehrere Dateien an die Playlist an, gibt deren ids an
+    pub fn extend_queue(&self, paths: Vec<String>) -> Vec<u32> {
+        self.open().map_or_else(Vec::new, |mut state| state.extend_queue(paths))
+    }
+
+    // spielt eine Datei nach dem aktuellen Titel (ohne ihn zu unterbrechen)
+    pub fn play_next(&self, path: String) -> u32 {
+        self.open().map_or(0, |mut state| state.play_next(path))
+    }
+
+    // spielt mehrere Dateien nach dem aktuellen Titel, gibt deren ids an
+    pub fn play_next_all(&self, paths: Vec<String>) -> Vec<u32> {
+        self.open().map_or_else(Vec::new, |mut state| state.play_next_all(paths))
+    }
+
+    // spielt eine Datei sofort (unterbricht den aktuellen Titel)
+    pub fn play_now(&self, path: String) -> u32 {
+        self.open().map_or(0, |mut state| state.play_now(path))
+    }
+
+    // entfernt einen Eintrag der Playlist,
+    // gibt false zurück, falls es die id nicht gibt
+    pub fn remove(&self, id: u32) -> bool {
+        self.open().is_some_and(|mut state| state.remove(id))
+    }
+
+    // verschiebt einen Eintrag der Playlist an die Position to
+    pub fn move_track(&self, id: u32, to: usize) -> bool {
+        self.open().is_some_and(|mut state| state.move_track(id, to))
+    }
+
+    // fügt eine Datei an einer Position der Playlist ein und gibt
+    // die id des Eintrags an, None, falls es die Position nicht gibt
+    pub fn insert(&self, index: usize, path: String) -> Option<u32> {
+        self.open().and_then(|mut state| state.insert(index, path))
+    }
+
+    // leert die Playlist, die Wiedergabe endet
+    pub fn clear(&self) {
+        if let Some(mut state) = self.open() {
+            state.clear();
+        }
+    }
+
+    // entfernt alle Titel nach dem aktuellen
+    pub fn clear_upcoming(&self) {
+        if let Some(mut state) = self.open() {
+            state.clear_upcoming();
+        }
+    }
+
+    // entfernt doppelte Einträge, gibt deren Anzahl an
+    pub fn dedupe(&self) -> usize {
+        self.open().map_or(0, |mut state| state.dedupe())
+    }
+
+    // hängt alle Dateien einer Playlist (M3U, PLS oder XSPF) an,
+    // gibt die ids der Einträge an (s. playlist.rs)
+    pub fn import_playlist(&self, path: &str) -> Result<Vec<u32>> {
+        let files = read_playlist(path)?.into_iter().map(|e| e.path).collect();
+        Ok(self.extend_queue(files))
+    }
+
+    // wie import_playlist, liest ebenfalls alle Formate
+    pub fn import_m3u(&self, path: &str) -> Result<Vec<u32>> {
+        self.import_playlist(path)
+    }
+
+    // schreibt die Playlist in der angezeigten Reihenfolge in eine Datei,
+    // wahlweise mit Pfaden relativ zu deren Ordner. Titel und Länge
+    // stammen aus dem Zwischenspeicher, nur neue oder veränderte
+    // Dateien werden dafür geöffnet (s. TagCache::info)
+    pub fn export_playlist(&self, path: &str, format: PlaylistFormat, relative: bool) -> Result<()> {
+        let entries : Vec<PlaylistEntry> = self.tracks().into_iter().map(|track| {
+            let (tags, duration) = self.tags.info(&track.path);
+            let title = match (tags.artist(), tags.title()) {
+                (_, title) if title.is_empty() => None,
+                (artist, title) if artist.is_empty() => Some(title),
+                (artist, title) => Some(format!("{} - {}", artist, title))
+            };
+
+            PlaylistEntry { path: track.path, title: title, duration: duration }
+        }).collect();
+
+        write_playlist(path, format, &entries, relative)
+    }
+
+    // legt die Wiederholung fest (s. RepeatMode)
+    pub fn set_repeat(&self, mode: RepeatMode) {
+        if let Some(mut state) = self.open() {
+            state.set_repeat(mode);
+        }
+    }
+
+    // momentane Wiederholung
+    pub fn repeat(&self) -> RepeatMode {
+        self.lock().repeat
+    }
+
+    // schaltet die zufällige Wiedergabe ein oder aus (s. ShuffleMode),
+    // für ganze Alben werden zuvor die Tags aller Titel gelesen
+    pub fn set_shuffle(&self, mode: ShuffleMode) {
+        let mut albums = HashMap::new();
+
+        if mode == ShuffleMode::Album {
+            for track in self.tracks() {
+                let album = self.tags.tags(&track.path).album();
+                if !album.is_empty() {
+                    albums.insert(track.id, album);
+                }
+            }
+        }
+
+        if let Some(mut state) = self.open() {
+            state.set_shuffle(mode, albums);
+        }
+    }
+
+    // momentane zufällige Wiedergabe
+    pub fn shuffle(&self) -> ShuffleMode {
+        self.lock().shuffle.as_ref().map(|s| s.mode).unwrap_or(ShuffleMode::Off)
+    }
+
+    // speichert Playlist, aktuellen Titel mit Position, Lautstärke,
+    // Wiederholung und zufällige Wiedergabe (s. session.rs)
+    pub fn save_session(&self, path: &str) -> Result<()> {
+        let session = self.lock().session();
+        session.write(path)
+    }
+
+    // ersetzt die Playlist durch eine gespeicherte Sitzung, die Wiedergabe
+    // wird pausiert und beginnt an der gespeicherten Position
+    pub fn load_session(&self, path: &str) -> Result<()> {
+        let session = Session::read(path)?;
+
+        // für ganze Alben werden zuvor die Tags aller Titel gelesen
+        let mut albums = HashMap::new();
+        if session.shuffle == ShuffleMode::Album {
+            for path in session.played.iter().chain(session.current.iter()).chain(session.queued.iter()) {
+                let album = self.tags.tags(path).album();
+                if !album.is_empty() {
+                    albums.insert(path.clone(), album);
+                }
+            }
+        }
+
+        self.pause()?;
+        if let Some(mut state) = self.open() {
+            state.restore(session, albums);
+        }
+        Ok(())
+    }
+
+    // speichert die Sitzung regelmäßig und beim Beenden in path,
+    // mit None wird nicht mehr automatisch gespeichert
+    pub fn set_autosave(&self, path: Option<String>) {
+        let mut state = match self.open() {
+            Some(state) => state,
+            None => return
+        };
+        let spawn = path.is_some() && !state.autosaving;
+        state.session = path;
+
+        if spawn {
+            state.autosaving = true;
+            spawn_autosave(self.state.clone());
+        }
+    }
+
+    // legt Länge (in Sekunden) und Verlauf der Überblendung fest
+    pub fn set_crossfade(&self, seconds: f32, curve: Curve) {
+        if let Some(mut state) = self.open() {
+            state.set_crossfade(seconds, curve);
+        }
+    }
+
+    // schaltet die Überblendung ein oder aus
+    pub fn enable_crossfade(&self, enabled: bool) {
+        if let Some(mut state) = self.open() {
+            state.enable_crossfade(enabled);
+        }
+    }
+
+    // setzt die Lautstärke (zwischen 0 und 1)
+    pub fn set_volume(&self, level: f32) {
+        if let Some(state) = self.open() {
+            state.volume.set(level);
+        }
+    }
+
+    // momentane Lautstärke (zwischen 0 und 1)
+    pub fn volume(&self) -> f32 {
+        self.lock().volume.get()
+    }
+
+    // schaltet stumm (true) oder wieder laut (false)
+    pub fn set_muted(&self, muted: bool) {
+        if let Some(state) = self.open() {
+            state.volume.set_muted(muted);
+        }
+    }
+
+    // legt die Lautstärkeangleichung fest (s. audio_reader/replay_gain.rs)
+    pub fn set_replay_gain(&self, mode: GainMode, preamp: f32, prevent_clipping: bool) {
+        if let Some(mut state) = self.open() {
+            state.set_replay_gain(mode, preamp, prevent_clipping);
+        }
+    }
+
+    // wechselt das Audio-Gerät, ohne Namen wird das Standard-Gerät verwendet
+    pub fn set_device(&self, name: Option<&str>) -> Result<()> {
+        self.open().map_or(Ok(()), |mut state| state.set_device(name))
+    }
+
+    // Name des verwendeten Audio-Geräts
+    pub fn device(&self) -> Option<String> {
+        self.lock().player.as_ref().map(|p| p.device.clone())
+    }
+
+    // Dateiname des aktuellen Titels
+    pub fn curr_playing(&self) -> Option<String> {
+        self.lock().curr_playing()
+    }
+
+    // Künstler, Album und Titel des aktuellen Titels
+    pub fn curr_tags(&self) -> Option<Tags> {
+        self.lock().curr_tags()
+    }
+
+    // id des aktuellen Eintrags (s. Track)
+    pub fn curr_id(&self) -> Option<u32> {
+        self.lock().curr_id()
+    }
+
+    // Position des aktuellen Titels in der Playlist
+    pub fn curr_index(&self) -> u32 {
+        self.lock().curr_index()
+    }
+
+    // Künstler, Album und Titel einer Datei, bereits gelesene
+    // Dateien werden nicht erneut geöffnet (s. tag_cache.rs)
+    pub fn tags(&self, path: &str) -> Tags {
+        self.tags.tags(path)
+    }
+
+    // alle Einträge der Playlist mit Künstler, Album und Titel in der
+    // gewünschten Reihenfolge (s. PlaylistOrder), die Dateien
+    // werden gelesen, ohne den Player zu blockieren
+    pub fn playlist(&self, order: PlaylistOrder) -> Vec<(Track, Tags)> {
+        let (tracks, curr) = {
+            let state = self.lock();
+            let tracks = match order {
+                PlaylistOrder::Display => state.tracks(),
+                PlaylistOrder::Playback => state.playback()
+            };
+            (tracks, state.curr.as_ref().map(|c| (c.id, c.tags.clone())))
+        };
+
+        // entfernte Titel werden nicht mehr benötigt
+        self.tags.retain(tracks.iter().map(|t| t.path.as_str()));
+
+        tracks.into_iter().map(|t| {
+            let tags = match &curr {
+                Some((id, tags)) if *id == t.id => tags.clone(),
+                _ => self.tags.tags(&t.path)
+            };
+            (t, tags)
+        }).collect()
+    }
+
+    // alle Einträge der Playlist
+    pub fn tracks(&self) -> Vec<Track> {
+        self.lock().tracks()
+    }
+
+    // die bereits gespielten Titel in der gespielten Reihenfolge
+    pub fn history(&self) -> Vec<Track> {
+        self.lock().played_list.clone()
+    }
+
+    // die folgenden Titel in der Reihenfolge, in der sie gespielt werden
+    pub fn upcoming(&self) -> Vec<Track> {
+        self.lock().play_queue.iter().cloned().collect()
+    }
+
+    // Dateinamen aller Titel in der Playlist
+    pub fn files(&self) -> Vec<String> {
+        self.lock().files()
+    }
+
+    // gibt an, ob sich seit dem letzten Aufruf Informationen verändert haben
+    pub fn changed(&self) -> bool {
+        self.lock().changed()
+    }
+
+    // meldet einen Empfänger für Ereignisse an (s. events.rs)
+    pub fn subscribe(&self, listener: Listener) {
+        if let Some(state) = self.open() {
+            state.events.subscribe(listener);
+        }
+    }
+
+    // beendet die Wiedergabe und gibt das Audio-Gerät frei,
+    // danach haben alle Methoden keine Wirkung mehr
+    pub fn close(&self) {
+        let player = self.lock().close();
+        // dabei wird auf das Ende des Audio-Threads gewartet (s. output.rs),
+        // ohne Sperre, damit Empfänger von Ereignissen nicht blockieren
+        drop(player);
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{pin::Pin, future::Future, task::Context};
+    use futures::{future::{pending, Pending}, task::noop_waker_ref};
+
+    // Lesevorgang des aktuellen Titels, an dem ein Abbruch erkennbar ist
+    type Reading = Abortable<Pending<()>>;
+
+    // beginnt den nächsten Titel wie der Lese-Thread (s. spawn_file_reader)
+    fn start_next (state: &mut PlayerState) -> Reading {
+        let track = state.play_queue.front().unwrap().clone();
+        let (handle, registration) = AbortHandle::new_pair();
+
+        state.advance(Some(Current {
+            id: track.id,
+            path: track.path,
+            tags: Tags::empty(),
+            handle: handle,
+            progress: Arc::new(Progress::new(44100, 0, 0))
+        }), false);
+
+        Abortable::new(pending(), registration)
+    }
+
+    // Playlist aus files, die ersten played Titel sind gespielt, der folgende läuft
+    fn playing (files: &[&str], played: usize) -> (PlayerState<'static>, Reading) {
+        let mut state = PlayerState::new();
+        state.extend_queue(files.iter().map(|f| f.to_string()).collect());
+
+        let mut reading = start_next(&mut state);
+        for _ in 0 .. played {
+            reading = start_next(&mut state);
+        }
+        (state, reading)
+    }
+
+    fn aborted (reading: &mut Reading) -> bool {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        Pin::new(reading).poll(&mut cx).is_ready()
+    }
+
+    fn paths<'a, I: IntoIterator<Item = &'a Track>> (tracks: I) -> Vec<String> {
+        tracks.into_iter().map(|t| t.path.clone()).collect()
+    }
+
+    fn strings (paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    // prüft die Aufteilung der Playlist in gespielte, aktuelle und folgende Titel
+    fn assert_split (state: &PlayerState, played: &[&str], curr: Option<&str>, queued: &[&str]) {
+        let split = (paths(&state.played_list), state.curr_playing(), paths(&state.play_queue));
+        assert_eq!(split, (strings(played), curr.map(|c| c.to_string()), strings(queued)));
+    }
+
+    fn id_of (state: &PlayerState, path: &str) -> u32 {
+        state.tracks().iter().find(|t| t.path == path).unwrap().id
+    }
+
+    #[test]
+    fn remove () {
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d", "e"], 2);
+
+        assert!(state.remove(id_of(&state, "b")));
+        assert!(state.remove(id_of(&state, "e")));
+        assert_split(&state, &["a"], Some("c"), &["d"]);
+        assert!(!aborted(&mut reading));
+
+        // nur der laufende Titel wird abgebrochen
+        assert!(state.remove(id_of(&state, "c")));
+        assert_split(&state, &["a"], None, &["d"]);
+        assert_eq!(state.curr_index(), 1);
+        assert!(aborted(&mut reading));
+
+        assert!(!state.remove(99));
+    }
+
+    #[test]
+    fn move_track () {
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d", "e"], 2);
+
+        assert!(state.move_track(id_of(&state, "a"), 4));
+        assert_split(&state, &["b"], Some("c"), &["d", "e", "a"]);
+
+        // der laufende Titel wird verschoben, ohne abzubrechen
+        assert!(state.move_track(id_of(&state, "c"), 0));
+        assert_split(&state, &[], Some("c"), &["b", "d", "e", "a"]);
+        assert_eq!(state.curr_index(), 0);
+
+        assert!(!state.move_track(id_of(&state, "b"), 5));
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn insert () {
+        let (mut state, mut reading) = playing(&["a", "b", "c"], 1);
+
+        assert!(state.insert(0, "x".to_string()).is_some());
+        // an der Position nach dem aktuellen Titel folgt der neue als nächstes
+        assert!(state.insert(3, "y".to_string()).is_some());
+        assert!(state.insert(5, "z".to_string()).is_some());
+        assert_eq!(state.insert(7, "w".to_string()), None);
+
+        assert_split(&state, &["x", "a"], Some("b"), &["y", "c", "z"]);
+        assert_eq!(state.curr_index(), 2);
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn dedupe () {
+        let (mut state, mut reading) = playing(&["a", "b", "a", "c", "b"], 2);
+
+        // der aktuelle Titel bleibt, sonst das erste Vorkommen
+        assert_eq!(state.dedupe(), 2);
+        assert_split(&state, &["b"], Some("a"), &["c"]);
+        assert_eq!(state.curr_id(), Some(3));
+        assert_eq!(state.dedupe(), 0);
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn clear_upcoming () {
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d"], 1);
+
+        state.clear_upcoming();
+        assert_split(&state, &["a"], Some("b"), &[]);
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn skip_to () {
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d", "e"], 1);
+
+        // der gewünschte Titel wird der nächste, alle davor gelten als gespielt
+        assert!(state.skip_to(id_of(&state, "d")));
+        assert_split(&state, &["a", "b", "c"], None, &["d", "e"]);
+        assert_eq!(state.curr_index(), 3);
+        assert!(aborted(&mut reading));
+
+        assert!(state.skip_to(id_of(&state, "a")));
+        assert_split(&state, &[], None, &["a", "b", "c", "d", "e"]);
+
+        let mut reading = start_next(&mut state);
+        assert!(state.skip_to(id_of(&state, "a")));
+        assert!(!aborted(&mut reading));
+        assert!(!state.skip_to(99));
+    }
+
+    #[test]
+    fn stable_ids () {
+        let (mut state, mut reading) = playing(&["a", "b", "c"], 1);
+        let ids : Vec<u32> = state.tracks().iter().map(|t| t.id).collect();
+
+        // Einträge behalten ihre id, entfernte ids werden nicht wiederverwendet
+        assert!(state.remove(ids[2]));
+        let x = state.insert(0, "x".to_string()).unwrap();
+        let y = state.play_next("y".to_string());
+        assert!(!ids.contains(&x) && !ids.contains(&y) && x != y);
+
+        assert!(state.move_track(ids[0], 3));
+        let tracks : Vec<(u32, String)> = state.tracks().into_iter().map(|t| (t.id, t.path)).collect();
+        assert_eq!(tracks, vec![(x, "x".to_string()), (ids[1], "b".to_string()), (y, "y".to_string()), (ids[0], "a".to_string())]);
+        assert_eq!(state.curr_id(), Some(ids[1]));
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn shuffle_keeps_order () {
+        let files = strings(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d", "e", "f", "g", "h"], 2);
+
+        // nur die folgenden Titel werden gemischt, die Playlist bleibt
+        for &mode in &[ShuffleMode::Track, ShuffleMode::Album, ShuffleMode::Track] {
+            state.set_shuffle(mode, HashMap::new());
+            assert_eq!(paths(&state.tracks()), files);
+            assert_eq!(state.curr_index(), 2);
+
+            let mut queued = paths(&state.play_queue);
+            queued.sort_unstable();
+            assert_eq!(queued, files[3 ..]);
+            assert_eq!((paths(&state.played_list), state.curr_playing()), (strings(&["a", "b"]), Some("c".to_string())));
+            assert!(!aborted(&mut reading));
+        }
+
+        // es geht nach dem aktuellen Titel in der Reihenfolge der Playlist weiter
+        reading = start_next(&mut state);
+        let next = state.curr_playing().unwrap();
+        state.set_shuffle(ShuffleMode::Off, HashMap::new());
+        assert_eq!(paths(&state.tracks()), files);
+        assert_eq!(paths(&state.playback()), files);
+        assert_eq!(state.curr_playing(), Some(next.clone()));
+        assert_eq!(state.curr_index() as usize, files.iter().position(|f| *f == next).unwrap());
+        assert!(!aborted(&mut reading));
+    }
+
+    #[test]
+    fn shuffled () {
+        let (mut state, mut reading) = playing(&["a", "b", "c", "d", "e", "f"], 1);
+        state.set_shuffle(ShuffleMode::Track, HashMap::new());
+
+        assert!(state.remove(id_of(&state, "d")));
+        assert!(state.insert(1, "x".to_string()).is_some());
+        assert!(state.move_track(id_of(&state, "x"), 5));
+        state.extend_queue(vec!["a".to_string()]);
+        assert_eq!(state.dedupe(), 1);
+
+        // die angezeigte Reihenfolge enthält genau die Einträge der Wiedergabe
+        let mut playback = paths(&state.playback());
+        playback.sort_unstable();
+        assert_eq!(paths(&state.tracks()), strings(&["a", "b", "c", "e", "f", "x"]));
+        assert_eq!(playback, strings(&["a", "b", "c", "e", "f", "x"]));
+        assert_eq!(state.curr_playing().as_deref(), Some("b"));
+        assert!(!aborted(&mut reading));
+
+        state.clear_upcoming();
+        assert_eq!(paths(&state.tracks()), paths(&state.playback()));
+    }
+
+    #[test]
+    fn closed () {
+        // ein Player ohne Audio-Gerät und Lese-Thread
+        let player = Player { state: Arc::new(Mutex::new(PlayerState::new())), tags: TagCache::default() };
+        let a = player.add_to_queue("a".to_string());
+        player.set_volume(0.5);
+        player.close();
+
+        // nach close bleibt alles unverändert
+        assert_eq!(player.add_to_queue("b".to_string()), 0);
+        assert_eq!(player.insert(0, "c".to_string()), None);
+        assert!(!player.remove(a));
+        player.clear();
+        player.set_volume(1.0);
+        player.set_shuffle(ShuffleMode::Track);
+        assert_eq!(paths(&player.tracks()), strings(&["a"]));
+        assert_eq!((player.volume(), player.shuffle()), (0.5, ShuffleMode::Off));
+        assert!(player.play().is_ok());
+    }
+}
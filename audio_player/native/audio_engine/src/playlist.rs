@@ -0,0 +1,650 @@
+//+-------------------------------------------------------------+
+//| playlist.rs - Lesen und Schreiben von Playlist-Dateien:     |
+//|          M3U/M3U8 (mit #EXTINF), PLS und XSPF. Relative     |
+//|          Pfade beziehen sich auf den Ordner der Playlist,   |
+//|          enthaltene Playlists werden aufgelöst. Einträge,   |
+//|          die keine lokalen Dateien sind (z.B. Streams über  |
+//|          http://), werden übersprungen.                     |
+//+-------------------------------------------------------------+
+
+use std::{
+    env, fs,
+    fmt::Write,
+    path::{Path, PathBuf, Component},
+    collections::BTreeMap
+};
+
+use crate::error::{Error, Result};
+
+// enthaltene Playlists werden bis zu dieser Tiefe aufgelöst
+const MAX_DEPTH : usize = 8;
+
+//+--------------------------------
+//| enum PlaylistFormat
+//|     - unterstützte Formate
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistFormat {
+    // eine Datei pro Zeile, mit #EXTINF Titel und Länge
+    M3u,
+    // INI-Datei mit FileN, TitleN und LengthN
+    Pls,
+    // XML Shareable Playlist Format
+    Xspf
+}
+
+impl PlaylistFormat {
+    // Name, wie er aus JavaScript übergeben wird
+    pub fn from_name(name: &str) -> Option<PlaylistFormat> {
+        match name.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None
+        }
+    }
+
+    // Format anhand der Dateiendung
+    pub fn from_path(path: &str) -> Option<PlaylistFormat> {
+        Path::new(path).extension()
+            .and_then(|e| e.to_str())
+            .and_then(PlaylistFormat::from_name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "m3u",
+            PlaylistFormat::Pls => "pls",
+            PlaylistFormat::Xspf => "xspf"
+        }
+    }
+}
+
+//+--------------------------------
+//| struct PlaylistEntry
+//|     - ein Eintrag einer Playlist-Datei
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    // Dateipfad (beim Lesen immer absolut bzw. wie angegeben)
+    pub path: String,
+    // angezeigter Titel, meist "Künstler - Titel"
+    pub title: Option<String>,
+    // Länge in Sekunden
+    pub duration: Option<f64>
+}
+
+// gibt an, ob es sich um eine Playlist handelt (nach der Dateiendung)
+pub fn is_playlist(path: &str) -> bool {
+    PlaylistFormat::from_path(path).is_some()
+}
+
+// liest alle Einträge einer Playlist, das Format ergibt sich aus
+// der Dateiendung (unbekannte Endungen werden als M3U gelesen)
+pub fn read_playlist(path: &str) -> Result<Vec<PlaylistEntry>> {
+    let mut res = Vec::new();
+    let mut open = vec![fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))];
+    read_nested(Path::new(path), &mut open, &mut res)?;
+    Ok(res)
+}
+
+// liest eine Playlist und hängt ihre Einträge an res an. open: die
+// Playlists, in denen diese enthalten ist (gegen Endlosschleifen)
+fn read_nested(path: &Path, open: &mut Vec<PathBuf>, res: &mut Vec<PlaylistEntry>) -> Result<()> {
+    let name = path.to_string_lossy();
+    let bytes = fs::read(path).map_err(|e| Error::Playlist(format!("{}: {}", name, e)))?;
+    // ältere M3U-Dateien sind oft nicht in UTF-8 gespeichert
+    let text = String::from_utf8_lossy(&bytes);
+
+    let format = PlaylistFormat::from_path(&name).unwrap_or(PlaylistFormat::M3u);
+    let entries = match format {
+        PlaylistFormat::M3u => parse_m3u(&text),
+        PlaylistFormat::Pls => parse_pls(&text),
+        PlaylistFormat::Xspf => parse_xspf(&text)
+    };
+
+    // base: Ordner der Playlist
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    for entry in entries {
+        let file = match resolve(&entry.path, base, format == PlaylistFormat::Xspf) {
+            Some(file) => file,
+            None => continue
+        };
+        let file = file.to_str()
+            .ok_or_else(|| Error::Playlist(format!("{}: invalid path {:?}", name, file)))?
+            .to_string();
+
+        if !is_playlist(&file) {
+            res.push(PlaylistEntry { path: file, ..entry });
+            continue;
+        }
+
+        // enthaltene Playlist, sofern sie nicht schon gelesen wird
+        let canonical = fs::canonicalize(&file).unwrap_or_else(|_| PathBuf::from(&file));
+        if open.contains(&canonical) || open.len() >= MAX_DEPTH {
+            continue;
+        }
+        open.push(canonical);
+        read_nested(Path::new(&file), open, res)?;
+        open.pop();
+    }
+
+    Ok(())
+}
+
+// Pfad einer Datei aus dem Eintrag einer Playlist, None für Einträge,
+// die keine lokale Datei sind. uri: Pfade sind URIs (s. XSPF)
+fn resolve(location: &str, base: &Path, uri: bool) -> Option<PathBuf> {
+    if let Some(rest) = location.strip_prefix("file://") {
+        // file:///pfad oder file://localhost/pfad
+        let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+        if !rest.starts_with('/') { return None; }
+
+        let path = percent_decode(rest);
+        // file:///C:/Musik unter Windows
+        let path = match path.get(1 .. 3) {
+            Some(drive) if cfg!(windows) && drive.ends_with(':') => path[1 ..].to_string(),
+            _ => path
+        };
+        return Some(PathBuf::from(path));
+    }
+
+    // andere Schemata (http://, https://, ...)
+    if location.contains("://") { return None; }
+
+    let path = if uri { percent_decode(location) } else { location.to_string() };
+    Some(normalize(&base.join(path)))
+}
+
+// entfernt . und .. aus einem Pfad, ohne Symlinks aufzulösen
+// (die Datei muss dafür nicht existieren)
+fn normalize(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => match res.components().next_back() {
+                Some(Component::Normal(_)) => { res.pop(); },
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                _ => res.push(component)
+            },
+            _ => res.push(component)
+        }
+    }
+    res
+}
+
+// M3U: Kommentare beginnen mit #, #EXTINF:Länge,Titel beschreibt die folgende Datei
+fn parse_m3u(text: &str) -> Vec<PlaylistEntry> {
+    let mut res = Vec::new();
+    let mut info : Option<(Option<f64>, Option<String>)> = None;
+
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // die Länge kann von Attributen gefolgt werden (z.B. tvg-name="..."),
+            // der Titel beginnt nach dem ersten Komma außerhalb von Anführungszeichen
+            let mut quoted = false;
+            let comma = extinf.find(|c: char| {
+                if c == '"' { quoted = !quoted; }
+                c == ',' && !quoted
+            });
+            let (head, title) = match comma {
+                Some(i) => (&extinf[.. i], &extinf[i + 1 ..]),
+                None => (extinf, "")
+            };
+            let duration = head.split_whitespace().next()
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|d| *d >= 0.0);
+            let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+            info = Some((duration, title));
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let (duration, title) = info.take().unwrap_or((None, None));
+            res.push(PlaylistEntry {
+                path: line.to_string(),
+                title: title,
+                duration: duration
+            });
+        }
+    }
+
+    res
+}
+
+// PLS: FileN=..., TitleN=..., LengthN=... (Länge -1 für Streams)
+fn parse_pls(text: &str) -> Vec<PlaylistEntry> {
+    // Einträge ohne FileN bleiben ohne Pfad und werden übergangen
+    let mut entries : BTreeMap<u32, PlaylistEntry> = BTreeMap::new();
+
+    for line in text.lines() {
+        let (key, value) = match line.trim().split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => continue
+        };
+
+        // Name und Nummer des Eintrags trennen
+        let digits = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let number = match key[digits ..].parse::<u32>() {
+            Ok(n) => n,
+            Err(_) => continue
+        };
+        let entry = entries.entry(number).or_insert(PlaylistEntry {
+            path: String::new(),
+            title: None,
+            duration: None
+        });
+
+        match &key[.. digits] {
+            "file" => entry.path = value.to_string(),
+            "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+            "length" => entry.duration = value.parse::<f64>().ok().filter(|d| *d >= 0.0),
+            _ => {}
+        }
+    }
+
+    entries.into_values()
+        .filter(|entry| !entry.path.is_empty())
+        .collect()
+}
+
+// XSPF: <track> mit <location> (URI), <creator>, <title> und <duration> (in ms)
+fn parse_xspf(text: &str) -> Vec<PlaylistEntry> {
+    let mut res = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = find_element(rest, "track") {
+        let track = &rest[start ..];
+        let end = track.find("</track>").unwrap_or(track.len());
+        let body = &track[.. end];
+        rest = &track[end ..];
+
+        let location = match element(body, "location") {
+            Some(location) => location,
+            None => continue
+        };
+        let title = match (element(body, "creator"), element(body, "title")) {
+            (Some(creator), Some(title)) => Some(format!("{} - {}", creator, title)),
+            (_, title) => title
+        };
+        let duration = element(body, "duration")
+            .and_then(|d| d.parse::<f64>().ok())
+            .map(|ms| ms / 1000.0);
+
+        res.push(PlaylistEntry {
+            path: location,
+            title: title,
+            duration: duration
+        });
+    }
+
+    res
+}
+
+// Position des Inhalts nach dem öffnenden Tag <name> bzw. <name ...>
+fn find_element(text: &str, name: &str) -> Option<usize> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+
+    while let Some(i) = text[from ..].find(&open) {
+        let after = from + i + open.len();
+        match text[after ..].chars().next() {
+            Some('>') => return Some(after + 1),
+            Some(c) if c.is_whitespace() => return text[after ..].find('>').map(|j| after + j + 1),
+            _ => from = after
+        }
+    }
+    None
+}
+
+// Inhalt des ersten Elements name (ohne Kind-Elemente)
+fn element(text: &str, name: &str) -> Option<String> {
+    let start = find_element(text, name)?;
+    let end = text[start ..].find(&format!("</{}>", name))?;
+    let value = xml_unescape(text[start .. start + end].trim());
+    Some(value).filter(|v| !v.is_empty())
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('&') {
+        res.push_str(&rest[.. i]);
+        rest = &rest[i ..];
+
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break
+        };
+        let decoded = match &rest[1 .. end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(std::char::from_u32)
+        };
+
+        match decoded {
+            Some(c) => {
+                res.push(c);
+                rest = &rest[end + 1 ..];
+            },
+            None => {
+                res.push('&');
+                rest = &rest[1 ..];
+            }
+        }
+    }
+
+    res.push_str(rest);
+    res
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// %XX in Bytes umwandeln, ungültige Angaben bleiben stehen
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1 .. i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                res.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                res.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+// alle Zeichen außer Buchstaben, Ziffern, -._~ und / als %XX schreiben
+fn percent_encode(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        match byte {
+            b'a' ..= b'z' | b'A' ..= b'Z' | b'0' ..= b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => res.push(byte as char),
+            _ => { let _ = write!(res, "%{:02X}", byte); }
+        }
+    }
+
+    res
+}
+
+// Pfad von file relativ zum Ordner base, None, falls
+// es keinen gibt (z.B. auf einem anderen Laufwerk)
+fn relative_path(file: &Path, base: &Path) -> Option<PathBuf> {
+    if !file.is_absolute() || !base.is_absolute() { return None; }
+
+    let file : Vec<Component> = file.components().collect();
+    let base : Vec<Component> = base.components().collect();
+
+    // Laufwerk bzw. Wurzel müssen übereinstimmen
+    if file.first() != base.first() { return None; }
+
+    let common = file.iter().zip(base.iter()).take_while(|(a, b)| a == b).count();
+
+    let mut res = PathBuf::new();
+    for _ in common .. base.len() {
+        res.push("..");
+    }
+    for component in &file[common ..] {
+        res.push(component.as_os_str());
+    }
+    Some(res)
+}
+
+// schreibt die Einträge als Playlist, wahlweise mit Pfaden
+// relativ zum Ordner der Playlist (sofern möglich)
+pub fn write_playlist(path: &str, format: PlaylistFormat, entries: &[PlaylistEntry], relative: bool) -> Result<()> {
+    let base = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let base = env::current_dir()?.join(base);
+
+    // Pfad jedes Eintrags, wie er in die Playlist geschrieben wird
+    let locations : Vec<(String, bool)> = entries.iter().map(|e| {
+        let file = Path::new(&e.path);
+        match relative_path(file, &base).filter(|_| relative) {
+            Some(rel) => (rel.to_string_lossy().into_owned(), true),
+            None => (e.path.clone(), false)
+        }
+    }).collect();
+
+    // ohne Titel wird der Dateiname angezeigt, Zeilenumbrüche würden
+    // bei M3U und PLS den Aufbau der Datei zerstören
+    let title = |e: &PlaylistEntry| e.title.clone().unwrap_or_else(|| {
+        Path::new(&e.path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+    }).replace(['\r', '\n'], " ");
+
+    let mut text = String::new();
+
+    match format {
+        PlaylistFormat::M3u => {
+            text.push_str("#EXTM3U\n");
+            for (e, (location, _)) in entries.iter().zip(&locations) {
+                let duration = e.duration.map(|d| d.round() as i64).unwrap_or(-1);
+                let _ = write!(text, "#EXTINF:{},{}\n{}\n", duration, title(e), location);
+            }
+        },
+        PlaylistFormat::Pls => {
+            text.push_str("[playlist]\n");
+            for (i, (e, (location, _))) in entries.iter().zip(&locations).enumerate() {
+                let duration = e.duration.map(|d| d.round() as i64).unwrap_or(-1);
+                let _ = write!(text, "File{n}={}\nTitle{n}={}\nLength{n}={}\n", location, title(e), duration, n = i + 1);
+            }
+            let _ = write!(text, "NumberOfEntries={}\nVersion=2\n", entries.len());
+        },
+        PlaylistFormat::Xspf => {
+            text.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n");
+            for (e, (location, rel)) in entries.iter().zip(&locations) {
+                // relative URIs bleiben relativ, absolute Pfade werden zu file://
+                let location = if cfg!(windows) { location.replace('\\', "/") } else { location.clone() };
+                let uri = match (*rel, location.starts_with('/')) {
+                    (true, _) => percent_encode(&location),
+                    (false, true) => format!("file://{}", percent_encode(&location)),
+                    (false, false) => format!("file:///{}", percent_encode(&location))
+                };
+                let _ = writeln!(text, "    <track>");
+                let _ = writeln!(text, "      <location>{}</location>", xml_escape(&uri));
+                let _ = writeln!(text, "      <title>{}</title>", xml_escape(&title(e)));
+                if let Some(duration) = e.duration {
+                    let _ = writeln!(text, "      <duration>{}</duration>", (duration * 1000.0).round() as u64);
+                }
+                let _ = writeln!(text, "    </track>");
+            }
+            text.push_str("  </trackList>\n</playlist>\n");
+        }
+    }
+
+    fs::write(path, text).map_err(|e| Error::Playlist(format!("{}: {}", path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry (path: &str, title: Option<&str>, duration: Option<f64>) -> PlaylistEntry {
+        PlaylistEntry {
+            path: path.to_string(),
+            title: title.map(|t| t.to_string()),
+            duration: duration
+        }
+    }
+
+    // eigener Ordner je Test, da die Tests parallel laufen
+    fn test_dir (name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("audio_engine_playlist_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn m3u_extinf () {
+        let text = "\u{feff}#EXTM3U\r\n\
+            # Kommentar\r\n\
+            #EXTINF:123,Artist - Title\r\n\
+            a.flac\r\n\
+            \r\n\
+            #EXTINF:-1 tvg-name=\"a, b\" group-title=\"x\",Stream, live\n\
+            b.mp3\n\
+            #EXTINF:bad\n\
+            c.ogg\n\
+            d.wav\n";
+
+        assert_eq!(parse_m3u(text), vec![
+            entry("a.flac", Some("Artist - Title"), Some(123.0)),
+            entry("b.mp3", Some("Stream, live"), None),
+            entry("c.ogg", None, None),
+            entry("d.wav", None, None)
+        ]);
+    }
+
+    #[test]
+    fn pls_order () {
+        let text = "[playlist]\n\
+            File3=c.mp3\n\
+            Title1=Eins\n\
+            File1=a.mp3\n\
+            Length1=61\n\
+            Title2=ohne Datei\n\
+            Length3=-1\n\
+            NumberOfEntries=3\n\
+            Version=2\n";
+
+        assert_eq!(parse_pls(text), vec![
+            entry("a.mp3", Some("Eins"), Some(61.0)),
+            entry("c.mp3", None, None)
+        ]);
+    }
+
+    #[test]
+    fn xspf_entities () {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <trackList>
+                <track>
+                  <location>file:///musik/A%20%26%20B/%C3%A4.flac</location>
+                  <creator>Tom &amp; Jerry</creator>
+                  <title>&lt;Caf&#xE9;&#33;&gt;</title>
+                  <duration>1500</duration>
+                </track>
+                <track id="2"><location>http://example.com/stream</location></track>
+                <track><title>ohne Ort</title></track>
+                <trackList/>
+              </trackList>
+            </playlist>"#;
+
+        let entries = parse_xspf(text);
+        assert_eq!(entries, vec![
+            entry("file:///musik/A%20%26%20B/%C3%A4.flac", Some("Tom & Jerry - <Café!>"), Some(1.5)),
+            entry("http://example.com/stream", None, None)
+        ]);
+
+        // file:// wird dekodiert, andere Schemata übersprungen
+        let base = Path::new("/playlists");
+        assert_eq!(resolve(&entries[0].path, base, true), Some(PathBuf::from("/musik/A & B/ä.flac")));
+        assert_eq!(resolve(&entries[1].path, base, true), None);
+        assert_eq!(resolve("file://localhost/x.mp3", base, true), Some(PathBuf::from("/x.mp3")));
+        assert_eq!(resolve("a%20b.mp3", base, true), Some(PathBuf::from("/playlists/a b.mp3")));
+        assert_eq!(resolve("a%20b.mp3", base, false), Some(PathBuf::from("/playlists/a%20b.mp3")));
+    }
+
+    #[test]
+    fn paths () {
+        assert_eq!(normalize(Path::new("/a/./b/../c")), PathBuf::from("/a/c"));
+        assert_eq!(normalize(Path::new("/a/../../b")), PathBuf::from("/b"));
+        assert_eq!(normalize(Path::new("../a/../b")), PathBuf::from("../b"));
+
+        let base = Path::new("/musik/listen");
+        assert_eq!(relative_path(Path::new("/musik/listen/a.mp3"), base), Some(PathBuf::from("a.mp3")));
+        assert_eq!(relative_path(Path::new("/musik/alben/b.mp3"), base), Some(PathBuf::from("../alben/b.mp3")));
+        assert_eq!(relative_path(Path::new("/c.mp3"), base), Some(PathBuf::from("../../c.mp3")));
+        assert_eq!(relative_path(Path::new("d.mp3"), base), None);
+
+        assert_eq!(percent_decode(&percent_encode("/a b/ä&%.mp3")), "/a b/ä&%.mp3");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn write_read () {
+        let dir = test_dir("write_read");
+        let music = dir.join("musik");
+        let lists = dir.join("listen");
+        fs::create_dir_all(&lists).unwrap();
+        let file = |name: &str| music.join(name).to_string_lossy().into_owned();
+
+        let entries = vec![
+            entry(&file("a b&c.flac"), Some("X & <Y>"), Some(3.0)),
+            entry(&file("ä/d.mp3"), None, None)
+        ];
+
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Pls, PlaylistFormat::Xspf] {
+            for relative in [false, true] {
+                let path = lists.join(format!("liste.{}", format.name()));
+                let path = path.to_str().unwrap();
+                write_playlist(path, format, &entries, relative).unwrap();
+
+                let text = fs::read_to_string(path).unwrap();
+                assert_eq!(text.contains("../musik/"), relative, "{}", text);
+
+                // ohne Titel wird der Dateiname geschrieben
+                let read = read_playlist(path).unwrap();
+                assert_eq!(read, vec![
+                    entries[0].clone(),
+                    entry(&file("ä/d.mp3"), Some("d"), None)
+                ], "{}", text);
+            }
+        }
+
+        // Zeilenumbrüche im Titel
+        let path = lists.join("umbruch.m3u");
+        let path = path.to_str().unwrap();
+        write_playlist(path, PlaylistFormat::M3u, &[entry(&file("a.mp3"), Some("a\nb\r\nc"), None)], false).unwrap();
+        let read = read_playlist(path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].title.as_deref(), Some("a b  c"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn nested () {
+        let dir = test_dir("nested");
+        fs::write(dir.join("a.m3u"), "a.mp3\nb.pls\na.m3u\nhttp://example.com/x\n").unwrap();
+        fs::write(dir.join("b.pls"), "[playlist]\nFile1=sub/b.mp3\nFile2=a.m3u\n").unwrap();
+
+        let read = read_playlist(dir.join("a.m3u").to_str().unwrap()).unwrap();
+        let paths : Vec<PathBuf> = read.into_iter().map(|e| PathBuf::from(e.path)).collect();
+        assert_eq!(paths, vec![dir.join("a.mp3"), dir.join("sub/b.mp3")]);
+
+        assert!(read_playlist(dir.join("fehlt.m3u").to_str().unwrap()).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
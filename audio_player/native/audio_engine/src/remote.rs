@@ -0,0 +1,537 @@
+//+-------------------------------------------------------------+
+//| remote.rs - HTTP-Server mit einer JSON-API, über die sich   |
+//|          der Player fernsteuern lässt (z.B. von einer       |
+//|          Webseite aus). Die Befehle entsprechen denen des   |
+//|          Node-Moduls, Änderungen werden über einen          |
+//|          WebSocket (/api/events) gemeldet.                  |
+//|                                                             |
+//|   GET  /api/state, /api/curr_tag,                           |
+//|        /api/playlist?order=display|playback                 |
+//|   POST /api/play, /api/pause, /api/skip, /api/prev          |
+//|   POST /api/skip_to { id }, /api/seek { position },         |
+//|        /api/volume { volume }, /api/add_to_queue { path },  |
+//|        /api/play_next { path }, /api/play_now { path },     |
+//|        /api/import_m3u { path } (M3U, PLS oder XSPF),       |
+//|        /api/remove { id }, /api/move { id, to },            |
+//|        /api/insert { index, path }, /api/clear,             |
+//|        /api/clear_upcoming, /api/dedupe,                    |
+//|        /api/repeat { mode: "off" | "one" | "all" },         |
+//|        /api/shuffle { mode: "off" | "track" | "album" }     |
+//|   Einträge der Playlist werden über ihre id angesprochen,   |
+//|   Befehle, die Titel einfügen, antworten mit deren id.      |
+//|   POST erwartet "Content-Type: application/json", der       |
+//|   WebSocket ohne Token nur Anfragen derselben Herkunft.     |
+//+-------------------------------------------------------------+
+
+use std::{
+    thread, thread::JoinHandle, time::{Duration, Instant},
+    io::{self, Read, Cursor},
+    sync::{ mpsc::{channel, Receiver, Sender, RecvTimeoutError}, Arc, Mutex, atomic::{AtomicBool, Ordering} }
+};
+
+use tiny_http::{Server, Request, Response, Header, Method, ReadWrite};
+use tungstenite::{WebSocket, Message, protocol::Role};
+use serde_json::{json, Value};
+use sha1::{Sha1, Digest};
+
+use crate::audio_reader::Tags;
+use crate::events::Event;
+use crate::player::{Player, RepeatMode, ShuffleMode, PlaylistOrder};
+use crate::error::{Error, Result};
+
+// Adresse, falls keine angegeben wird (nur lokal erreichbar)
+pub const DEFAULT_ADDRESS : &str = "127.0.0.1:6680";
+
+// Intervall, in dem Anfragen und Ereignisse geprüft werden
+const POLL : Duration = Duration::from_millis(100);
+
+// Anzahl der Threads, die Anfragen beantworten, damit eine langsame
+// Anfrage (z.B. /api/playlist) die übrigen nicht aufhält
+const WORKERS : usize = 4;
+
+// Abstand der Pings, an denen getrennte WebSockets erkannt werden
+const PING : Duration = Duration::from_secs(15);
+
+// größter angenommener Body einer Anfrage (in Bytes)
+const MAX_BODY : u64 = 64 * 1024;
+
+// wird an Sec-WebSocket-Key angehängt (s. RFC 6455)
+const WS_GUID : &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+type Reply = Response<Cursor<Vec<u8>>>;
+type Socket = WebSocket<Box<dyn ReadWrite + Send>>;
+
+// alle Pfade der API, andere Methoden werden mit 405 beantwortet
+const ROUTES : [&str; 22] = [
+    "/api/state", "/api/playlist", "/api/curr_tag", "/api/play", "/api/pause", "/api/skip",
+    "/api/prev", "/api/skip_to", "/api/seek", "/api/volume", "/api/add_to_queue", "/api/play_next",
+    "/api/play_now", "/api/import_m3u", "/api/remove", "/api/move", "/api/insert", "/api/clear",
+    "/api/clear_upcoming", "/api/dedupe", "/api/repeat", "/api/shuffle"
+];
+
+// Ergebnis eines Befehls, Fehler mit HTTP-Statuscode
+type Answer = std::result::Result<Value, (u16, String)>;
+
+//+--------------------------------
+//| struct RemoteServer
+//|     - beantwortet die Anfragen in
+//|       WORKERS eigenen Threads, jeder
+//|       WebSocket hat einen weiteren.
+//|       Wird der Server freigegeben,
+//|       enden alle Threads
+
+pub struct RemoteServer {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>
+}
+
+impl RemoteServer {
+    // öffnet den Port (z.B. DEFAULT_ADDRESS). Ist ein Token angegeben, muss
+    // jede Anfrage es als "Authorization: Bearer <token>" oder als Parameter
+    // ?token=<token> mitschicken (Browser setzen bei WebSockets keine Header).
+    // Nur dann sind Anfragen von anderen Seiten erlaubt (CORS), sonst
+    // könnte jede Webseite im Browser den Player steuern
+    pub fn start(player: Arc<Player>, address: &str, token: Option<String>) -> Result<RemoteServer> {
+        let server = Server::http(address)
+            .map_err(|e| Error::Io(io::Error::other(e.to_string())))?;
+
+        let sockets : Arc<Mutex<Vec<Sender<String>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        // der Empfänger sperrt nur die Liste der WebSockets, nie den Player (s. events.rs)
+        let listeners = sockets.clone();
+        player.subscribe(Box::new(move |event: &Event| {
+            let message = event_json(event).to_string();
+            listeners.lock().unwrap().retain(|s| s.send(message.clone()).is_ok());
+        }));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = Arc::new(server);
+
+        // tiny_http verteilt die Anfragen auf alle wartenden Threads
+        let threads = (0 .. WORKERS).map(|_| {
+            let server = server.clone();
+            let player = player.clone();
+            let token = token.clone();
+            let sockets = sockets.clone();
+            let stop = stop.clone();
+            thread::spawn(move || serve(&server, &player, token.as_deref(), &sockets, &stop))
+        }).collect();
+
+        Ok(RemoteServer {
+            stop: stop,
+            threads: threads
+        })
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+// beantwortet Anfragen, bis der Server beendet wird (s. WORKERS)
+fn serve(server: &Server, player: &Player, token: Option<&str>, sockets: &Mutex<Vec<Sender<String>>>, stopped: &Arc<AtomicBool>) {
+    let mut streams = Vec::new();
+    let cors = token.is_some();
+
+    while !stopped.load(Ordering::Relaxed) {
+        let mut request = match server.recv_timeout(POLL) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(_) => break
+        };
+
+        // Vorabanfrage des Browsers, kommt ohne Token
+        if cors && *request.method() == Method::Options {
+            let _ = request.respond(preflight());
+            continue;
+        }
+
+        if !authorized(&request, token) {
+            let _ = request.respond(allow_origin(reply(401, &json!({ "error": "missing or wrong token" }))));
+            continue;
+        }
+
+        if path(request.url()) == "/api/events" {
+            let key = match handshake(&request, cors) {
+                Ok(key) => key,
+                Err(res) => {
+                    let _ = request.respond(res);
+                    continue;
+                }
+            };
+
+            // erst anmelden, dann den Zustand senden,
+            // damit keine Änderung verloren geht
+            let (send, messages) = channel();
+            sockets.lock().unwrap().push(send);
+            let state = state_json(player).to_string();
+
+            let socket = upgrade(request, &key);
+            let stop = stopped.clone();
+            streams.push(thread::spawn(move || push(socket, messages, stop, state)));
+        } else {
+            let res = match handle(player, &mut request) {
+                Ok(value) => reply(200, &value),
+                Err((status, message)) => reply(status, &json!({ "error": message }))
+            };
+            let _ = request.respond(if cors { allow_origin(res) } else { res });
+        }
+    }
+
+    for stream in streams {
+        let _ = stream.join();
+    }
+}
+
+// führt den Befehl einer Anfrage aus, GET liest nur,
+// POST verändert und erwartet ein JSON-Objekt als Body
+fn handle(player: &Player, request: &mut Request) -> Answer {
+    let path = path(request.url()).to_string();
+
+    let body = if *request.method() == Method::Post {
+        read_body(request)?
+    } else {
+        Value::Null
+    };
+
+    match (request.method(), path.as_str()) {
+        (Method::Get, "/api/state") => Ok(state_json(player)),
+        (Method::Get, "/api/playlist") => order(request.url()).map(|order| playlist_json(player, order)),
+        (Method::Get, "/api/curr_tag") => Ok(player.curr_tags().map(|t| tag_json(&t)).unwrap_or(Value::Null)),
+        (Method::Post, "/api/play") => player.play().map(|_| done()).map_err(failed),
+        (Method::Post, "/api/pause") => player.pause().map(|_| done()).map_err(failed),
+        (Method::Post, "/api/skip") => { player.skip(); Ok(done()) },
+        (Method::Post, "/api/prev") => { player.prev(); Ok(done()) },
+        (Method::Post, "/api/skip_to") => found(player.skip_to(id(&body, "id")?)),
+        (Method::Post, "/api/seek") => { player.seek(number(&body, "position")?); Ok(done()) },
+        (Method::Post, "/api/volume") => { player.set_volume(number(&body, "volume")? as f32); Ok(done()) },
+        (Method::Post, "/api/add_to_queue") => Ok(added(player.add_to_queue(string(&body, "path")?))),
+        (Method::Post, "/api/play_next") => Ok(added(player.play_next(string(&body, "path")?))),
+        (Method::Post, "/api/play_now") => Ok(added(player.play_now(string(&body, "path")?))),
+        (Method::Post, "/api/import_m3u") => {
+            player.import_playlist(&string(&body, "path")?).map(|ids| json!({ "ok": true, "ids": ids })).map_err(failed)
+        },
+        (Method::Post, "/api/remove") => found(player.remove(id(&body, "id")?)),
+        (Method::Post, "/api/move") => found(player.move_track(id(&body, "id")?, index(&body, "to")?)),
+        (Method::Post, "/api/insert") => {
+            player.insert(index(&body, "index")?, string(&body, "path")?)
+                .map(added)
+                .ok_or_else(|| (400, "index is not in the playlist".to_string()))
+        },
+        (Method::Post, "/api/clear") => { player.clear(); Ok(done()) },
+        (Method::Post, "/api/clear_upcoming") => { player.clear_upcoming(); Ok(done()) },
+        (Method::Post, "/api/dedupe") => Ok(json!({ "ok": true, "removed": player.dedupe() })),
+        (Method::Post, "/api/repeat") => {
+            let mode = string(&body, "mode")?;
+            let mode = RepeatMode::from_name(&mode).ok_or_else(|| (400, format!("unknown repeat mode \"{}\"", mode)))?;
+            player.set_repeat(mode);
+            Ok(done())
+        },
+        (Method::Post, "/api/shuffle") => {
+            let mode = string(&body, "mode")?;
+            let mode = ShuffleMode::from_name(&mode).ok_or_else(|| (400, format!("unknown shuffle mode \"{}\"", mode)))?;
+            player.set_shuffle(mode);
+            Ok(done())
+        },
+        (_, path) if ROUTES.contains(&path) => Err((405, "method not allowed".to_string())),
+        _ => Err((404, "not found".to_string()))
+    }
+}
+
+// prüft den Handshake eines WebSockets (s. RFC 6455, Abschnitt 4.2.1) und
+// gibt Sec-WebSocket-Key an. Für WebSockets gilt CORS nicht, ohne Token wird
+// daher nur ein Client ohne Origin (kein Browser) oder mit der Herkunft
+// des Servers selbst angenommen, sonst könnte jede Webseite mitlesen
+fn handshake(request: &Request, cors: bool) -> std::result::Result<String, Reply> {
+    let error = |status, message: &str| reply(status, &json!({ "error": message }));
+
+    if !cors && !same_origin(request) {
+        return Err(error(403, "WebSocket from another origin requires a token"));
+    }
+
+    let websocket = header(request, "Upgrade")
+        .map(|u| u.split(',').any(|p| p.trim().eq_ignore_ascii_case("websocket")))
+        .unwrap_or(false);
+    if *request.method() != Method::Get || !websocket {
+        return Err(error(400, "expected a WebSocket handshake"));
+    }
+
+    if header(request, "Sec-WebSocket-Version").map(|v| v.trim()) != Some("13") {
+        return Err(error(426, "unsupported WebSocket version")
+            .with_header(Header::from_bytes(&b"Sec-WebSocket-Version"[..], &b"13"[..]).unwrap()));
+    }
+
+    header(request, "Sec-WebSocket-Key")
+        .map(|k| k.trim().to_string())
+        .ok_or_else(|| error(400, "expected a WebSocket handshake"))
+}
+
+// eine Anfrage ohne Origin (z.B. aus einem Skript) oder von einer
+// Seite, die der Server selbst ausliefert (Origin passt zu Host)
+fn same_origin(request: &Request) -> bool {
+    let origin = match header(request, "Origin") {
+        Some(origin) => origin,
+        None => return true
+    };
+
+    let origin = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://"));
+    match (origin, header(request, "Host")) {
+        (Some(origin), Some(host)) => origin.eq_ignore_ascii_case(host),
+        _ => false
+    }
+}
+
+// übernimmt die Verbindung als WebSocket (s. RFC 6455, Abschnitt 4.2.2)
+fn upgrade(request: Request, key: &str) -> Socket {
+    let mut sha1 = Sha1::default();
+    sha1.input(key.as_bytes());
+    sha1.input(WS_GUID.as_bytes());
+    let accept = base64::encode(&sha1.result());
+
+    let response = Response::empty(101)
+        .with_header(Header::from_bytes(&b"Sec-WebSocket-Accept"[..], accept.as_bytes()).unwrap());
+
+    let stream = request.upgrade("websocket", response);
+    WebSocket::from_raw_socket(stream, Role::Server, None)
+}
+
+// Wert eines Headers der Anfrage
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+// sendet zuerst den Zustand, dann jedes Ereignis als JSON-Objekt,
+// bis die Verbindung getrennt oder der Server beendet wird.
+// Nachrichten des Browsers werden nicht gelesen.
+fn push(mut socket: Socket, messages: Receiver<String>, stop: Arc<AtomicBool>, state: String) {
+    let mut last_ping = Instant::now();
+
+    if socket.write_message(Message::Text(state)).is_err() {
+        return;
+    }
+
+    while !stop.load(Ordering::Relaxed) {
+        let res = match messages.recv_timeout(POLL) {
+            Ok(message) => socket.write_message(Message::Text(message)),
+            Err(RecvTimeoutError::Timeout) if last_ping.elapsed() >= PING => {
+                last_ping = Instant::now();
+                socket.write_message(Message::Ping(Vec::new()))
+            },
+            Err(RecvTimeoutError::Timeout) => Ok(()),
+            Err(RecvTimeoutError::Disconnected) => break
+        };
+
+        if res.is_err() {
+            return;
+        }
+    }
+
+    let _ = socket.close(None);
+    let _ = socket.write_pending();
+}
+
+// prüft das Token im Header Authorization oder im Parameter token
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return true
+    };
+
+    let bearer = format!("Bearer {}", token);
+    let header = request.headers().iter()
+        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == bearer);
+
+    header || query(request.url(), "token").as_deref() == Some(token)
+}
+
+// liest den Body als JSON-Objekt. Andere Typen (z.B. text/plain aus einem
+// Formular) sendet ein Browser ohne Vorabanfrage, sie werden abgelehnt
+fn read_body(request: &mut Request) -> std::result::Result<Value, (u16, String)> {
+    let json = header(request, "Content-Type")
+        .and_then(|t| t.split(';').next())
+        .map(|t| t.trim().eq_ignore_ascii_case("application/json"))
+        .unwrap_or(false);
+
+    if !json {
+        return Err((415, "expected Content-Type: application/json".to_string()));
+    }
+
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY).read_to_string(&mut body)
+        .map_err(|e| (400, e.to_string()))?;
+
+    if body.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(&body).map_err(|e| (400, format!("invalid JSON: {}", e)))
+}
+
+// Antwort mit einem JSON-Wert
+fn reply(status: u16, value: &Value) -> Reply {
+    Response::from_data(value.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+// macht eine Antwort auch von anderen Seiten aus lesbar (nur mit Token)
+fn allow_origin(res: Reply) -> Reply {
+    res.with_header(Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap())
+}
+
+// Antwort auf OPTIONS, erlaubt Anfragen von anderen Seiten (CORS, nur mit Token)
+fn preflight() -> Reply {
+    allow_origin(Response::from_data(Vec::new()))
+        .with_status_code(204)
+        .with_header(Header::from_bytes(&b"Access-Control-Allow-Methods"[..], &b"GET, POST"[..]).unwrap())
+        .with_header(Header::from_bytes(&b"Access-Control-Allow-Headers"[..], &b"Authorization, Content-Type"[..]).unwrap())
+}
+
+// Antwort auf einen erfolgreichen Befehl ohne Ergebnis
+fn done() -> Value {
+    json!({ "ok": true })
+}
+
+// Antwort auf einen Befehl mit einem Eintrag oder einer Position der Playlist
+fn found(ok: bool) -> Answer {
+    if ok { Ok(done()) } else { Err((400, "id or index is not in the playlist".to_string())) }
+}
+
+// Antwort auf einen Befehl, der einen Titel einfügt
+fn added(id: u32) -> Value {
+    json!({ "ok": true, "id": id })
+}
+
+fn failed(e: Error) -> (u16, String) {
+    (500, e.to_string())
+}
+
+fn number(body: &Value, name: &str) -> std::result::Result<f64, (u16, String)> {
+    body[name].as_f64().ok_or_else(|| (400, format!("expected a number \"{}\"", name)))
+}
+
+fn index(body: &Value, name: &str) -> std::result::Result<usize, (u16, String)> {
+    body[name].as_u64().map(|i| i as usize).ok_or_else(|| (400, format!("expected an index \"{}\"", name)))
+}
+
+fn id(body: &Value, name: &str) -> std::result::Result<u32, (u16, String)> {
+    body[name].as_u64().map(|i| i as u32).ok_or_else(|| (400, format!("expected an id \"{}\"", name)))
+}
+
+fn string(body: &Value, name: &str) -> std::result::Result<String, (u16, String)> {
+    body[name].as_str().map(|s| s.to_string()).ok_or_else(|| (400, format!("expected a string \"{}\"", name)))
+}
+
+// Pfad einer URL ohne Parameter
+fn path(url: &str) -> &str {
+    url.split('?').next().unwrap_or("")
+}
+
+// Wert eines Parameters einer URL (?name=wert&...)
+fn query(url: &str, name: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+
+    query.split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+// ersetzt %XX und + in einem Parameter
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1 ..= i + 2]).ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => { res.push(b); i += 3; continue; },
+                    None => res.push(b'%')
+                }
+            },
+            b'+' => res.push(b' '),
+            b => res.push(b)
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+// Zustand des Players, wird auch als erste Nachricht eines WebSockets gesendet
+fn state_json(player: &Player) -> Value {
+    json!({
+        "event": "state",
+        "playing": player.playing(),
+        "path": player.curr_playing(),
+        "id": player.curr_id(),
+        "index": player.curr_index(),
+        "tag": player.curr_tags().map(|t| tag_json(&t)),
+        "position": player.position(),
+        "duration": player.duration(),
+        "volume": player.volume(),
+        "repeat": player.repeat().name(),
+        "shuffle": player.shuffle().name()
+    })
+}
+
+// Reihenfolge der Playlist aus dem Parameter order, sonst wie angezeigt
+fn order(url: &str) -> std::result::Result<PlaylistOrder, (u16, String)> {
+    match query(url, "order").as_deref() {
+        None | Some("display") => Ok(PlaylistOrder::Display),
+        Some("playback") => Ok(PlaylistOrder::Playback),
+        Some(order) => Err((400, format!("unknown order \"{}\"", order)))
+    }
+}
+
+// alle Einträge der Playlist mit id und Pfad
+fn playlist_json(player: &Player, order: PlaylistOrder) -> Value {
+    Value::Array(player.playlist(order).into_iter().map(|(track, tags)| {
+        let mut entry = tag_json(&tags);
+        entry["id"] = json!(track.id);
+        entry["path"] = json!(track.path);
+        entry
+    }).collect())
+}
+
+// entspricht tag_to_js im Node-Modul, leere Angaben sind null
+fn tag_json(tags: &Tags) -> Value {
+    let str_or_null = |s: String| if s.is_empty() { Value::Null } else { Value::String(s) };
+
+    json!({
+        "artist": str_or_null(tags.artist()),
+        "album": str_or_null(tags.album()),
+        "title": str_or_null(tags.title())
+    })
+}
+
+// entspricht event_to_js im Node-Modul, der Name steht unter "event"
+fn event_json(event: &Event) -> Value {
+    let mut res = json!({ "event": event.name() });
+
+    match event {
+        Event::TrackStarted { path, id, tags } => {
+            res["path"] = json!(path);
+            res["id"] = json!(id);
+            res["tag"] = tag_json(tags);
+        },
+        Event::TrackEnded { path } => res["path"] = json!(path),
+        Event::PlaybackState { playing } => res["playing"] = json!(playing),
+        Event::Error { message } => res["message"] = json!(message),
+        Event::RepeatChanged { mode } => res["mode"] = json!(mode.name()),
+        Event::ShuffleChanged { mode } => res["mode"] = json!(mode.name()),
+        Event::QueueChanged | Event::EndOfQueue => {}
+    }
+
+    res
+}
@@ -0,0 +1,242 @@
+//+-------------------------------------------------------------+
+//| session.rs - Speichern und Laden der Sitzung: Playlist,     |
+//|          aktueller Titel mit Position, Lautstärke sowie     |
+//|          Wiederholung und zufällige Wiedergabe. Die Datei   |
+//|          ist eine Textdatei mit einer Angabe pro Zeile:     |
+//|                                                             |
+//|   croissant-session 1                                       |
+//|   volume 0.800                                              |
+//|   repeat all                                                |
+//|   shuffle track                                             |
+//|   position 83.250                                           |
+//|   order 2 0 1                                               |
+//|   played /musik/a.flac                                      |
+//|   current /musik/b.flac                                     |
+//|   queued /musik/c.flac                                      |
+//|                                                             |
+//|   Die erste Zeile gibt die Version des Formats an. Einträge |
+//|   stehen in der Reihenfolge der Wiedergabe, order gibt bei  |
+//|   zufälliger Wiedergabe die Reihenfolge der Playlist als    |
+//|   Positionen darin an. Unbekannte Angaben werden ignoriert. |
+//+-------------------------------------------------------------+
+
+use std::{fs, fmt::Write, path::Path};
+
+use crate::player::{RepeatMode, ShuffleMode};
+use crate::error::{Error, Result};
+
+// Kennung in der ersten Zeile
+const MAGIC : &str = "croissant-session";
+
+// Version des Formats, ältere Versionen können weiterhin gelesen werden
+pub const VERSION : u32 = 1;
+
+//+--------------------------------
+//| struct Session
+//|     - gespeicherter Zustand
+//|       eines Players
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    // Lautstärke zwischen 0 und 1
+    pub volume: f32,
+    pub repeat: RepeatMode,
+    pub shuffle: ShuffleMode,
+    // Position (in Sekunden) im aktuellen Titel
+    pub position: f64,
+    // bereits gespielte Titel in der gespielten Reihenfolge
+    pub played: Vec<String>,
+    // aktueller Titel, wird beim Laden an der Position fortgesetzt
+    pub current: Option<String>,
+    // folgende Titel in der Reihenfolge, in der sie gespielt werden
+    pub queued: Vec<String>,
+    // Reihenfolge der Playlist bei zufälliger Wiedergabe, als Positionen
+    // in played, current und queued (leer ohne zufällige Wiedergabe)
+    pub order: Vec<usize>
+}
+
+impl Session {
+    // liest eine gespeicherte Sitzung
+    pub fn read(path: &str) -> Result<Session> {
+        let not_session = || Error::Session(format!("{}: not a session file", path));
+        let text = String::from_utf8(fs::read(path)?).map_err(|_| not_session())?;
+        let invalid = |n: usize, msg: &str| Error::Session(format!("{}: line {}: {}", path, n + 1, msg));
+
+        let mut lines = text.lines().enumerate();
+
+        // Kennung und Version
+        let version = lines.next()
+            .and_then(|(_, l)| l.strip_prefix(MAGIC))
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or_else(not_session)?;
+        if version > VERSION {
+            return Err(Error::Session(format!("{}: unsupported version {}", path, version)));
+        }
+
+        let mut session = Session {
+            volume: 1.0,
+            repeat: RepeatMode::Off,
+            shuffle: ShuffleMode::Off,
+            position: 0.0,
+            played: Vec::new(),
+            current: None,
+            queued: Vec::new(),
+            order: Vec::new()
+        };
+
+        for (n, line) in lines {
+            if line.is_empty() { continue; }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+
+            match key {
+                "volume" => {
+                    session.volume = value.parse().ok()
+                        .filter(|v: &f32| (0.0 ..= 1.0).contains(v))
+                        .ok_or_else(|| invalid(n, "invalid volume"))?;
+                },
+                "position" => {
+                    session.position = value.parse().ok()
+                        .filter(|p: &f64| p.is_finite() && *p >= 0.0)
+                        .ok_or_else(|| invalid(n, "invalid position"))?;
+                },
+                "repeat" => session.repeat = RepeatMode::from_name(value).ok_or_else(|| invalid(n, "unknown repeat mode"))?,
+                "shuffle" => session.shuffle = ShuffleMode::from_name(value).ok_or_else(|| invalid(n, "unknown shuffle mode"))?,
+                "order" => {
+                    session.order = value.split_whitespace()
+                        .map(|i| i.parse())
+                        .collect::<std::result::Result<_, _>>()
+                        .map_err(|_| invalid(n, "invalid order"))?;
+                },
+                "played" => session.played.push(value.to_string()),
+                "current" => session.current = Some(value.to_string()),
+                "queued" => session.queued.push(value.to_string()),
+                // Angaben späterer Versionen
+                _ => {}
+            }
+        }
+
+        Ok(session)
+    }
+
+    // speichert die Sitzung, zuerst in eine temporäre Datei, damit bei einem
+    // Absturz während des Schreibens die vorherige Sitzung erhalten bleibt
+    pub fn write(&self, path: &str) -> Result<()> {
+        let mut text = format!("{} {}\n", MAGIC, VERSION);
+
+        let _ = writeln!(text, "volume {:.3}", self.volume);
+        let _ = writeln!(text, "repeat {}", self.repeat.name());
+        let _ = writeln!(text, "shuffle {}", self.shuffle.name());
+        let _ = writeln!(text, "position {:.3}", self.position);
+
+        if !self.order.is_empty() {
+            let order : Vec<String> = self.order.iter().map(|i| i.to_string()).collect();
+            let _ = writeln!(text, "order {}", order.join(" "));
+        }
+
+        // Zeilenumbrüche in Dateinamen lassen sich nicht speichern
+        for path in &self.played {
+            let _ = writeln!(text, "played {}", path);
+        }
+        if let Some(path) = &self.current {
+            let _ = writeln!(text, "current {}", path);
+        }
+        for path in &self.queued {
+            let _ = writeln!(text, "queued {}", path);
+        }
+
+        let tmp = format!("{}.tmp", path);
+        if let Some(dir) = Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // eigene Datei je Test, da die Tests parallel laufen
+    fn test_file (name: &str) -> String {
+        env::temp_dir()
+            .join(format!("audio_engine_session_{}_{}", name, std::process::id()))
+            .to_string_lossy().into_owned()
+    }
+
+    fn session () -> Session {
+        Session {
+            volume: 0.8,
+            repeat: RepeatMode::All,
+            shuffle: ShuffleMode::Album,
+            position: 83.25,
+            played: vec!["/musik/a b.flac".to_string()],
+            current: Some("/musik/ä/b.mp3".to_string()),
+            queued: vec!["/musik/c.ogg".to_string(), "/musik/a b.flac".to_string()],
+            order: vec![2, 0, 3, 1]
+        }
+    }
+
+    // liest text als Sitzung
+    fn read (name: &str, text: &[u8]) -> Result<Session> {
+        let path = test_file(name);
+        fs::write(&path, text).unwrap();
+        let session = Session::read(&path);
+        let _ = fs::remove_file(&path);
+        session
+    }
+
+    #[test]
+    fn write_read () {
+        let path = test_file("write_read");
+
+        for session in &[session(), Session { current: None, order: vec![], shuffle: ShuffleMode::Off, ..session() }] {
+            session.write(&path).unwrap();
+            assert_eq!(Session::read(&path).unwrap(), *session);
+        }
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn versions () {
+        // ältere Versionen und unbekannte Angaben werden gelesen
+        let text = "croissant-session 0\nvolume 0.5\nlyrics x\n\ncurrent /a.mp3\n";
+        let session = read("older", text.as_bytes()).unwrap();
+        assert_eq!((session.volume, session.current.as_deref()), (0.5, Some("/a.mp3")));
+
+        let text = format!("croissant-session {}\n", VERSION + 1);
+        assert!(matches!(read("newer", text.as_bytes()), Err(Error::Session(_))));
+    }
+
+    #[test]
+    fn invalid () {
+        let texts : &[&[u8]] = &[
+            b"",
+            b"croissant-session\n",
+            b"croissant-session x\n",
+            b"[playlist]\nFile1=a.mp3\n",
+            b"\xff\xfe\x00garbage",
+            b"croissant-session 1\nvolume laut\n",
+            b"croissant-session 1\nvolume 2\n",
+            b"croissant-session 1\nvolume NaN\n",
+            b"croissant-session 1\nposition -1\n",
+            b"croissant-session 1\nposition inf\n",
+            b"croissant-session 1\nrepeat sometimes\n",
+            b"croissant-session 1\nshuffle x\n",
+            b"croissant-session 1\norder 1 -2\n"
+        ];
+
+        for (i, text) in texts.iter().enumerate() {
+            let result = read(&format!("invalid_{}", i), text);
+            assert!(matches!(result, Err(Error::Session(_))), "{:?}: {:?}", String::from_utf8_lossy(text), result);
+        }
+
+        assert!(matches!(Session::read(&test_file("missing")), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,168 @@
+//+-------------------------------------------------------------+
+//| shuffle.rs - zufällige Wiedergabe. Die Playlist behält ihre |
+//|          ursprüngliche Reihenfolge (order), nur die noch    |
+//|          nicht gespielten Titel werden gemischt, einzeln    |
+//|          oder als ganze Alben (s. PlayerState::set_shuffle) |
+//+-------------------------------------------------------------+
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+    collections::HashMap
+};
+
+use crate::player::{Track, ShuffleMode};
+
+//+--------------------------------
+//| struct Shuffle
+//|     - Zustand der zufälligen Wiedergabe
+
+pub struct Shuffle {
+    // einzelne Titel oder ganze Alben (nie ShuffleMode::Off)
+    pub mode: ShuffleMode,
+    // ids aller Einträge in der Reihenfolge der Playlist
+    pub order: Vec<u32>,
+    // Album je id, fehlt es, gilt der Ordner der Datei als Album
+    albums: HashMap<u32, String>,
+    // Zustand des Zufallsgenerators (xorshift)
+    state: u64
+}
+
+impl Shuffle {
+    // der Zeitpunkt dient als Startwert des Zufallsgenerators
+    pub fn new(mode: ShuffleMode, order: Vec<u32>, albums: HashMap<u32, String>) -> Shuffle {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Shuffle {
+            mode: mode,
+            order: order,
+            albums: albums,
+            state: seed | 1
+        }
+    }
+
+    // zufällige Zahl zwischen 0 und n (ausschließlich)
+    fn random(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n.max(1) as u64) as usize
+    }
+
+    // mischt die Einträge (Fisher-Yates), bei ShuffleMode::Album bleiben
+    // die Titel eines Albums in ihrer Reihenfolge zusammen
+    pub fn mix(&mut self, tracks: Vec<Track>) -> Vec<Track> {
+        if self.mode != ShuffleMode::Album {
+            let mut tracks = tracks;
+            for i in (1 .. tracks.len()).rev() {
+                let j = self.random(i + 1);
+                tracks.swap(i, j);
+            }
+            return tracks;
+        }
+
+        // Alben in der Reihenfolge ihres ersten Titels
+        let mut albums : Vec<(String, Vec<Track>)> = Vec::new();
+        for track in tracks {
+            let album = self.album(&track);
+            match albums.iter_mut().find(|(a, _)| *a == album) {
+                Some((_, list)) => list.push(track),
+                None => albums.push((album, vec![track]))
+            }
+        }
+
+        for i in (1 .. albums.len()).rev() {
+            let j = self.random(i + 1);
+            albums.swap(i, j);
+        }
+        albums.into_iter().flat_map(|(_, list)| list).collect()
+    }
+
+    // Stelle unter den folgenden Titeln, an der ein neuer Titel gespielt
+    // wird. Bei ShuffleMode::Album wird er angehängt, um kein Album zu teilen
+    pub fn insert_position(&mut self, upcoming: usize) -> usize {
+        match self.mode {
+            ShuffleMode::Album => upcoming,
+            _ => self.random(upcoming + 1)
+        }
+    }
+
+    // Album eines Eintrags bzw. der Ordner der Datei
+    fn album(&self, track: &Track) -> String {
+        match self.albums.get(&track.id) {
+            Some(album) => album.clone(),
+            None => Path::new(&track.path).parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Einträge mit fortlaufenden ids
+    fn tracks (paths: &[&str]) -> Vec<Track> {
+        paths.iter().enumerate()
+            .map(|(i, p)| Track { id: i as u32, path: p.to_string() })
+            .collect()
+    }
+
+    // Alben in der Reihenfolge der Wiedergabe, jedes nur einmal
+    fn albums (shuffle: &Shuffle, tracks: &[Track]) -> Vec<String> {
+        let mut albums : Vec<String> = tracks.iter().map(|t| shuffle.album(t)).collect();
+        albums.dedup();
+        albums
+    }
+
+    #[test]
+    fn track_mix () {
+        let list = tracks(&["/a/1", "/a/2", "/b/1", "/b/2", "/c/1"]);
+        let mut shuffle = Shuffle::new(ShuffleMode::Track, vec![0, 1, 2, 3, 4], HashMap::new());
+
+        for _ in 0 .. 20 {
+            let mut mixed = shuffle.mix(list.clone());
+            mixed.sort_by_key(|t| t.id);
+            assert_eq!(mixed, list);
+        }
+    }
+
+    #[test]
+    fn album_mix () {
+        let list = tracks(&["/a/1", "/a/2", "/a/3", "/b/1", "/b/2", "/c/1", "/d/1", "/d/2"]);
+        let mut shuffle = Shuffle::new(ShuffleMode::Album, (0 .. 8).collect(), HashMap::new());
+
+        for _ in 0 .. 20 {
+            let mixed = shuffle.mix(list.clone());
+
+            // jedes Album zusammenhängend, seine Titel in der ursprünglichen Reihenfolge
+            let mut found = albums(&shuffle, &mixed);
+            assert_eq!(found.len(), 4);
+            found.sort_unstable();
+            assert_eq!(found, vec!["/a", "/b", "/c", "/d"]);
+
+            for album in &found {
+                let ids : Vec<u32> = mixed.iter().filter(|t| shuffle.album(t) == *album).map(|t| t.id).collect();
+                assert!(ids.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn album_tags () {
+        // das Album aus den Tags hat Vorrang vor dem Ordner
+        let list = tracks(&["/x/1", "/y/1", "/x/2", "/y/2"]);
+        let albums_by_id = (0 .. 4).map(|id| (id, if id < 2 { "A" } else { "B" }.to_string())).collect();
+        let mut shuffle = Shuffle::new(ShuffleMode::Album, (0 .. 4).collect(), albums_by_id);
+
+        for _ in 0 .. 20 {
+            let mixed = shuffle.mix(list.clone());
+            let ids : Vec<u32> = mixed.iter().map(|t| t.id).collect();
+            assert!(ids == vec![0, 1, 2, 3] || ids == vec![2, 3, 0, 1]);
+            assert_eq!(shuffle.insert_position(ids.len()), ids.len());
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//+-------------------------------------------------------------+
+//| tag_cache.rs - Zwischenspeicher für Tags und Länge der      |
+//|          Dateien in der Playlist. Beim Anzeigen der         |
+//|          Playlist muss so nicht jede Datei erneut geöffnet  |
+//|          werden, geänderte Dateien werden neu gelesen.      |
+//+-------------------------------------------------------------+
+
+use std::{
+    fs,
+    time::SystemTime,
+    sync::Mutex,
+    collections::{HashMap, HashSet}
+};
+
+use crate::audio_reader::{AudioFile, AudioProducer, Tags, Tagged};
+
+//+--------------------------------
+//| struct Entry
+//|     - Angaben zu einer Datei
+
+#[derive(Clone)]
+struct Entry {
+    // Änderungszeitpunkt der Datei beim Lesen
+    modified: Option<SystemTime>,
+    tags: Tags,
+    // Länge in Sekunden, wird erst bei Bedarf bestimmt
+    // (bei MP3-Dateien muss dafür evtl. die ganze Datei gelesen werden),
+    // None, solange sie noch nicht bestimmt wurde
+    duration: Option<Option<f64>>
+}
+
+//+--------------------------------
+//| struct TagCache
+//|     - wird zwischen allen Threads geteilt, die
+//|       Dateien werden ohne Sperre gelesen
+
+#[derive(Default)]
+pub struct TagCache {
+    entries: Mutex<HashMap<String, Entry>>
+}
+
+// Änderungszeitpunkt einer Datei, None falls sie nicht existiert
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl TagCache {
+    // gespeicherter Eintrag, falls die Datei seitdem nicht verändert wurde
+    fn cached(&self, path: &str, modified: Option<SystemTime>) -> Option<Entry> {
+        self.entries.lock().unwrap().get(path)
+            .filter(|e| e.modified == modified)
+            .cloned()
+    }
+
+    // Künstler, Album und Titel einer Datei, leer falls sie
+    // nicht geöffnet werden kann
+    pub fn tags(&self, path: &str) -> Tags {
+        let modified = modified(path);
+        if let Some(entry) = self.cached(path, modified) {
+            return entry.tags;
+        }
+
+        let tags = Tags::read(path);
+        self.entries.lock().unwrap().insert(path.to_string(), Entry {
+            modified: modified,
+            tags: tags.clone(),
+            duration: None
+        });
+        tags
+    }
+
+    // Tags und Länge (in Sekunden) einer Datei, die Länge ist
+    // None, falls die Datei nicht geöffnet werden kann
+    pub fn info(&self, path: &str) -> (Tags, Option<f64>) {
+        let modified = modified(path);
+        if let Some(Entry { tags, duration: Some(duration), .. }) = self.cached(path, modified) {
+            return (tags, duration);
+        }
+
+        let (tags, duration) = match AudioFile::open(path) {
+            Ok(file) => {
+                let rate = file.native_samplerate();
+                let duration = if rate > 0 { Some(file.length() as f64 / rate as f64) } else { None };
+                (file.tags(), duration)
+            },
+            Err(_) => (Tags::empty(), None)
+        };
+
+        self.entries.lock().unwrap().insert(path.to_string(), Entry {
+            modified: modified,
+            tags: tags.clone(),
+            duration: Some(duration)
+        });
+        (tags, duration)
+    }
+
+    // vergisst alle Dateien, die nicht in paths enthalten
+    // sind (z.B. nach dem Entfernen aus der Playlist)
+    pub fn retain<'a, I: IntoIterator<Item = &'a str>>(&self, paths: I) {
+        let keep : HashSet<&str> = paths.into_iter().collect();
+        self.entries.lock().unwrap().retain(|path, _| keep.contains(path.as_str()));
+    }
+}
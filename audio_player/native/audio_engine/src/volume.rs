@@ -0,0 +1,115 @@
+//+-------------------------------------------------------------+
+//| volume.rs - Lautstärkeregelung. Die Lautstärke wird im      |
+//|          Audio-Thread auf die Samples angewendet, wobei     |
+//|          Änderungen langsam angeglichen werden, damit keine |
+//|          Störgeräusche entstehen.                           |
+//+-------------------------------------------------------------+
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, AtomicBool, Ordering}
+};
+
+//+--------------------------------
+//| struct Volume
+//|     - gewünschte Lautstärke, wird
+//|       zwischen dem globalen Zustand
+//|       und dem Audio-Thread geteilt
+
+pub struct Volume {
+    // Lautstärke zwischen 0 und 1 (als Bits eines f32)
+    level: AtomicU32,
+    // stumm geschaltet
+    muted: AtomicBool
+}
+
+impl Volume {
+    // volle Lautstärke, nicht stumm
+    pub fn new() -> Volume {
+        Volume {
+            level: AtomicU32::new(1.0f32.to_bits()),
+            muted: AtomicBool::new(false)
+        }
+    }
+
+    // setzt die Lautstärke (wird auf 0 bis 1 begrenzt)
+    pub fn set(&self, level: f32) {
+        let level = if level.is_nan() { 0.0 } else { level.clamp(0.0, 1.0) };
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    // schaltet stumm, ohne die Lautstärke zu verändern
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    // Verstärkung, die tatsächlich angewendet werden soll
+    fn target(&self) -> f32 {
+        if self.muted() { 0.0 } else { self.get() }
+    }
+}
+
+//+--------------------------------
+//| struct GainRamp
+//|     - wendet die Lautstärke im Audio-
+//|       Thread an. Die Verstärkung folgt
+//|       der gewünschten Lautstärke für
+//|       jedes Sample ein Stück weit, so
+//|       entstehen keine Sprünge.
+
+pub struct GainRamp {
+    volume: Arc<Volume>,
+    // momentane Verstärkung
+    gain: f32,
+    // Anteil, um den sich die Verstärkung je Sample angleicht
+    coeff: f32
+}
+
+impl GainRamp {
+    pub fn new(volume: Arc<Volume>, sample_rate: u32) -> GainRamp {
+        GainRamp {
+            gain: volume.target(),
+            volume: volume,
+            coeff: GainRamp::coeff(sample_rate)
+        }
+    }
+
+    // nach etwa 10 ms ist die neue Lautstärke zu 63% erreicht
+    fn coeff(sample_rate: u32) -> f32 {
+        let time_constant = 0.01 * sample_rate as f32;
+        1.0 - (-1.0 / time_constant).exp()
+    }
+
+    // passt die Angleichung an ein Gerät mit anderer Samplingrate an
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.coeff = GainRamp::coeff(sample_rate);
+    }
+
+    // wendet die Lautstärke auf einen Buffer mit
+    // abwechselnd linkem und rechtem Kanal an
+    pub fn apply(&mut self, buffer: &mut [f32]) {
+        let target = self.volume.target();
+
+        // keine Änderung notwendig
+        if self.gain == target && target == 1.0 { return; }
+
+        for frame in buffer.chunks_mut(2) {
+            self.gain += (target - self.gain) * self.coeff;
+
+            // sehr kleine Abstände sofort angleichen
+            if (target - self.gain).abs() < 1e-5 { self.gain = target; }
+
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
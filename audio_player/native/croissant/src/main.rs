@@ -0,0 +1,458 @@
+//+-------------------------------------------------------------+
+//| main.rs - croissant: spielt Dateien, Ordner und Playlists   |
+//|          im Terminal ab, ohne Electron. Die Wiedergabe      |
+//|          übernimmt die Bibliothek audio_engine.             |
+//|                                                             |
+//|   croissant [--shuffle] [--repeat] [--device NAME]          |
+//|             [--start-at ZEIT] [--tui] [--mpd[=ADRESSE]]     |
+//|             [--http[=ADRESSE]] [--token TOKEN]              |
+//|             [--session DATEI] DATEI|ORDNER|PLAYLIST ...     |
+//|                                                             |
+//|   Mit --tui startet eine Vollbild-Oberfläche (s. tui.rs),   |
+//|   mit --mpd können MPD-Clients den Player steuern, mit      |
+//|   --http eine Webseite über die JSON-API (s. remote.rs).    |
+//|   Mit --session wird die Sitzung gespeichert und ohne       |
+//|   Dateien beim nächsten Start fortgesetzt.                  |
+//+-------------------------------------------------------------+
+
+// Felder werden im ganzen Projekt ausgeschrieben (feld: feld)
+#![allow(clippy::redundant_field_names)]
+
+extern crate audio_engine;
+extern crate libc;
+
+// Modul für den Zugriff auf das Terminal (siehe dort)
+mod terminal;
+// Modul für die Vollbild-Oberfläche (siehe dort)
+mod tui;
+
+use audio_engine::{Player, Options, Event, RepeatMode, ShuffleMode, read_playlist, list_output_devices};
+use audio_engine::mpd::{self, MpdServer};
+#[cfg(feature = "mpris")]
+use audio_engine::mpris::Mpris;
+#[cfg(feature = "remote")]
+use audio_engine::remote::{self, RemoteServer};
+
+use terminal::{Key, RawMode, spawn_key_reader, format_time};
+
+use std::{
+    env, fs, process,
+    io::{self, Write},
+    path::Path,
+    time::Duration,
+    sync::{Arc, mpsc::{channel, RecvTimeoutError}}
+};
+
+// Dateiendungen, die beim Durchsuchen von Ordnern berücksichtigt werden
+const AUDIO_EXTENSIONS : [&str; 4] = ["mp3", "wav", "flac", "opus"];
+
+// Schrittweite beim Spulen mit den Pfeiltasten (in Sekunden)
+const SEEK_STEP : f64 = 10.0;
+
+const USAGE : &str = "usage: croissant [OPTIONS] FILE|DIRECTORY|PLAYLIST ...
+
+options:
+    --shuffle           play in random order
+    --repeat            start over when the end of the queue is reached
+    --device NAME       output device (see --list-devices)
+    --start-at TIME     start the first track at TIME (seconds or m:ss)
+    --tui               full-screen interface with queue and file browser
+    --mpd[=ADDRESS]     accept MPD clients (default: 127.0.0.1:6600)
+    --http[=ADDRESS]    serve the JSON/WebSocket API (default: 127.0.0.1:6680)
+    --token TOKEN       require TOKEN for the JSON API (or set CROISSANT_TOKEN)
+    --session FILE      save the session to FILE, resume it when no files are given
+    --list-devices      list output devices and exit
+    --help              show this help
+
+keys:
+    space   pause / resume
+    n       next track
+    p       previous track
+    ← →     seek backward / forward
+    r       repeat: off / all / one
+    s       shuffle: off / track / album
+    q       quit
+
+in the full-screen interface additionally:
+    tab         switch between queue and file browser
+    ↑ ↓         select
+    enter       play the selected track / open or add the selected entry
+    a           add the selected file, directory or playlist to the queue
+    e           play the selected entry after the current track
+    i           play the selected file right away
+    backspace   go to the parent directory";
+
+//+--------------------------------
+//| struct Args
+//|     - Kommandozeilenargumente
+
+struct Args {
+    shuffle: bool,
+    repeat: bool,
+    device: Option<String>,
+    start_at: Option<f64>,
+    tui: bool,
+    mpd: Option<String>,
+    #[cfg(feature = "remote")]
+    http: Option<String>,
+    #[cfg(feature = "remote")]
+    token: Option<String>,
+    session: Option<String>,
+    list_devices: bool,
+    help: bool,
+    paths: Vec<String>
+}
+
+impl Args {
+    // liest die Argumente (ohne Programmnamen)
+    fn parse<I: Iterator<Item=String>> (mut args: I) -> Result<Args, String> {
+        let mut res = Args {
+            shuffle: false,
+            repeat: false,
+            device: None,
+            start_at: None,
+            tui: false,
+            mpd: None,
+            #[cfg(feature = "remote")]
+            http: None,
+            #[cfg(feature = "remote")]
+            token: None,
+            session: None,
+            list_devices: false,
+            help: false,
+            paths: Vec::new()
+        };
+
+        while let Some(arg) = args.next() {
+            // --name=wert ist gleichbedeutend mit --name wert
+            let (name, inline) = match arg.find('=') {
+                Some(i) if arg.starts_with("--") => (arg[.. i].to_string(), Some(arg[i + 1 ..].to_string())),
+                _ => (arg.clone(), None)
+            };
+            let mut value = |name: &str| inline.clone()
+                .or_else(|| args.next())
+                .ok_or(format!("missing value for {}", name));
+
+            match name.as_str() {
+                "--shuffle" => res.shuffle = true,
+                "--repeat" => res.repeat = true,
+                "--tui" => res.tui = true,
+                // die Adresse ist optional und muss daher mit = angegeben werden
+                "--mpd" => res.mpd = Some(inline.clone().unwrap_or_else(|| mpd::DEFAULT_ADDRESS.to_string())),
+                #[cfg(feature = "remote")]
+                "--http" => res.http = Some(inline.clone().unwrap_or_else(|| remote::DEFAULT_ADDRESS.to_string())),
+                #[cfg(feature = "remote")]
+                "--token" => res.token = Some(value("--token")?),
+                "--list-devices" => res.list_devices = true,
+                "--help" | "-h" => res.help = true,
+                "--device" => res.device = Some(value("--device")?),
+                "--session" => res.session = Some(value("--session")?),
+                "--start-at" => {
+                    let v = value("--start-at")?;
+                    res.start_at = Some(parse_time(&v).ok_or(format!("invalid time: {}", v))?);
+                },
+                n if n.starts_with("--") => return Err(format!("unknown option: {}", n)),
+                _ => res.paths.push(arg)
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+// liest eine Zeitangabe in Sekunden ("90", "1:30" oder "1:02:03")
+fn parse_time(value: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        let v = part.trim().parse::<f64>().ok().filter(|v| *v >= 0.0)?;
+        seconds = seconds * 60.0 + v;
+    }
+    Some(seconds)
+}
+
+// sammelt die abzuspielenden Dateien: Ordner werden
+// rekursiv durchsucht, Playlists (M3U, PLS, XSPF) aufgelöst
+fn collect_files(paths: &[String]) -> Result<Vec<String>, String> {
+    let mut res = Vec::new();
+
+    for p in paths {
+        let path = Path::new(p);
+
+        if path.is_dir() {
+            walk_dir(path, &mut res).map_err(|e| format!("{}: {}", p, e))?;
+        } else if is_playlist(path) {
+            let entries = read_playlist(p).map_err(|e| e.to_string())?;
+            res.extend(entries.into_iter().map(|e| e.path));
+        } else if path.exists() {
+            res.push(p.clone());
+        } else {
+            return Err(format!("{}: no such file or directory", p));
+        }
+    }
+
+    Ok(res)
+}
+
+// hängt alle Audiodateien eines Ordners (alphabetisch sortiert) an files an
+fn walk_dir(dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+    let mut entries : Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            walk_dir(&entry, files)?;
+        } else if AUDIO_EXTENSIONS.contains(&extension(&entry).as_str()) {
+            if let Some(f) = entry.to_str() {
+                files.push(f.to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// gibt an, ob es sich um eine Playlist (.m3u, .pls, .xspf) handelt
+fn is_playlist(path: &Path) -> bool {
+    path.to_str().is_some_and(audio_engine::is_playlist)
+}
+
+// gibt an, ob die Datei abgespielt werden kann (Audiodatei oder Playlist)
+fn is_playable(path: &Path) -> bool {
+    AUDIO_EXTENSIONS.contains(&extension(path).as_str()) || is_playlist(path)
+}
+
+// Dateiendung in Kleinbuchstaben
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+// gibt an, ob eine Adresse nur auf diesem Rechner erreichbar ist
+#[cfg(feature = "remote")]
+fn is_local(address: &str) -> bool {
+    use std::net::ToSocketAddrs;
+
+    address.to_socket_addrs()
+        .map(|mut addrs| addrs.all(|a| a.ip().is_loopback()))
+        .unwrap_or(false)
+}
+
+// schaltet die Wiederholung weiter: aus, Playlist, aktueller Titel
+fn cycle_repeat(player: &Player) {
+    player.set_repeat(match player.repeat() {
+        RepeatMode::Off => RepeatMode::All,
+        RepeatMode::All => RepeatMode::One,
+        RepeatMode::One => RepeatMode::Off
+    });
+}
+
+// schaltet die zufällige Wiedergabe weiter: aus, einzelne Titel, ganze Alben
+fn cycle_shuffle(player: &Player) {
+    player.set_shuffle(match player.shuffle() {
+        ShuffleMode::Off => ShuffleMode::Track,
+        ShuffleMode::Track => ShuffleMode::Album,
+        ShuffleMode::Album => ShuffleMode::Off
+    });
+}
+
+//+--------------------------------
+//| enum Input
+//|     - Nachrichten an die Hauptschleife
+
+enum Input {
+    // Taste (s. terminal.rs)
+    Key(Key),
+    // Ereignis des Players (s. audio_engine/src/events.rs)
+    Event(Event)
+}
+
+// gibt die Geräte mit ihren Formaten aus
+fn print_devices() -> Result<(), String> {
+    for device in list_output_devices().map_err(|e| e.to_string())? {
+        println!("{}{}", device.name, if device.default { " (default)" } else { "" });
+        for f in device.formats {
+            println!("    {} ch, {} - {} Hz, {:?}",
+                f.channels, f.min_sample_rate.0, f.max_sample_rate.0, f.data_type);
+        }
+    }
+    Ok(())
+}
+
+// Statuszeile: Zustand, Position und Länge des aktuellen Titels
+fn print_status(player: &Player) {
+    print!("\r\x1b[K{} {} / {}",
+        if player.playing() { ">" } else { "||" },
+        format_time(player.position()),
+        format_time(player.duration()));
+    let _ = io::stdout().flush();
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let files = collect_files(&args.paths)?;
+    // ohne Dateien wird die gespeicherte Sitzung fortgesetzt
+    let resume = match &args.session {
+        Some(session) => files.is_empty() && Path::new(session).exists(),
+        None => false
+    };
+    // die Oberfläche kann auch mit leerer Playlist starten
+    if files.is_empty() && !resume && !args.tui {
+        return Err(String::from("nothing to play"));
+    }
+
+    let mut options = Options::new();
+    options.device = args.device.clone();
+    let player = Arc::new(Player::new(options).map_err(|e| e.to_string())?);
+
+    // ohne Session-Bus (z.B. über SSH) wird einfach ohne MPRIS gespielt
+    #[cfg(feature = "mpris")]
+    let _mpris = Mpris::start(player.clone())
+        .map_err(|e| eprintln!("croissant: MPRIS not available: {}", e))
+        .ok();
+
+    let _mpd = match &args.mpd {
+        Some(address) => Some(MpdServer::start(player.clone(), address)
+            .map_err(|e| format!("MPD server on {}: {}", address, e))?),
+        None => None
+    };
+
+    // das Token steht besser in der Umgebung als in der Prozessliste
+    #[cfg(feature = "remote")]
+    let _remote = match &args.http {
+        Some(address) => {
+            let token = args.token.clone().or_else(|| env::var("CROISSANT_TOKEN").ok());
+            if token.is_none() && !is_local(address) {
+                eprintln!("croissant: warning: the API on {} is reachable without a token", address);
+            }
+            Some(RemoteServer::start(player.clone(), address, token)
+                .map_err(|e| format!("HTTP server on {}: {}", address, e))?)
+        },
+        None => None
+    };
+
+    // Tasten und Ereignisse kommen über denselben Kanal an
+    let (send, recv) = channel();
+    let events = send.clone();
+    player.subscribe(Box::new(move |event: &Event| {
+        let _ = events.send(Input::Event(event.clone()));
+    }));
+
+    // ohne Terminal (z.B. als Dienst) wird nur abgespielt
+    let raw = RawMode::enable();
+    if raw.is_none() && args.tui {
+        return Err(String::from("--tui needs a terminal"));
+    }
+    if raw.is_some() {
+        spawn_key_reader(send, Input::Key);
+    }
+
+    // die Angaben der Kommandozeile gelten vor denen der Sitzung
+    if let (true, Some(session)) = (resume, &args.session) {
+        player.load_session(session).map_err(|e| e.to_string())?;
+    }
+    if let Some(session) = &args.session {
+        player.set_autosave(Some(session.clone()));
+    }
+    if let Some(seconds) = args.start_at {
+        player.set_start(seconds);
+    }
+    if args.repeat {
+        player.set_repeat(RepeatMode::All);
+    }
+    // die Reihenfolge der Playlist bleibt erhalten (s. ShuffleMode)
+    if args.shuffle {
+        player.set_shuffle(ShuffleMode::Track);
+    }
+    player.extend_queue(files);
+    player.play().map_err(|e| e.to_string())?;
+
+    if args.tui {
+        return tui::run(&player, &recv);
+    }
+
+    loop {
+        let input = match recv.recv_timeout(Duration::from_millis(500)) {
+            Ok(input) => input,
+            Err(RecvTimeoutError::Timeout) => {
+                if raw.is_some() { print_status(&player); }
+                continue;
+            },
+            Err(RecvTimeoutError::Disconnected) => break
+        };
+
+        match input {
+            // der Zustand kann sich auch über MPD, MPRIS oder
+            // HTTP ändern, daher beim Player nachfragen
+            Input::Key(Key::Char(' ')) => {
+                let res = if player.playing() { player.pause() } else { player.play() };
+                res.map_err(|e| e.to_string())?;
+            },
+            Input::Key(Key::Char('n')) => player.skip(),
+            Input::Key(Key::Char('p')) => player.prev(),
+            Input::Key(Key::Left) => player.seek(player.position() - SEEK_STEP),
+            Input::Key(Key::Right) => player.seek(player.position() + SEEK_STEP),
+            Input::Key(Key::Char('r')) => cycle_repeat(&player),
+            Input::Key(Key::Char('s')) => cycle_shuffle(&player),
+            Input::Key(Key::Char('q')) => break,
+            Input::Key(_) => {},
+
+            Input::Event(Event::TrackStarted { path, tags, .. }) => {
+                let name = if tags.title().is_empty() {
+                    path
+                } else {
+                    format!("{} - {}", tags.artist(), tags.title())
+                };
+                println!("\r\x1b[K{}", name);
+            },
+            Input::Event(Event::Error { message }) => {
+                println!("\r\x1b[Kerror: {}", message);
+            },
+            Input::Event(Event::RepeatChanged { mode }) => {
+                println!("\r\x1b[Krepeat: {}", mode.name());
+            },
+            Input::Event(Event::ShuffleChanged { mode }) => {
+                println!("\r\x1b[Kshuffle: {}", mode.name());
+            },
+            Input::Event(Event::PlaybackState { .. }) => {
+                if raw.is_some() { print_status(&player); }
+            },
+            // mit repeat-all endet die Playlist nicht (s. RepeatMode)
+            Input::Event(Event::EndOfQueue) => break,
+            Input::Event(_) => {}
+        }
+    }
+
+    if raw.is_some() { println!(); }
+    Ok(())
+}
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("croissant: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let res = if args.help {
+        println!("{}", USAGE);
+        Ok(())
+    } else if args.list_devices {
+        print_devices()
+    } else if args.paths.is_empty() && args.session.is_none() && !args.tui {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    } else {
+        run(args)
+    };
+
+    if let Err(e) = res {
+        eprintln!("croissant: {}", e);
+        process::exit(1);
+    }
+}
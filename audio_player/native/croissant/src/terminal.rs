@@ -0,0 +1,145 @@
+//+-------------------------------------------------------------+
+//| terminal.rs - Zugriff auf das Terminal: einzelne Tasten     |
+//|          werden ohne Enter gelesen (raw mode), die Ausgabe  |
+//|          erfolgt über ANSI-Escape-Sequenzen.                |
+//+-------------------------------------------------------------+
+
+use std::{
+    io::{self, Read, Write},
+    thread,
+    sync::mpsc::Sender
+};
+
+//+--------------------------------
+//| enum Key
+//|     - eine gedrückte Taste
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Esc
+}
+
+//+--------------------------------
+//| struct RawMode
+//|     - schaltet Zeilenpuffer und Echo des
+//|       Terminals ab, solange die Struktur
+//|       existiert
+
+pub struct RawMode {
+    // ursprüngliche Einstellungen, werden beim Freigeben wiederhergestellt
+    original: libc::termios
+}
+
+impl RawMode {
+    // gibt None zurück, falls die Eingabe kein Terminal ist
+    // (z.B. beim Start als Dienst)
+    pub fn enable() -> Option<RawMode> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 { return None; }
+
+            let mut original : libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 { return None; }
+
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 { return None; }
+
+            Some(RawMode { original: original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original); }
+    }
+}
+
+//+--------------------------------
+//| struct Screen
+//|     - schaltet auf den alternativen
+//|       Bildschirm des Terminals um und
+//|       blendet den Cursor aus, solange
+//|       die Struktur existiert
+
+pub struct Screen;
+
+impl Screen {
+    pub fn enter() -> Screen {
+        print!("\x1b[?1049h\x1b[?25l");
+        let _ = io::stdout().flush();
+        Screen
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+// Breite und Höhe des Terminals in Zeichen
+pub fn size() -> (usize, usize) {
+    unsafe {
+        let mut ws : libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) == 0 && ws.ws_col > 0 {
+            (ws.ws_col as usize, ws.ws_row as usize)
+        } else {
+            (80, 24)
+        }
+    }
+}
+
+// startet einen Thread, der Tasten liest und mit
+// wrap verpackt an send übergibt, bis die Eingabe endet
+pub fn spawn_key_reader<T, F> (send: Sender<T>, wrap: F)
+    where T: Send + 'static, F: Fn(Key) -> T + Send + 'static
+{
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        let mut buf = [0u8; 16];
+
+        // eine Escape-Sequenz (z.B. Pfeiltasten: ESC [ A bis ESC [ D)
+        // kommt mit einem einzigen read an, ESC allein ebenfalls
+        while let Ok(n) = stdin.read(&mut buf) {
+            if n == 0 { break; }
+
+            let mut i = 0;
+            while i < n {
+                let (key, len) = match &buf[i .. n] {
+                    [0x1b, b'[', b'A', ..] => (Key::Up, 3),
+                    [0x1b, b'[', b'B', ..] => (Key::Down, 3),
+                    [0x1b, b'[', b'C', ..] => (Key::Right, 3),
+                    [0x1b, b'[', b'D', ..] => (Key::Left, 3),
+                    // unbekannte Sequenz, der Rest wird verworfen
+                    [0x1b, _, ..] => (Key::Esc, n - i),
+                    [0x1b] => (Key::Esc, 1),
+                    [b'\n', ..] | [b'\r', ..] => (Key::Enter, 1),
+                    [0x7f, ..] | [0x08, ..] => (Key::Backspace, 1),
+                    [b, ..] => (Key::Char(*b as char), 1),
+                    [] => break
+                };
+                i += len;
+
+                if send.send(wrap(key)).is_err() { return; }
+            }
+        }
+    });
+}
+
+// formatiert Sekunden als m:ss
+pub fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
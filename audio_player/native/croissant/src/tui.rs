@@ -0,0 +1,449 @@
+//+-------------------------------------------------------------+
+//| tui.rs - Vollbild-Oberfläche im Terminal (croissant --tui): |
+//|          oben der aktuelle Titel mit Fortschrittsbalken,    |
+//|          darunter links die Playlist und rechts ein         |
+//|          Dateibrowser zum Hinzufügen von Titeln.            |
+//+-------------------------------------------------------------+
+
+use audio_engine::{Player, Event, RepeatMode, ShuffleMode, PlaylistOrder, Tags, Track};
+
+use crate::terminal::{self, Key, Screen, format_time};
+use crate::{Input, SEEK_STEP, collect_files, is_playable, is_playlist, cycle_repeat, cycle_shuffle};
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+    sync::mpsc::{Receiver, RecvTimeoutError}
+};
+
+// Intervall, in dem der Fortschrittsbalken neu gezeichnet wird
+const REFRESH : Duration = Duration::from_millis(250);
+
+// Zeilen oberhalb (aktueller Titel, Überschriften) und
+// unterhalb (Tastenbelegung) der beiden Listen
+const HEADER_ROWS : usize = 5;
+const FOOTER_ROWS : usize = 1;
+
+const HELP : &str = "space pause  n next  p prev  ←→ seek  r repeat  s shuffle  tab switch pane  \
+    enter play/open  a add  e play next  i play now  backspace up  q quit";
+
+// Bereich, der die Pfeiltasten erhält
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Queue,
+    Browser
+}
+
+// wie ein Eintrag des Browsers zur Playlist hinzukommt
+#[derive(Clone, Copy, PartialEq)]
+enum Add {
+    // hinten anhängen
+    Queue,
+    // nach dem aktuellen Titel, ohne ihn zu unterbrechen
+    Next,
+    // sofort abspielen, der aktuelle Titel folgt danach
+    Now
+}
+
+//+--------------------------------
+//| struct List
+//|     - Auswahl und sichtbarer
+//|       Ausschnitt einer Liste
+
+struct List {
+    selected: usize,
+    top: usize
+}
+
+impl List {
+    fn new() -> List {
+        List { selected: 0, top: 0 }
+    }
+
+    // bewegt die Auswahl um delta Zeilen innerhalb einer Liste der Länge len
+    fn move_by(&mut self, delta: isize, len: usize) {
+        let max = len.saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).max(0).min(max) as usize;
+    }
+
+    // verschiebt den Ausschnitt (height Zeilen), sodass die Auswahl sichtbar ist
+    fn scroll(&mut self, height: usize) {
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if height > 0 && self.selected >= self.top + height {
+            self.top = self.selected + 1 - height;
+        }
+    }
+}
+
+//+--------------------------------
+//| struct Entry
+//|     - Eintrag im Dateibrowser
+
+struct Entry {
+    // angezeigter Name, Ordner enden auf /
+    name: String,
+    path: PathBuf,
+    dir: bool
+}
+
+//+--------------------------------
+//| struct Browser
+//|     - zeigt die Unterordner, Audio-
+//|       dateien und Playlists eines
+//|       Ordners an
+
+struct Browser {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    list: List
+}
+
+impl Browser {
+    fn new(dir: PathBuf) -> Browser {
+        let mut browser = Browser {
+            dir: dir,
+            entries: Vec::new(),
+            list: List::new()
+        };
+        browser.read();
+        browser
+    }
+
+    // liest den aktuellen Ordner ein, versteckte Dateien werden ausgelassen
+    fn read(&mut self) {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for e in entries.filter_map(|e| e.ok()) {
+                let name = e.file_name().to_string_lossy().into_owned();
+                let path = e.path();
+                if name.starts_with('.') { continue; }
+
+                if path.is_dir() {
+                    dirs.push(Entry { name: name + "/", path: path, dir: true });
+                } else if is_playable(&path) {
+                    files.push(Entry { name: name, path: path, dir: false });
+                }
+            }
+        }
+        dirs.sort_by(|a, b| a.name.cmp(&b.name));
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.entries.clear();
+        if let Some(parent) = self.dir.parent() {
+            self.entries.push(Entry { name: String::from("../"), path: parent.to_path_buf(), dir: true });
+        }
+        self.entries.extend(dirs);
+        self.entries.extend(files);
+        self.list = List::new();
+    }
+
+    // wechselt in einen anderen Ordner; beim Wechsel nach oben
+    // wird der Ordner ausgewählt, aus dem man kommt
+    fn open(&mut self, dir: PathBuf) {
+        let previous = std::mem::replace(&mut self.dir, dir);
+        self.read();
+
+        if let Some(i) = self.entries.iter().position(|e| e.path == previous) {
+            self.list.selected = i;
+        }
+    }
+
+    // wechselt in den übergeordneten Ordner
+    fn up(&mut self) {
+        if let Some(parent) = self.dir.parent().map(|p| p.to_path_buf()) {
+            self.open(parent);
+        }
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.list.selected)
+    }
+}
+
+//+--------------------------------
+//| struct Tui
+//|     - Zustand der Oberfläche
+
+struct Tui<'a> {
+    player: &'a Player,
+    playing: bool,
+    focus: Pane,
+
+    // Einträge der Playlist mit ihren Informationen (s. Player::playlist)
+    queue: Vec<(Track, Tags)>,
+    // id des aktuellen Eintrags
+    curr: Option<u32>,
+    queue_list: List,
+
+    browser: Browser,
+
+    // zuletzt aufgetretener Fehler, wird statt der Tastenbelegung angezeigt
+    message: Option<String>
+}
+
+impl<'a> Tui<'a> {
+    fn new(player: &'a Player, browser: Browser) -> Tui<'a> {
+        let mut tui = Tui {
+            player: player,
+            playing: true,
+            focus: Pane::Queue,
+            queue: Vec::new(),
+            curr: None,
+            queue_list: List::new(),
+            browser: browser,
+            message: None
+        };
+        tui.refresh_queue();
+        tui.queue_list.selected = player.curr_index() as usize;
+        tui
+    }
+
+    // liest die Playlist neu ein
+    fn refresh_queue(&mut self) {
+        self.queue = self.player.playlist(PlaylistOrder::Display);
+        self.curr = self.player.curr_id();
+        self.queue_list.move_by(0, self.queue.len());
+    }
+
+    // verarbeitet ein Ereignis des Players
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::QueueChanged | Event::TrackStarted { .. } => self.refresh_queue(),
+            Event::PlaybackState { playing } => self.playing = playing,
+            Event::Error { message } => self.message = Some(message),
+            _ => {}
+        }
+    }
+
+    // verarbeitet eine Taste, gibt false zurück, wenn das Programm enden soll
+    fn key(&mut self, key: Key) -> Result<bool, String> {
+        // eine Fehlermeldung verschwindet beim nächsten Tastendruck
+        self.message = None;
+
+        match key {
+            Key::Char('q') => return Ok(false),
+            Key::Char(' ') => {
+                let res = if self.playing { self.player.pause() } else { self.player.play() };
+                res.map_err(|e| e.to_string())?;
+            },
+            Key::Char('n') => self.player.skip(),
+            Key::Char('p') => self.player.prev(),
+            Key::Left => self.player.seek(self.player.position() - SEEK_STEP),
+            Key::Right => self.player.seek(self.player.position() + SEEK_STEP),
+            Key::Char('r') => cycle_repeat(self.player),
+            Key::Char('s') => cycle_shuffle(self.player),
+            Key::Char('\t') => {
+                self.focus = if self.focus == Pane::Queue { Pane::Browser } else { Pane::Queue };
+            },
+            Key::Up | Key::Char('k') => self.move_selection(-1),
+            Key::Down | Key::Char('j') => self.move_selection(1),
+
+            Key::Enter if self.focus == Pane::Queue => {
+                if let Some((track, _)) = self.queue.get(self.queue_list.selected) {
+                    self.player.skip_to(track.id);
+                }
+            },
+            Key::Enter if self.focus == Pane::Browser => {
+                let dir = self.browser.selected().filter(|e| e.dir).map(|e| e.path.clone());
+                match dir {
+                    Some(dir) => self.browser.open(dir),
+                    None => self.add(Add::Queue)
+                }
+            },
+            Key::Char('a') if self.focus == Pane::Browser => self.add(Add::Queue),
+            Key::Char('e') if self.focus == Pane::Browser => self.add(Add::Next),
+            Key::Char('i') if self.focus == Pane::Browser => self.add(Add::Now),
+            Key::Backspace if self.focus == Pane::Browser => self.browser.up(),
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        match self.focus {
+            Pane::Queue => self.queue_list.move_by(delta, self.queue.len()),
+            Pane::Browser => self.browser.list.move_by(delta, self.browser.entries.len())
+        }
+    }
+
+    // fügt den ausgewählten Eintrag des Browsers (Datei, Ordner
+    // oder Playlist) an die Playlist an oder nach dem aktuellen Titel
+    // ein, bzw. spielt eine einzelne Datei sofort ab (s. Player::play_now)
+    fn add(&mut self, mode: Add) {
+        let entry = match self.browser.selected() {
+            Some(entry) => entry,
+            None => return
+        };
+        let path = entry.path.to_string_lossy().into_owned();
+
+        if mode == Add::Now {
+            if entry.dir || is_playlist(&entry.path) {
+                self.message = Some(String::from("only single files can be played right away"));
+                return;
+            }
+            self.player.play_now(path);
+        } else {
+            let files = match collect_files(&[path]) {
+                Ok(files) => files,
+                Err(e) => { self.message = Some(e); return; }
+            };
+            if mode == Add::Next {
+                self.player.play_next_all(files);
+            } else {
+                self.player.extend_queue(files);
+            }
+        }
+
+        // weiter zum nächsten Eintrag, so lassen sich mehrere nacheinander hinzufügen
+        self.browser.list.move_by(1, self.browser.entries.len());
+    }
+
+    // zeichnet den gesamten Bildschirm neu
+    fn draw(&mut self) {
+        let (width, height) = terminal::size();
+        let rows = height.saturating_sub(HEADER_ROWS + FOOTER_ROWS);
+        let left = width / 2;
+        let right = width.saturating_sub(left + 1);
+
+        let mut lines = Vec::with_capacity(height);
+
+        // aktueller Titel
+        match self.player.curr_tags() {
+            Some(tags) => {
+                let state = if self.playing { ">" } else { "||" };
+                lines.push(format!(" {} \x1b[1m{}\x1b[0m", state, fit(&tags.title(), width.saturating_sub(4))));
+                lines.push(fit(&format!("    {} - {}", tags.artist(), tags.album()), width));
+            },
+            None => {
+                lines.push(fit("    nothing playing", width));
+                lines.push(String::new());
+            }
+        }
+
+        // Fortschrittsbalken
+        let position = format_time(self.player.position());
+        let duration = format_time(self.player.duration());
+        let bar = width.saturating_sub(position.len() + duration.len() + 8);
+        lines.push(format!("    {} [{}] {}",
+            position, progress_bar(self.player.position(), self.player.duration(), bar), duration));
+        lines.push(String::new());
+
+        // Überschriften, die des aktiven Bereichs hervorgehoben
+        let heading = |text: &str, width: usize, active: bool| if active {
+            format!("\x1b[1;7m{}\x1b[0m", fit(text, width))
+        } else {
+            fit(text, width)
+        };
+        // Wiederholung und zufällige Wiedergabe werden neben der Überschrift angezeigt
+        let repeat = match self.player.repeat() {
+            RepeatMode::Off => String::new(),
+            mode => format!("  [repeat {}]", mode.name())
+        };
+        let shuffle = match self.player.shuffle() {
+            ShuffleMode::Off => String::new(),
+            mode => format!("  [shuffle {}]", mode.name())
+        };
+        lines.push(format!("{}│{}",
+            heading(&format!(" Queue ({}){}{}", self.queue.len(), repeat, shuffle), left, self.focus == Pane::Queue),
+            heading(&format!(" {}", self.browser.dir.display()), right, self.focus == Pane::Browser)));
+
+        // Playlist und Browser nebeneinander
+        self.queue_list.scroll(rows);
+        self.browser.list.scroll(rows);
+        for row in 0 .. rows {
+            let q = self.queue_list.top + row;
+            let queue = match self.queue.get(q) {
+                Some((track, tags)) => {
+                    let marker = if Some(track.id) == self.curr { ">" } else { " " };
+                    let text = fit(&format!("{}{:>3} {}", marker, q + 1, display_name(tags)), left);
+                    highlight(text, self.focus == Pane::Queue && q == self.queue_list.selected)
+                },
+                None => fit("", left)
+            };
+
+            let b = self.browser.list.top + row;
+            let browser = match self.browser.entries.get(b) {
+                Some(entry) => {
+                    let text = fit(&format!(" {}", entry.name), right);
+                    highlight(text, self.focus == Pane::Browser && b == self.browser.list.selected)
+                },
+                None => String::new()
+            };
+
+            lines.push(format!("{}│{}", queue, browser));
+        }
+
+        // Tastenbelegung bzw. Fehlermeldung
+        lines.push(match &self.message {
+            Some(message) => format!("\x1b[1m{}\x1b[0m", fit(&format!(" error: {}", message), width)),
+            None => fit(&format!(" {}", HELP), width)
+        });
+
+        // jede Zeile wird einzeln positioniert und ihr Rest gelöscht
+        let mut out = String::new();
+        for (i, line) in lines.iter().take(height).enumerate() {
+            out.push_str(&format!("\x1b[{};1H{}\x1b[K", i + 1, line));
+        }
+        print!("{}", out);
+        let _ = io::stdout().flush();
+    }
+}
+
+// kürzt einen Text auf width Zeichen bzw. füllt ihn mit Leerzeichen auf
+fn fit(text: &str, width: usize) -> String {
+    let mut res : String = text.chars().take(width).collect();
+    let len = res.chars().count();
+    res.push_str(&" ".repeat(width - len));
+    res
+}
+
+// stellt eine ausgewählte Zeile invertiert dar
+fn highlight(text: String, selected: bool) -> String {
+    if selected { format!("\x1b[7m{}\x1b[0m", text) } else { text }
+}
+
+// Anzeige eines Titels in der Playlist
+fn display_name(tags: &Tags) -> String {
+    match (tags.title().is_empty(), tags.artist().is_empty()) {
+        (true, _) => String::from("(unknown title)"),
+        (false, true) => tags.title(),
+        (false, false) => format!("{} - {}", tags.artist(), tags.title())
+    }
+}
+
+// Balken aus width Zeichen, gefüllt entsprechend der Position im Titel
+fn progress_bar(position: f64, duration: f64, width: usize) -> String {
+    let filled = if duration > 0.0 {
+        ((position / duration).clamp(0.0, 1.0) * width as f64) as usize
+    } else {
+        0
+    };
+    "=".repeat(filled) + &"-".repeat(width - filled)
+}
+
+// startet die Oberfläche im aktuellen Ordner und läuft, bis q gedrückt wird
+pub fn run(player: &Player, input: &Receiver<Input>) -> Result<(), String> {
+    let dir = env::current_dir().map_err(|e| e.to_string())?;
+
+    let _screen = Screen::enter();
+    let mut tui = Tui::new(player, Browser::new(dir));
+
+    loop {
+        tui.draw();
+
+        match input.recv_timeout(REFRESH) {
+            Ok(Input::Key(key)) => if !tui.key(key)? { break; },
+            Ok(Input::Event(event)) => tui.event(event),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break
+        }
+    }
+
+    Ok(())
+}
@@ -79,6 +79,14 @@ enum_from_primitive! {
     }
 }
 
+impl Error {
+    // wandelt einen Rückgabecode um, unbekannte Codes
+    // (neuere Versionen der Bibliothek) gelten als OpEfault
+    fn from_code (code: i32) -> Error {
+        Error::from_i32(code).unwrap_or(Error::OpEfault)
+    }
+}
+
 //+------------------------------------------------------------------------------
 //| struct Opusfile<'a>
 //|     - einfaches Öffnen und Dekodieren von Opus-Dateien
@@ -89,15 +97,20 @@ impl Opusfile<'_> {
     // öffnet eine Datei
     pub fn open<'a, P: AsRef<Path>> (filename: P) -> Result<Opusfile<'a>, Error> 
     {
-        let path = CString::new(filename.as_ref().to_str().unwrap()).unwrap().into_raw();
+        // Pfade, die nicht in UTF-8 darstellbar sind oder ein Nullbyte
+        // enthalten, kann die Bibliothek nicht öffnen
+        let path = filename.as_ref().to_str()
+            .and_then(|s| CString::new(s).ok())
+            .ok_or(Error::OpEinval)?
+            .into_raw();
         let mut error :i32 = 0;
         let handle = unsafe { opusfile_sys::op_open_file(path, &mut error) };
 
         if error != 0 {
-            return Err(Error::from_i32(error).unwrap());
+            return Err(Error::from_code(error));
         }
 
-        Ok(Opusfile(unsafe { handle.as_mut().unwrap() }))
+        unsafe { handle.as_mut() }.map(Opusfile).ok_or(Error::OpEfault)
     }
 
     // liest die Opus-Datei in einen Buffer (target) und 
@@ -111,12 +124,88 @@ impl Opusfile<'_> {
         };
 
         if res < 0 {
-            Err(Error::from_i32(res).unwrap())
+            Err(Error::from_code(res))
         } else {
             Ok(res as usize)
         }
     }
 
+    // liest die Opus-Datei mit allen Kanälen (abwechselnd gespeichert,
+    // in Vorbis-Reihenfolge) in einen Buffer (target) und gibt die Anzahl
+    // der Samples je Kanal sowie die Anzahl der Kanäle zurück
+    pub fn read_float (&mut self, target: &mut [f32]) -> Result<(usize, usize), Error> {
+        let mut link :i32 = 0;
+        let res = unsafe {
+            opusfile_sys::op_read_float(
+                self.0,
+                target.as_mut_ptr(),
+                target.len() as i32,
+                &mut link)
+        };
+
+        if res < 0 {
+            Err(Error::from_code(res))
+        } else {
+            Ok((res as usize, self.channel_count(link)))
+        }
+    }
+
+    // gibt die Anzahl der Kanäle eines Abschnitts (link) an,
+    // -1 steht für den aktuellen Abschnitt
+    pub fn channel_count (&self, link: i32) -> usize {
+        unsafe { opusfile_sys::op_channel_count(self.0, link) as usize }
+    }
+
+    // springt an eine Position innerhalb der Datei,
+    // angegeben in Samples (je Kanal, bei 48 kHz)
+    pub fn pcm_seek (&mut self, offset: i64) -> Result<(), Error> {
+        let res = unsafe { opusfile_sys::op_pcm_seek(self.0, offset) };
+
+        if res < 0 {
+            Err(Error::from_code(res))
+        } else {
+            Ok(())
+        }
+    }
+
+    // gibt die Gesamtlänge der Datei in Samples
+    // (je Kanal, bei 48 kHz) an
+    pub fn pcm_total (&self) -> Result<i64, Error> {
+        let res = unsafe { opusfile_sys::op_pcm_total(self.0, -1) };
+
+        if res < 0 {
+            Err(Error::from_code(res as i32))
+        } else {
+            Ok(res)
+        }
+    }
+
+    // gibt die Lautstärkeanpassung aus dem Tag R128_TRACK_GAIN in
+    // 1/256 dB an, sie gilt zusätzlich zur Anpassung im Header
+    pub fn track_gain (&self) -> Option<i32> {
+        self.tag_gain(opusfile_sys::opus_tags_get_track_gain)
+    }
+
+    // wie track_gain, aber aus dem Tag R128_ALBUM_GAIN
+    pub fn album_gain (&self) -> Option<i32> {
+        self.tag_gain(opusfile_sys::opus_tags_get_album_gain)
+    }
+
+    fn tag_gain (
+        &self,
+        get: unsafe extern "C" fn(*const opusfile_sys::OpusTags, *mut i32) -> i32
+    ) -> Option<i32>
+    {
+        let mut gain :i32 = 0;
+        let res = unsafe {
+            let tags = opusfile_sys::op_tags(self.0, 0);
+            if tags.is_null() { return None; }
+            get(tags, &mut gain)
+        };
+
+        if res == 0 { Some(gain) } else { None }
+    }
+
     // Liest alle Metadaten aus der Datei
     pub fn tags<'a> (this: &Opusfile<'a>) -> Option<Tags<'a>> {
         unsafe {
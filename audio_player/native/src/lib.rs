@@ -1,603 +1,551 @@
-
 //+-------------------------------------------------------------+
 //| lib.rs - enthält alle von außen zugreifbaren Funktionen des |
-//|          Rust-Moduls, verwaltet die unterschiedlichen       |
-//|          Threads und den gemeinsamen Zustand                |
+//|          Rust-Moduls. Die Wiedergabe selbst übernimmt die   |
+//|          Bibiliothek audio_engine, dieses Modul wandelt     |
+//|          nur zwischen JavaScript- und Rust-Werten um.       |
 //+-------------------------------------------------------------+
 
 extern crate neon;
+extern crate audio_engine;      // Wiedergabe (s. audio_engine/src/lib.rs)
 
 use neon::prelude::*;
+use neon::event::EventHandler;
 
-// registriert die aus JavaScript zugreifbaren Funtionen
-register_module!(mut cx, {
-    cx.export_function("play", play)
-      .and(cx.export_function("pause", pause))
-      .and(cx.export_function("skip", abort_curr))
-      .and(cx.export_function("prev", prev))
-      .and(cx.export_function("init", init))
-      .and(cx.export_function("add_to_queue", add_pl))
-      .and(cx.export_function("add_next", add_next))
-      .and(cx.export_function("import_m3u", import_m3u))
-      .and(cx.export_function("curr_playing", curr_playing))
-      .and(cx.export_function("curr_tag", curr_tag))
-      .and(cx.export_function("curr_id", curr_id))
-      .and(cx.export_function("playlist", playlist))
-      .and(cx.export_function("changed", changed))
-      .and(cx.export_function("skip_to", skip_to))
-});
-
-// importiere Bibiliotheken (crates)
-
-extern crate cpal;              // Zugriff auf Audiogeräte
-extern crate samplerate;        // Resampling der Audiodateien
-extern crate ringbuf;           // Ringbuffer um Audiodaten zu übertragen
-extern crate lazy_static;       // Verwaltung des gemeinsamen Zustands
-extern crate m3u;               // Unterstützung für .m3u Dateien (Playlists)
-extern crate futures_util;      // Hilfsfunktionen für das arbeiten mit asynchronen Vorgängen
-
-// Modul für das Lesen der Audiodateien (siehe dort)
-mod audio_reader;
-
-// use - wird in Rust benutzt, um anzugeben, welche der Funktionen und
-//       Objekte direkt zugreifbar sind, ohne das crate oder Modul anzugeben
-
-use audio_reader::buffered_reader::{BufferedReader, ReaderTarget};
-use audio_reader::{AudioFile, AudioProducer, resample_read, Tags, Tagged};
-
-use cpal::traits::{HostTrait, EventLoopTrait};
-use cpal::{StreamData, UnknownTypeOutputBuffer, Format};
-
-use std::{
-    thread, thread::sleep, time::Duration, fmt::Debug,
-    sync::{ mpsc::{channel, Sender}, Arc, Mutex },
-    marker::PhantomData, collections::VecDeque
-};
-
-use futures::{
-    executor::block_on,
-    task::AtomicWaker,
-    future::{Abortable, AbortHandle}
+use audio_engine::{
+    Player, Options, Event, Tags, Curve, GainMode, RepeatMode, ShuffleMode, PlaylistOrder, PlaylistFormat, Result,
+    SampleFormat, list_output_devices
 };
 
-use ringbuf::{ RingBuffer, Producer };
-
-use lazy_static::lazy_static;
-
-use m3u::{Entry, EntryReader, Url};
-
-
-//+--------------------------------
-//| struct Playerstate<'a>
-//|     - diese Struktur speichert
-//|       den momentanen Zustand
-//|       des Programms
+// registriert die aus JavaScript zugreifbaren Klassen und Funtionen
+register_module!(mut cx, {
+    cx.export_class::<JsPlayer>("Player")
+      .and(cx.export_function("list_devices", list_devices))
+});
 
-struct PlayerState<'a> {
-    // player: handhabt die Audiogeräte
-    player: Option<CpalPlayer<'a>>,
+//+------------------------------------------------------------------------------
+//| JavaScript-Interface
+//|     - Die Klasse Player und die folgenden Funktionen können aus JavaScript
+//|       aufgerufen werden. Sie sind die Schnittstelle des Rustmoduls zum
+//|       Benutzer. Jeder Player hat seinen eigenen Zustand, Audio-Stream
+//|       und Lese-Thread, mehrere Player können gleichzeitig spielen.
+
+// Inhalt eines JavaScript Player-Objekts ist ein Player der Bibiliothek
+// (s. audio_engine/src/player.rs). Wird das JavaScript-Objekt freigegeben,
+// wird auch der Player beendet.
+
+declare_types! {
+    pub class JsPlayer for Player {
+        // new Player({ device, sampleRate, bufferSeconds }), alle Angaben
+        // sind optional. Ohne Audio-Gerät wird eine Exception geworfen.
+        init(mut cx) {
+            let options = match cx.argument_opt(0).and_then(|o| o.downcast::<JsObject>().ok()) {
+                Some(obj) => read_options(&mut cx, obj)?,
+                None => Options::new()
+            };
+
+            or_throw(&mut cx, Player::new(options))
+        }
 
-    // play_queue: Liste der noch zu spielenden Titel
-    play_queue: VecDeque<String>,
-    
-    // played_list: Liste der bereits gespielten Titel
-    played_list: Vec<String>,
-    
-    // curr: momentan gespielter Titel
-    curr: Option<(String, Tags, AbortHandle)>,
+        // Setze abspielen fort
+        method play(mut cx) {
+            let res = with_player(&mut cx, |p| p.play());
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
+        }
 
-    // changed: Veränderung seit der letzten Kontrolle
-    changed: bool
-}
+        // Pausiere das abspielen
+        method pause(mut cx) {
+            let res = with_player(&mut cx, |p| p.pause());
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
+        }
 
-impl<'a> PlayerState<'a> {
-    // erzeugt einen neuen, leeren Zustand
-    fn new() -> PlayerState<'a> {
-        PlayerState { 
-            player: None, 
-            played_list: Vec::new(), 
-            play_queue: VecDeque::new(), 
-            curr: None,
-            changed: false
+        // brich die aktuelle Datei ab
+        method skip(mut cx) {
+            with_player(&mut cx, |p| p.skip());
+            Ok(cx.null().upcast())
         }
-    }
 
-    // initialisiert den Zustand mit einem CpalPlayer (siehe dort)
-    fn init(&mut self, player: CpalPlayer<'a>) {
-        self.player = Some(player);
-    }
+        // brich ab und spiele die latzte Datei ab
+        method prev(mut cx) {
+            with_player(&mut cx, |p| p.prev());
+            Ok(cx.null().upcast())
+        }
 
-    // bricht momentanen Titel ab
-    fn abort_curr(&mut self) {
-        if let Some((_, _, handle)) = &self.curr {
-            handle.abort();
+        // springe zu einem Eintrag der Playlist (über dessen id), gibt
+        // false zurück, falls es den Eintrag nicht gibt
+        method skip_to(mut cx) {
+            let id = cx.argument::<JsNumber>(0)?.value() as u32;
+            let res = with_player(&mut cx, |p| p.skip_to(id));
+            Ok(cx.boolean(res).upcast())
         }
 
-        if let Some(player) = &self.player {
-            player.clear_buffer();
+        // hänge Datei an die Playlist an, gibt die id des Eintrags zurück
+        method add_to_queue(mut cx) {
+            if let Ok(arg0) = cx.argument::<JsString>(0) {
+                let id = with_player(&mut cx, |p| p.add_to_queue(arg0.value()));
+                return Ok(cx.number(id).upcast());
+            }
+            Ok(cx.null().upcast())
         }
-    }
 
-    // Dateiname des nächsten Songs in der Playlist
-    fn next(&self) -> Option<String> { 
-        self.play_queue.front().cloned() 
-    }
+        // spiele Datei nach dem aktuellen Titel, ohne ihn zu unterbrechen
+        method play_next(mut cx) {
+            if let Ok(arg0) = cx.argument::<JsString>(0) {
+                let id = with_player(&mut cx, |p| p.play_next(arg0.value()));
+                return Ok(cx.number(id).upcast());
+            }
+            Ok(cx.null().upcast())
+        }
 
-    // entfernt das erste Element der Playlist und setzt den aktuellen Titel
-    fn advance(&mut self, info: Option<(String, Tags, AbortHandle)>) {
-        let _ = self.play_queue.pop_front();
+        // spiele Datei sofort, der aktuelle Titel folgt danach von vorne
+        method play_now(mut cx) {
+            if let Ok(arg0) = cx.argument::<JsString>(0) {
+                let id = with_player(&mut cx, |p| p.play_now(arg0.value()));
+                return Ok(cx.number(id).upcast());
+            }
+            Ok(cx.null().upcast())
+        }
 
-        if let Some((l, _, _)) = &self.curr {
-            self.played_list.push(l.clone()) 
+        // entferne einen Eintrag der Playlist (über dessen id), gibt
+        // false zurück, falls es den Eintrag nicht gibt
+        method remove(mut cx) {
+            let id = cx.argument::<JsNumber>(0)?.value() as u32;
+            let res = with_player(&mut cx, |p| p.remove(id));
+            Ok(cx.boolean(res).upcast())
         }
-        self.curr = info;
-        self.changed = true;
-    }
 
-    // gibt an, ob sich seit dem letzten Aufruf informationen verändert haben
-    fn changed (&mut self) -> bool {
-        let c = self.changed;
-        self.changed = false;
-        c
-    }
+        // verschiebe einen Eintrag der Playlist (über dessen id) an Position
+        // to (der aktuelle Titel wird dabei nicht abgebrochen)
+        method move_track(mut cx) {
+            let id = cx.argument::<JsNumber>(0)?.value() as u32;
+            let to = cx.argument::<JsNumber>(1)?.value() as usize;
+            let res = with_player(&mut cx, |p| p.move_track(id, to));
+            Ok(cx.boolean(res).upcast())
+        }
 
-    // hängt den momentanen und zuletzt gespielten Titel an die playlist an
-    // bricht momentane Wiedergabe ab
-    fn go_back(&mut self) {
-        if let Some((curr, _, _)) = self.curr.clone() {
-            self.play_queue.push_front(curr);
+        // füge eine Datei an einer Position der Playlist ein, gibt die
+        // id des Eintrags zurück, null, falls es die Position nicht gibt
+        method insert(mut cx) {
+            let index = cx.argument::<JsNumber>(0)?.value() as usize;
+            let path = cx.argument::<JsString>(1)?.value();
+            let res = with_player(&mut cx, |p| p.insert(index, path));
 
-            if let Some(last) = self.played_list.pop().clone() {
-                self.play_queue.push_front(last);
+            match res {
+                Some(id) => Ok(cx.number(id).upcast()),
+                None => Ok(cx.null().upcast())
             }
-
-            self.abort_curr();
-
-            self.curr = None;
         }
-    }
 
-    // gibt den Dateinamen des aktuellen Titels an
-    fn curr_playing(&self) -> Option<String> {
-        self.curr.as_ref().map(|(t, _, _)| t.clone())
-    }
+        // leere die Playlist (beendet die Wiedergabe)
+        method clear(mut cx) {
+            with_player(&mut cx, |p| p.clear());
+            Ok(cx.null().upcast())
+        }
 
-    // gibt die Informationen (Künstler, Album, Titel) des aktuellen Titels an
-    fn curr_tags(&self) -> Option<Tags> {
-        self.curr.as_ref().map(|(_, t, _)| t.clone())
-    }
+        // entferne alle Titel nach dem aktuellen
+        method clear_upcoming(mut cx) {
+            with_player(&mut cx, |p| p.clear_upcoming());
+            Ok(cx.null().upcast())
+        }
 
-    // gibt die Position des aktuellen Titels an
-    fn curr_id(&self) -> u32 {
-        self.played_list.len() as u32
-    }
+        // entferne doppelte Einträge, gibt deren Anzahl zurück
+        method dedupe(mut cx) {
+            let n = with_player(&mut cx, |p| p.dedupe());
+            Ok(cx.number(n as f64).upcast())
+        }
 
-    // gibt die Informationen aller Titel in der Playlist an
-    fn tags(&self) -> Vec<Tags> {
-        let mut res = Vec::new();
+        // importiere eine Playlist (M3U, PLS oder XSPF), gibt die ids der Einträge zurück
+        method import_playlist(mut cx) {
+            if let Ok(arg0) = cx.argument::<JsString>(0) {
+                let res = with_player(&mut cx, |p| p.import_playlist(arg0.value().as_str()));
+                let ids = or_throw(&mut cx, res)?;
 
-        for f in self.played_list.iter() {
-            if let Some(file) = AudioFile::open(f.as_str()) {
-                res.push(file.tags());
+                let array = cx.empty_array();
+                for (i, id) in ids.into_iter().enumerate() {
+                    let id = cx.number(id);
+                    array.set(&mut cx, i as u32, id)?;
+                }
+                return Ok(array.upcast());
             }
+            Ok(cx.null().upcast())
         }
-        if let Some(tags) = self.curr_tags() {
-            res.push(tags);
+
+        // exportiere die Playlist: export_playlist(path, format, relative), das
+        // Format ("m3u", "pls" oder "xspf") ergibt sich sonst aus der Endung,
+        // mit relative = true werden Pfade relativ zur Playlist geschrieben
+        method export_playlist(mut cx) {
+            let path = cx.argument::<JsString>(0)?.value();
+            let name = cx.argument_opt(1)
+                .and_then(|f| f.downcast::<JsString>().ok())
+                .map(|f| f.value());
+            let relative = cx.argument_opt(2)
+                .and_then(|r| r.downcast::<JsBoolean>().ok())
+                .map(|r| r.value())
+                .unwrap_or(false);
+
+            let format = match &name {
+                Some(name) => PlaylistFormat::from_name(name),
+                None => Some(PlaylistFormat::from_path(&path).unwrap_or(PlaylistFormat::M3u))
+            };
+            let format = match format {
+                Some(format) => format,
+                None => return cx.throw_error(format!("unknown playlist format \"{}\"", name.unwrap_or_default()))
+            };
+
+            let res = with_player(&mut cx, |p| p.export_playlist(&path, format, relative));
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
         }
-        for f in self.play_queue.iter() {
-            if let Some(file) = AudioFile::open(f.as_str()) {
-                res.push(file.tags());
-            }
+
+        // speichere die Sitzung (Playlist, Position, Einstellungen) in einer Datei
+        method save_session(mut cx) {
+            let path = cx.argument::<JsString>(0)?.value();
+            let res = with_player(&mut cx, |p| p.save_session(path.as_str()));
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
         }
 
-        res
-    }
+        // lade eine gespeicherte Sitzung, die Wiedergabe ist danach pausiert
+        method load_session(mut cx) {
+            let path = cx.argument::<JsString>(0)?.value();
+            let res = with_player(&mut cx, |p| p.load_session(path.as_str()));
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
+        }
 
-    // hängt eine Datei an die Playlist an
-    fn add_to_queue(&mut self, title: String) {
-        self.play_queue.push_back(title);
-    }
+        // speichere die Sitzung regelmäßig und beim Beenden in einer
+        // Datei, ohne Argument (oder mit null) nicht mehr
+        method set_autosave(mut cx) {
+            let path = cx.argument_opt(0)
+                .and_then(|p| p.downcast::<JsString>().ok())
+                .map(|p| p.value());
 
-    // hängt eine Datei vorne an die Playlist an
-    fn add_next(&mut self, title: String) {
-        if let Some((p, _, _)) = &self.curr {
-            self.play_queue.push_front(p.clone());
+            with_player(&mut cx, |p| p.set_autosave(path));
+            Ok(cx.null().upcast())
         }
-        self.play_queue.push_front(title);
-        self.abort_curr();
-        self.curr = None;
-    }
 
-    // entfernt den aktuellen Titel aus der Playlist
-    fn rm_curr (&mut self) {
-        self.played_list.pop();
-    }
+        // springe zu einer Position (in Sekunden) im aktuellen Titel
+        method seek(mut cx) {
+            if let Ok(arg) = cx.argument::<JsNumber>(0) {
+                with_player(&mut cx, |p| p.seek(arg.value()));
+            }
+            Ok(cx.null().upcast())
+        }
 
-    // springt zu einer beliebigen Position in der Playlist
-    fn skip_to(&mut self, id: u32) {
-        let mut curr = self.curr_id();
+        // gib die Position (in Sekunden) im aktuellen Titel zurück
+        method position(mut cx) {
+            let p = with_player(&mut cx, |p| p.position());
+            Ok(cx.number(p).upcast())
+        }
 
-        let total_count = 
-            self.played_list.len() 
-            + self.play_queue.len() 
-            + if self.curr.is_some() {1} else {0};
+        // gib die Länge (in Sekunden) des aktuellen Titels zurück
+        method duration(mut cx) {
+            let d = with_player(&mut cx, |p| p.duration());
+            Ok(cx.number(d).upcast())
+        }
 
-        if curr == id { return; }
-        if id as usize > total_count { return; }
+        // lege Länge (in Sekunden) und Verlauf der Überblendung fest,
+        // der Verlauf ist "linear" oder "equal_power" (Standard)
+        method set_crossfade(mut cx) {
+            let curve = cx.argument_opt(1)
+                .and_then(|c| c.downcast::<JsString>().ok())
+                .and_then(|c| Curve::from_name(c.value().as_str()))
+                .unwrap_or(Curve::EqualPower);
 
-        // es muss rückwärts gesprungen werden
-        if curr > id {
-            // aktuellen Titel entfernen
-            if let Some((t, _, _)) = &self.curr {
-                self.play_queue.push_front(t.clone());
-                curr -= 1;
-            }
-            // weiter zurück, bis zum gewünschten Titel
-            for _ in id .. curr+1 {
-                self.play_queue.push_front(self.played_list.pop().unwrap());
+            if let Ok(arg) = cx.argument::<JsNumber>(0) {
+                with_player(&mut cx, |p| p.set_crossfade(arg.value() as f32, curve));
             }
-        } 
-        else {
-            // aktuellen Titel entfernen
-            if let Some((t, _, _)) = &self.curr {
-                self.played_list.push(t.clone());
-                curr += 1;
+            Ok(cx.null().upcast())
+        }
+
+        // schalte die Überblendung ein oder aus
+        method enable_crossfade(mut cx) {
+            if let Ok(arg) = cx.argument::<JsBoolean>(0) {
+                with_player(&mut cx, |p| p.enable_crossfade(arg.value()));
             }
-            // weiter bis zum gewünschten Titel
-            for _ in curr .. id {
-                self.played_list.push(self.play_queue.pop_front().unwrap());
+            Ok(cx.null().upcast())
+        }
+
+        // setze die Lautstärke (zwischen 0 und 1)
+        method set_volume(mut cx) {
+            if let Ok(arg) = cx.argument::<JsNumber>(0) {
+                with_player(&mut cx, |p| p.set_volume(arg.value() as f32));
             }
+            Ok(cx.null().upcast())
         }
 
-        // momentanen Song abbrechen
-        if let Some((_, _, handle)) = &self.curr {
-            handle.abort();
+        // gib die Lautstärke (zwischen 0 und 1) zurück
+        method get_volume(mut cx) {
+            let v = with_player(&mut cx, |p| p.volume());
+            Ok(cx.number(v).upcast())
         }
 
-        if let Some(player) = &self.player {
-            player.clear_buffer();
+        // schalte stumm (true) oder wieder laut (false)
+        method mute(mut cx) {
+            if let Ok(arg) = cx.argument::<JsBoolean>(0) {
+                with_player(&mut cx, |p| p.set_muted(arg.value()));
+            }
+            Ok(cx.null().upcast())
         }
 
-        self.curr = None;
-    }
-}
+        // lege die Lautstärkeangleichung fest: Modus ("off", "track" oder
+        // "album"), zusätzliche Verstärkung in dB (Standard 0) und ob
+        // Übersteuerung anhand des höchsten Pegels vermieden wird (Standard true)
+        method set_replay_gain(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let mode = match GainMode::from_name(name.as_str()) {
+                Some(mode) => mode,
+                None => return cx.throw_error(format!("unknown replay gain mode \"{}\"", name))
+            };
+
+            let preamp = cx.argument_opt(1)
+                .and_then(|p| p.downcast::<JsNumber>().ok())
+                .map(|p| p.value() as f32)
+                .unwrap_or(0.0);
+
+            let prevent_clipping = cx.argument_opt(2)
+                .and_then(|c| c.downcast::<JsBoolean>().ok())
+                .map(|c| c.value())
+                .unwrap_or(true);
+
+            with_player(&mut cx, |p| p.set_replay_gain(mode, preamp, prevent_clipping));
+            Ok(cx.null().upcast())
+        }
 
-//+--------------------------------
-//| struct CpalPlayer<'a>
-//|     - diese Struktur handhabt
-//|       die Audio Hardware
-
-struct CpalPlayer<'a> {
-    // handle für die Kontrolle des Audio-Threads
-    event_loop: Arc<cpal::EventLoop>,
-    // id des Audio-Streaams
-    stream_id: cpal::StreamId,
-    // gibt an ob gerade Musik abgespielt wird
-    playing: bool,
-    // Kommunikation mit dem Audio-Thread (bei Abbruch)
-    channel: Sender<()>,
-    // enthält keine Daten, nur für das Rust-Typensystem vorhanden
-    phantom: PhantomData<&'a ()>
-}
+        // setze die Wiederholung ("off", "one" oder "all")
+        method set_repeat(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let mode = match RepeatMode::from_name(name.as_str()) {
+                Some(mode) => mode,
+                None => return cx.throw_error(format!("unknown repeat mode \"{}\"", name))
+            };
 
-// Ausgabe von Fehlermeldungen auf der Konsole
-fn stringify<T: Debug>(x: T) -> String { format!("Error - Debug: {:?}", x) }
-
-impl CpalPlayer<'_> {
-    // erzeugt einen neuen Audio-Player
-    fn new<'a>(
-        sample_rate: u32, 
-        mut cons: ringbuf::Consumer<f32>,
-        shared_waker: Arc<AtomicWaker>
-    ) -> Option<CpalPlayer<'a>>
-    {
-        // Standard-Gerät vom System erfragen
-        let host = cpal::default_host();
-        let event_loop = Arc::new(host.event_loop());
-        let device = host.default_output_device()?;
-
-        // festlegen des Sampleformats
-        let format = Format {
-            channels: 2,
-            sample_rate: cpal::SampleRate(sample_rate),
-            data_type: cpal::SampleFormat::F32
-        };
-
-        // Kommunikations-Kanal zum Abbruch von Dateien
-        let (send, recv) = channel();
-
-        // Audio-Stream erstellen
-        let stream_id = event_loop.build_output_stream(&device, &format).ok()?;
-        event_loop.play_stream(stream_id.clone()).unwrap();
-
-        // Kopie des event_loop für Verschiebung in den Audio-Thread
-        let event_loop_copy = event_loop.clone();
-        
-        // erstellen des Audio-Threads
-        // alle Variablen, die innerhalb des Threads
-        // verwendet werden, werden von Rust in den Thread
-        // verschoben und sind von außen nicht mehr zugreifbar
-        thread::spawn(move || {
-            event_loop_copy.run(move |stream_id, stream_result| {
-                if cons.is_empty() {
-                    // zur entlastung des Prozessors 200 ms warten
-                    sleep(Duration::from_millis(200));
-                }
+            with_player(&mut cx, |p| p.set_repeat(mode));
+            Ok(cx.null().upcast())
+        }
 
-                // audio buffer aus dem Argument erhalten
-                let stream_data = match stream_result {
-                    Ok(data) => data,
-                    Err(err) => {
-                        eprintln!("an error occurred on stream {:?}: {}", stream_id, err);
-                        return;
-                    }
-                };
-
-                // falls ein Abbruch erfragt wurde
-                if recv.try_recv().is_ok() {
-                    // ringbuffer leeren
-                    cons.pop_each(|_| true, None);
-                }
+        // gib die Wiederholung zurück
+        method get_repeat(mut cx) {
+            let mode = with_player(&mut cx, |p| p.repeat());
+            Ok(cx.string(mode.name()).upcast())
+        }
 
-                match stream_data {
-                    StreamData::Output { buffer: UnknownTypeOutputBuffer::F32(mut buffer) } => {
-                        // schreibt den inhalt des ringbuffers (cons) in den buffer
-                        for elem in buffer.iter_mut() {
-                            *elem = cons.pop().unwrap_or(0.0);
-                        }
-
-                        // dem Erzeuger der Daten signalisieren, dass
-                        // weitere Daten in den Buffer geschrieben werden können
-                        shared_waker.wake();
-                    },
-                    _ => (),
-                }
-            });
-        });
-    
-        Some(CpalPlayer {
-            event_loop: event_loop,
-            stream_id: stream_id,
-            playing: true,
-            channel: send,
-            phantom: PhantomData
-        })
-    }
+        // setze die zufällige Wiedergabe ("off", "track" oder "album")
+        method set_shuffle(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let mode = match ShuffleMode::from_name(name.as_str()) {
+                Some(mode) => mode,
+                None => return cx.throw_error(format!("unknown shuffle mode \"{}\"", name))
+            };
 
-    // Audio-Thread über Abbruch benachrichtigen
-    fn clear_buffer(&self) {
-        self.channel.send(()).unwrap();
-    }
+            with_player(&mut cx, |p| p.set_shuffle(mode));
+            Ok(cx.null().upcast())
+        }
 
-    // Audio-Thread aufwecken
-    fn play(&mut self) -> Result<(), String> {
-        self.playing = true;
-        self.event_loop.play_stream(self.stream_id.clone()).map_err(stringify)
-    }
+        // gib die zufällige Wiedergabe zurück
+        method get_shuffle(mut cx) {
+            let mode = with_player(&mut cx, |p| p.shuffle());
+            Ok(cx.string(mode.name()).upcast())
+        }
 
-    // Audio-Thread pausieren
-    fn pause(&mut self) -> Result<(), String> {
-        self.playing = false;
-        self.event_loop.pause_stream(self.stream_id.clone()).map_err(stringify)
-    }
-}
+        // gibt alle Audio-Geräte zurück, das Gerät
+        // dieses Players ist als aktiv markiert
+        method list_devices(mut cx) {
+            let current = with_player(&mut cx, |p| p.device());
+            let devices = devices_to_js(&mut cx, current)?;
+            Ok(devices.upcast())
+        }
 
-// erzeugt eine globale Variable STATE,
-// diese ist aus allen Threads zugreifbar
-lazy_static! { 
-    static ref STATE: Arc<Mutex<PlayerState<'static>>> = {
-        Arc::new(Mutex::new(PlayerState::new()))
-    }; 
-}
+        // wechselt das Audio-Gerät, ohne Namen wird das Standard-Gerät verwendet
+        method set_device(mut cx) {
+            let name = cx.argument_opt(0)
+                .and_then(|n| n.downcast::<JsString>().ok())
+                .map(|n| n.value());
 
-// implementiere die Schnittstelle (trait) ReaderTarget<T>
-// für ringbuf::Producer<T> (aus ringbuf)
-// siehe audio_reader/buffered_reader.rs
-impl<T: Send> ReaderTarget<T> for ringbuf::Producer<T> {
-    fn read_iter <I: Iterator<Item=T>> (&mut self, iter: &mut I) -> usize {
-        self.push_iter(iter)
-    }
+            let res = with_player(&mut cx, |p| p.set_device(name.as_deref()));
+            or_throw(&mut cx, res)?;
+            Ok(cx.null().upcast())
+        }
 
-    fn is_full (&self) -> bool {
-        self.is_full()
-    }
-}
+        // gib den Dateipfad der aktuellen Datei zurück
+        method curr_playing(mut cx) {
+            let p = with_player(&mut cx, |p| p.curr_playing());
 
-// diese Funktion erzeugt einen Thread, der
-// mit STATE interagiert um nach und nach die
-// Dateien in der PlayList in den Ringbuffer
-// zu schreiben (prod: Producer<f32>)
-fn spawn_file_reader (prod: Producer<f32>, sample_rate: u32, shared_waker: Arc<AtomicWaker>) {
-    thread::spawn(move || {
-        // speichere den RingBuffer innerhalb einer
-        // Thread-sicheren Datenstruktur
-        let prod = Arc::new(Mutex::new(prod));
-        loop {
-            // zugriff auf den globalen Zustand erhalten
-            let mut guard = STATE.lock().unwrap();
-            // nächste Date erfragen
-            let next = guard.next();
-
-            // Falls keine Datei in der Playlist
-            if next.is_none() {
-                std::mem::drop(guard);
-                // auf anhängen neuer Datei warten
-                sleep(Duration::from_millis(100));
-                continue;
+            match p {
+                Some(path) => Ok(cx.string(path).upcast()),
+                None => Ok(cx.null().upcast())
             }
+        }
 
-            // Datei öffnen (s. audio_reader/mod.rs)
-            let file_name = next.unwrap();
-            let opened = AudioFile::open(file_name.as_str());
+        // gibt Informationen über den momentanen Titel zurück
+        method curr_tag(mut cx) {
+            let t = with_player(&mut cx, |p| p.curr_tags());
 
-            // Falls Datei nicht geöffnet werden kann
-            if opened.is_none() {
-                // Datei aus Playlist entfernen
-                guard.rm_curr();
-                continue;
+            match t {
+                Some(t) => Ok(tag_to_js(&mut cx, t).upcast()),
+                None => Ok(cx.null().upcast())
             }
-
-            let mut file = opened.unwrap();
-            // Tags aus der Datei lesen
-            let tags = file.tags();
-
-            // Datei-Leser konstruieren (s. audio_reader/buffered_reader.rs)
-            let reader = BufferedReader::new(prod.clone(), shared_waker.clone());
-            // asynchronen Lesevorgang beginnen (s. audio_reader/mod.rs)
-            let future = resample_read(&mut file, reader, sample_rate);
-
-            // einbetten des Lesevorgangs in einen abbrechbaren Vorgang
-            let (abort_handle, abort_reg) = AbortHandle::new_pair();
-            let future = Abortable::new(future, abort_reg);
-            
-            // aktualisieren des globalen Zustands
-            guard.advance(Some((file_name.clone(), tags, abort_handle)));
-            drop(guard);
-
-            // für Debugging-Zwecke Dateinamen auf der Konsole ausgeben
-            println!("playing {}", file_name);
-            let _ = block_on(future);
         }
-    });
-}
-
-//+------------------------------------------------------------------------------
-//| JavaScript-Interface-Functions
-//|     - Die Folgenden Funktionen können aus JavaScript aufgerufen werden.
-//|       Sie sind die Schnittstelle des Rustmoduls zum Benutzer.
 
-// startet die Threads - initialisiert das Rust-Modul
-fn init(mut cx: FunctionContext) -> JsResult<JsNull> {
-    let mut state = STATE.lock().unwrap();
+        // gib die id des aktuellen Eintrags zurück (null ohne Titel)
+        method curr_id(mut cx) {
+            let id = with_player(&mut cx, |p| p.curr_id());
 
-    // tue nichts, falls bereits initialisiert
-    if state.player.is_some() { return Ok(cx.null()); }
-
-    // Erstelle den Ringbuffer
-    let sample_rate = 48000;
-    let buffer_size = sample_rate as usize * 4;
-    let auddiobuf = RingBuffer::<f32>::new(buffer_size);
-    let (prod, cons) = auddiobuf.split();
-
-    // Erstelle Waker zur benachrichtung des BufferedReader,
-    // wenn wieder Daten geschrieben werden können
-    let shared_waker = Arc::new(AtomicWaker::new());
-
-    // Starte den Datei-Thread
-    spawn_file_reader(prod, sample_rate, shared_waker.clone());
-
-    // Initialisiere den Player
-    let mut player = CpalPlayer::new(sample_rate, cons, shared_waker).expect("cannot open player");
-    player.pause().unwrap();
+            match id {
+                Some(id) => Ok(cx.number(id).upcast()),
+                None => Ok(cx.null().upcast())
+            }
+        }
 
-    // initialisiere den globalen Zustand
-    state.init(player);
+        // gib die Position der aktuellen Datei zurück
+        method curr_index(mut cx) {
+            let index = with_player(&mut cx, |p| p.curr_index());
+            Ok(cx.number(index).upcast())
+        }
 
-    Ok(cx.null())
-}
+        // erzeugt ein JavaScript Array, das Informationen über die Playlist
+        // liefert, jeder Eintrag enthält neben den Tags id und Pfad.
+        // Mit "playback" in der Reihenfolge der Wiedergabe, sonst wie angezeigt
+        method playlist(mut cx) {
+            let order = match cx.argument_opt(0).and_then(|o| o.downcast::<JsString>().ok()) {
+                Some(o) if o.value() == "playback" => PlaylistOrder::Playback,
+                _ => PlaylistOrder::Display
+            };
+            let entries = with_player(&mut cx, |p| p.playlist(order));
+
+            let array = cx.empty_array();
+
+            for (i, (track, tags)) in entries.into_iter().enumerate() {
+                let entry = tag_to_js(&mut cx, tags);
+                let id = cx.number(track.id);
+                let path = cx.string(track.path);
+                entry.set(&mut cx, "id", id).unwrap();
+                entry.set(&mut cx, "path", path).unwrap();
+                array.set(&mut cx, i as u32, entry).unwrap();
+            }
 
-// Setze abspielen fort
-fn play(mut cx: FunctionContext) -> JsResult<JsNull> {
-    let mut state = STATE.lock().unwrap();
-    match &mut state.player {
-        None => init(cx),
-        Some(player) => {
-            player.play().unwrap();
-            Ok(cx.null())
+            Ok(array.upcast())
         }
-    }
-}
 
-// Pausiere das abspielen
-fn pause(mut cx: FunctionContext) -> JsResult<JsNull> {
-    let mut state = STATE.lock().unwrap();
-    match &mut state.player {
-        None => init(cx),
-        Some(player) => {
-            player.pause().unwrap();
-            Ok(cx.null())
+        // gibt an, ob sich informationen seit dem letzten Aufruf geändert haben
+        method changed(mut cx) {
+            let c = with_player(&mut cx, |p| p.changed());
+            Ok(cx.boolean(c).upcast())
         }
-    }
-}
 
-// importiere eine Playlist
-fn import_m3u (mut cx: FunctionContext) -> JsResult<JsNull> {
-    if let Ok(arg0) = cx.argument::<JsString>(0) {
-        // lese Datei
-        let path = arg0.value();
-        let mut reader = EntryReader::open(path.clone()).unwrap();
-        // base: Dateipfad zur Playlist
-        let base = Url::from_file_path(path.as_str()).unwrap();
-
-        // Lies einträge aus der m3u-Datei
-        for entry in reader.entries() {
-            if let Ok(Entry::Path(p)) = entry {
-                let p = base.join((*p).to_str().unwrap()).unwrap().to_file_path().unwrap();
-                STATE.lock().unwrap().add_to_queue(p.to_str().unwrap().to_string());
+        // meldet eine Funktion an, die bei jedem Ereignis (s. events.rs) mit
+        // dessen Namen und einem Objekt mit weiteren Angaben aufgerufen wird.
+        // Die Ereignisse entstehen in anderen Threads, die Funktion wird
+        // über den EventHandler im JavaScript-Thread aufgerufen.
+        method subscribe(mut cx) {
+
+            if let Ok(callback) = cx.argument::<JsFunction>(0) {
+                let this = cx.undefined();
+                let handler = EventHandler::new(&cx, this, callback);
+
+                with_player(&mut cx, |p| p.subscribe(Box::new(move |event: &Event| {
+                    let event = event.clone();
+                    handler.schedule(move |cx: &mut TaskContext| {
+                        let name = cx.string(event.name());
+                        let data = event_to_js(cx, event);
+                        vec![name.upcast::<JsValue>(), data.upcast::<JsValue>()]
+                    });
+                })));
             }
+            Ok(cx.null().upcast())
+        }
+
+        // beendet den Player und gibt das Audio-Gerät frei,
+        // danach haben alle Methoden keine Wirkung mehr
+        method close(mut cx) {
+            with_player(&mut cx, |p| p.close());
+            Ok(cx.null().upcast())
         }
     }
-    Ok(cx.null())
 }
 
-// hänge Datei an die Playlist an
-fn add_pl (mut cx: FunctionContext) -> JsResult<JsNull> {
-    if let Ok(arg0) = cx.argument::<JsString>(0) {
-        STATE.lock().unwrap().add_to_queue(arg0.value());
-    }
-    Ok(cx.null())
+// ruft f mit dem Player auf, dessen Methode aufgerufen wurde
+fn with_player<T, F: FnOnce(&Player) -> T> (cx: &mut MethodContext<JsPlayer>, f: F) -> T {
+    let this = cx.this();
+    let guard = cx.lock();
+    let player = this.borrow(&guard);
+    f(&player)
 }
 
-// spiele Datei als nächstes
-fn add_next (mut cx: FunctionContext) -> JsResult<JsNull> {
-    if let Ok(arg0) = cx.argument::<JsString>(0) {
-        STATE.lock().unwrap().add_next(arg0.value());
+// liest die Einstellungen aus dem Argument von new Player(...)
+fn read_options<'a, C: Context<'a>> (cx: &mut C, obj: Handle<JsObject>) -> NeonResult<Options> {
+    let mut options = Options::new();
+
+    if let Ok(device) = obj.get(cx, "device")?.downcast::<JsString>() {
+        options.device = Some(device.value());
     }
-    Ok(cx.null())
+    if let Ok(rate) = obj.get(cx, "sampleRate")?.downcast::<JsNumber>() {
+        options.sample_rate = Some(rate.value() as u32);
+    }
+    if let Ok(seconds) = obj.get(cx, "bufferSeconds")?.downcast::<JsNumber>() {
+        options.buffer_seconds = seconds.value() as f32;
+    }
+
+    Ok(options)
 }
 
-// brich die aktuelle Datei ab
-fn abort_curr (mut cx: FunctionContext) -> JsResult<JsNull> {
-    STATE.lock().unwrap().abort_curr();
-    Ok(cx.null())
+// gibt einen Fehler als JavaScript-Exception weiter
+fn or_throw<'a, C: Context<'a>, T> (cx: &mut C, res: Result<T>) -> NeonResult<T> {
+    match res {
+        Ok(t) => Ok(t),
+        Err(e) => cx.throw_error(e.to_string())
+    }
 }
 
-// brich ab und spiele die latzte Datei ab
-fn prev (mut cx: FunctionContext) -> JsResult<JsNull> {
-    let mut state = STATE.lock().unwrap();
-    state.go_back();
-    Ok(cx.null())
+// gibt alle Audio-Geräte mit den unterstützten Formaten zurück,
+// ohne einen Player zu erzeugen (z.B. um ein Gerät auszuwählen)
+fn list_devices (mut cx: FunctionContext) -> JsResult<JsArray> {
+    devices_to_js(&mut cx, None)
 }
 
-// springe zu einer beliebigen Datei in der Playlist
-fn skip_to (mut cx: FunctionContext) -> JsResult<JsNull> {
-    let mut state = STATE.lock().unwrap();
+// erzeugt ein JavaScript Array mit allen Audio-Geräten,
+// current ist der Name des Geräts, das gerade verwendet wird
+fn devices_to_js<'a, C: Context<'a>> (cx: &mut C, current: Option<String>) -> JsResult<'a, JsArray> {
+    let devices = or_throw(cx, list_output_devices())?;
 
-    if let Ok(arg) = cx.argument::<JsNumber>(0) {
-        state.skip_to(arg.value() as u32);
-    }
+    let array = cx.empty_array();
 
-    Ok(cx.null())
-}
+    for (i, device) in devices.into_iter().enumerate() {
+        let obj = cx.empty_object();
+
+        let name = cx.string(&device.name);
+        let default = cx.boolean(device.default);
+        let active = cx.boolean(Some(&device.name) == current.as_ref());
+        obj.set(cx, "name", name)?;
+        obj.set(cx, "default", default)?;
+        obj.set(cx, "active", active)?;
+
+        let formats = cx.empty_array();
+        for (j, f) in device.formats.iter().enumerate() {
+            let format = cx.empty_object();
+
+            let channels = cx.number(f.channels);
+            let min_rate = cx.number(f.min_sample_rate.0);
+            let max_rate = cx.number(f.max_sample_rate.0);
+            let sample_format = cx.string(match f.data_type {
+                SampleFormat::I16 => "i16",
+                SampleFormat::U16 => "u16",
+                SampleFormat::F32 => "f32"
+            });
+            format.set(cx, "channels", channels)?;
+            format.set(cx, "min_sample_rate", min_rate)?;
+            format.set(cx, "max_sample_rate", max_rate)?;
+            format.set(cx, "sample_format", sample_format)?;
 
-// gib den Dateipfad der aktuellen Datei zurück
-fn curr_playing (mut cx: FunctionContext) -> JsResult<JsValue> {
-    let p = STATE.lock().unwrap().curr_playing();
+            formats.set(cx, j as u32, format)?;
+        }
+        obj.set(cx, "formats", formats)?;
 
-    if let Some(path) = p {
-        let res = cx.string(path);
-        Ok(res.as_value(&mut cx))
-    } else {
-        let res = cx.null();
-        Ok(res.as_value(&mut cx))
+        array.set(cx, i as u32, obj)?;
     }
-}
-
-// gib die Position der aktuellen Datei zurück
-fn curr_id (mut cx: FunctionContext) -> JsResult<JsNumber> {
-    let s = STATE.lock().unwrap();
 
-    Ok(cx.number(s.curr_id()))
+    Ok(array)
 }
 
 // Hilfsfunktion, konvertiert ein Tags Objekt (s. audio_reader/mod.rs)
@@ -606,8 +554,8 @@ fn tag_to_js<'a, C: Context<'a>> (cx: &mut C, t: Tags) -> Handle<'a, JsObject> {
     let res = cx.empty_object();
 
     // leere Strings als null ansehen
-    let mut str_or_null = |x| {
-        if x == "" {
+    let mut str_or_null = |x: String| {
+        if x.is_empty() {
             let res = cx.null();
             res.as_value(cx)
         } else {
@@ -626,35 +574,42 @@ fn tag_to_js<'a, C: Context<'a>> (cx: &mut C, t: Tags) -> Handle<'a, JsObject> {
     res
 }
 
-// erzeugt ein JavaScript Array, das Informationen über die Playlist liefert
-fn playlist(mut cx: FunctionContext) -> JsResult<JsArray> {
-    let tags = STATE.lock().unwrap().tags();
-
-    let array = cx.empty_array();
-
-    for i in 0 .. tags.len() {
-        let tag = tag_to_js(&mut cx, tags[i].clone());
-        array.set(&mut cx, i as u32, tag).unwrap();
-    }
-
-    Ok(array)
-}
-
-// gibt Informationen über den momentanen Titel zurück
-fn curr_tag (mut cx: FunctionContext) -> JsResult<JsValue> {
-    let state = STATE.lock().unwrap();
+// Hilfsfunktion, konvertiert die Angaben eines Ereignisses
+// (s. events.rs) in ein JavaScript Objekt
+fn event_to_js<'a, C: Context<'a>> (cx: &mut C, event: Event) -> Handle<'a, JsObject> {
+    let res = cx.empty_object();
 
-    if let Some(t) = state.curr_tags() {
-        let res = tag_to_js(&mut cx, t);
-        return Ok(res.as_value(&mut cx));
+    match event {
+        Event::TrackStarted { path, id, tags } => {
+            let path = cx.string(path);
+            let id = cx.number(id);
+            let tag = tag_to_js(cx, tags);
+            res.set(cx, "path", path).unwrap();
+            res.set(cx, "id", id).unwrap();
+            res.set(cx, "tag", tag).unwrap();
+        },
+        Event::TrackEnded { path } => {
+            let path = cx.string(path);
+            res.set(cx, "path", path).unwrap();
+        },
+        Event::PlaybackState { playing } => {
+            let playing = cx.boolean(playing);
+            res.set(cx, "playing", playing).unwrap();
+        },
+        Event::RepeatChanged { mode } => {
+            let mode = cx.string(mode.name());
+            res.set(cx, "mode", mode).unwrap();
+        },
+        Event::ShuffleChanged { mode } => {
+            let mode = cx.string(mode.name());
+            res.set(cx, "mode", mode).unwrap();
+        },
+        Event::Error { message } => {
+            let message = cx.string(message);
+            res.set(cx, "message", message).unwrap();
+        },
+        Event::QueueChanged | Event::EndOfQueue => {}
     }
 
-    let res = cx.null();
-    Ok(res.as_value(&mut cx))
-}
-
-// gibt an, ob sich informationen seit dem letzten Aufruf geändert haben
-fn changed (mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    Ok(cx.boolean(STATE.lock().unwrap().changed()))
+    res
 }
-
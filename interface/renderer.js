@@ -22,32 +22,86 @@ window.addEventListener('load', () => {
             text.hidden ? "info" : "close";
     }
 
-    document.getElementById('add_next').onclick = () => {
+    document.getElementById('play_now').onclick = () => {
         // Öffnen einer Datei
         open_action(f => {
-            // Datei als nächstes abspielen
-            audio.playlist.add_next(f);
-            // Die momentan gespielte Datei ändert sich, 
-            // also aktialisieren
-            schedule_update();
+            // Datei sofort abspielen
+            audio.playlist.play_now(f);
         }); 
     }
+    document.getElementById('play_next').onclick = () => {
+        // Datei öffnen und nach dem aktuellen Titel einfügen
+        open_action(audio.playlist.play_next); 
+    }
     document.getElementById('add_queue').onclick = () => {
         // Datei öffnen und anhängen 
         open_action(audio.playlist.add_to_queue); 
     }
     document.getElementById('add_m3u').onclick = () => {
         // Playlist-datei öffnen und importieren 
-        open_action(audio.playlist.import_m3u); 
+        open_action(audio.playlist.import_playlist); 
+    }
+    document.getElementById('export_playlist').onclick = () => {
+        // Playlist in eine Datei schreiben, das Format
+        // ergibt sich aus der Endung (.m3u, .pls, .xspf)
+        dialog.showSaveDialog({
+            filters: [{ name: 'Playlist', extensions: ['m3u', 'pls', 'xspf'] }]
+        }).then(res => {
+            if (!res.canceled && res.filePath) {
+                try {
+                    audio.playlist.export_playlist(res.filePath);
+                } catch (e) {
+                    console.error(e.message);
+                }
+            }
+        });
+    }
+
+    // Informationen aktualisieren, sobald ein
+    // neuer Titel zu hören ist
+    audio.events.on('track-started', info => {
+        cover.update(info.path);
+        tag.update(info.tag);
+        plSetId(info.id);
+    });
+
+    // Playlist neu aufbauen, sobald sie sich ändert, auch
+    // durch andere Clients (MPD, MPRIS, HTTP-Schnittstelle)
+    audio.events.on('queue-changed', updatePlaylist);
+
+    // Play-Button an den Zustand der Wiedergabe anpassen
+    audio.events.on('playback-state-changed', ({ playing }) => {
+        document.getElementById('play').src = playing ? "icons/pause.svg" : "icons/play.svg";
+        document.getElementById('play').onclick = playing ? pause_action : play_action;
+    });
+
+    // Fehler beim Abspielen auf der Konsole ausgeben
+    audio.events.on('error', ({ message }) => console.error(message));
+
+    // die letzte Sitzung dort fortsetzen, wo sie beendet wurde,
+    // und sie laufend sowie beim Schließen speichern
+    if (fs.existsSync(session_file)) {
+        try {
+            audio.load_session(session_file);
+        } catch (e) {
+            console.error(e.message);
+        }
+    }
+    audio.set_autosave(session_file);
+    window.addEventListener('beforeunload', () => audio.save_session(session_file));
+    updatePlaylist();
+
+    // nach dem Laden ist die Wiedergabe pausiert, track-started
+    // folgt erst beim Abspielen, daher Cover und Tags sofort anzeigen
+    const curr = audio.info.curr_info();
+    if (curr.path) {
+        cover.update(curr.path);
+        tag.update(curr.tag);
     }
 
     // Pausieren der Audio-Wiedergabe, 
     // solange nichts gespielt wird
     audio.control.pause();
-
-    // Beginne in regelmäßigen Abständen die Informationen
-    // zu aktualisieren
-    updateInfo();
 })
 
 // globale Variablen für Verwaltung von 
@@ -161,36 +215,10 @@ function setColor (c) {
         `ol#playlist > li:hover { background: ${rgb(sel)}}`;
 }
 
-// timeout bis zur nächsten aktualisierung
-let info_timeout = null;
-
-function updateInfo(){
-    // aktualisiere nur bei Änderung
-    if (audio.info.changed()) {
-        // info vom Rust-Modul erfragen
-        const info = audio.info.curr_info();
-
-        // info an die Objekte weitergeben
-        cover.update(info.path);
-        tag.update(info.tag);
-        plSetId(info.id);
-    }
-
-    // setze 3-Sekunden-Timeout 
-    // bis zur nächsten Aktualisierung
-    info_timeout = setTimeout(updateInfo, 3000);
-}
-
-// updateInfo vorziehen 
-function schedule_update () {
-    clearTimeout(info_timeout);
-    info_timeout = setTimeout(updateInfo, 100);
-}
-
 // setze das Element auf Fettdruck, welches gerade abgespielt wird
 function plSetId (id) {
-    document.getElementById("playlist").childNodes.forEach((x, i) => {
-        if (i == id) x.firstChild.style.fontWeight = "bold";
+    document.getElementById("playlist").childNodes.forEach(x => {
+        if (x.dataset.id == id) x.firstChild.style.fontWeight = "bold";
         else x.firstChild.style.fontWeight = "normal";
     })
 }
@@ -207,9 +235,10 @@ function updatePlaylist() {
     // sel_id = id des momentan gespielten Songs
     const sel_id = audio.info.curr_info().id;
 
-    playlist.forEach((item, i) => {
+    playlist.forEach(item => {
         // schreibe informationen in ein li Element
         const li = document.createElement("li");
+        li.dataset.id = item.id;
         ["title", "album", "artist"].forEach(x => {
             // jedes Tag bekommt sein eigenes
             // <span class="pl-[tag]">[tag-content]</span>
@@ -221,12 +250,11 @@ function updatePlaylist() {
         
         // bei Click, spiele das jeweilige Element ab
         li.onclick = () => {
-            audio.control.skip_to(i);
-            schedule_update();
+            audio.control.skip_to(item.id);
         }
 
         // das monentan gespielte Element wird fett gedruckt
-        if (i == sel_id) li.firstChild.style.fontWeight = "bold";
+        if (item.id === sel_id) li.firstChild.style.fontWeight = "bold";
         pl_node.appendChild(li);
     });
 
@@ -235,38 +263,32 @@ function updatePlaylist() {
 }
 
 // setze das Abspielen fort
+// (der Button wird über das Ereignis
+// playback-state-changed angepasst)
 function play_action () {
     audio.control.play();
-    document.getElementById('play').src = "icons/pause.svg";
-    document.getElementById('play').onclick = pause_action;
 }
 
 // halte die Musik an
 function pause_action () {
     audio.control.pause();
-    document.getElementById('play').src = "icons/play.svg";
-    document.getElementById('play').onclick = play_action;
 }
 
 // springe einen Titel weiter
 function next_action () {
     audio.control.skip();
-    schedule_update();
 }
 
 // springe einen Titel zurück
 function prev_action () {
     audio.control.prev();
-    schedule_update();
 }
 
-// öffne eine Datei, übergib sie f 
-// und aktualisiere die Playlist
+// öffne eine Datei und übergib sie f
+// (die Playlist wird über das Ereignis
+// queue-changed aktualisiert)
 function open_action (f) {
     dialog.showOpenDialog().then(res => {
-        res.filePaths.forEach(p => {
-            f(p);
-            updatePlaylist();
-        })
+        res.filePaths.forEach(p => f(p));
     });
 }
\ No newline at end of file
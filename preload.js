@@ -6,7 +6,7 @@
 
 const audio = require('./audio_player')
 const fs = require('fs');
-const {dialog} = require('electron').remote;
+const {dialog, app} = require('electron').remote;
 const path = require('path');
 
 process.once('loaded', () => {
@@ -14,4 +14,6 @@ process.once('loaded', () => {
   global.fs = fs;
   global.dialog = dialog;
   global.path = path;
+  // hier wird die Sitzung des Players gespeichert (s. renderer.js)
+  global.session_file = path.join(app.getPath('userData'), 'session');
 })
\ No newline at end of file